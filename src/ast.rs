@@ -1,8 +1,10 @@
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 use crate::token::{SourceLocation, TokenKind};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Statement {
     Module {
         name: Vec<(String, SourceLocation)>,
@@ -22,116 +24,507 @@ pub enum Statement {
         parameters: Vec<Parameter>,
         return_type: Option<Type>,
         binding: (String, SourceLocation),
+        doc: Option<String>,
     },
     Function {
         name: (String, SourceLocation),
         parameters: Vec<Parameter>,
         return_type: Option<Type>,
         body: Expression,
+        doc: Option<String>,
     },
     GenericFunction {
         name: (String, SourceLocation),
         generic_parameters: Vec<Type>,
+        constraints: Vec<Constraint>,
         parameters: Vec<Parameter>,
         return_type: Option<Type>,
         body: Expression,
+        doc: Option<String>,
     },
     Enum {
         name: (String, SourceLocation),
         variants: Vec<EnumVariant>,
+        doc: Option<String>,
     },
     GenericEnum {
         name: (String, SourceLocation),
         generic_parameters: Vec<Type>,
         variants: Vec<EnumVariant>,
+        doc: Option<String>,
+    },
+    Class {
+        name: (String, SourceLocation),
+        generic_parameter: (String, SourceLocation),
+        methods: Vec<ClassMethod>,
+    },
+    Instance {
+        class: (String, SourceLocation),
+        type_: Type,
+        methods: Vec<Statement>,
+    },
+    TypeAlias {
+        name: (String, SourceLocation),
+        generic_parameters: Vec<Type>,
+        type_: Type,
+    },
+    Newtype {
+        name: (String, SourceLocation),
+        type_: Type,
+    },
+    /// A `test "name" = <expression>` declaration, only compiled and run by
+    /// `harmony test`.
+    Test {
+        name: (String, SourceLocation),
+        body: Expression,
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expression {
     Binary {
         left: Box<Expression>,
         operator: TokenKind,
+        operator_location: SourceLocation,
         right: Box<Expression>,
     },
     Unary {
         operator: TokenKind,
         right: Box<Expression>,
+        location: SourceLocation,
     },
     Call {
         callee: (String, SourceLocation),
         generic_arguments: Vec<Type>,
         arguments: Vec<Expression>,
+        argument_names: Vec<Option<(String, SourceLocation)>>,
+        location: SourceLocation,
     },
     Identifier(String, SourceLocation),
     Integer(i64, SourceLocation),
     Float(f64, SourceLocation),
     String(String, SourceLocation),
+    InterpolatedString(Vec<StringPart>, SourceLocation),
     Bool(bool, SourceLocation),
     Char(String, SourceLocation),
     PatternMatch {
         expression: Box<Expression>,
         cases: Vec<PatternMatchCase>,
         default_case: Option<Box<Expression>>,
+        location: SourceLocation,
     },
-    List(Vec<Expression>),
+    List(Vec<Expression>, SourceLocation),
     If {
         condition: Box<Expression>,
         then_branch: Box<Expression>,
         else_branch: Box<Expression>,
+        location: SourceLocation,
     },
     Access {
         name: (String, SourceLocation),
         member: Box<Expression>,
     },
-    Rest(Box<Expression>),
+    Rest(Box<Expression>, SourceLocation),
     Index {
         expression: Box<Expression>,
         index: Box<Expression>,
+        location: SourceLocation,
     },
     Let {
         name: (String, SourceLocation),
         type_annotation: Option<Type>,
         value: Box<Expression>,
         body: Box<Expression>,
+        location: SourceLocation,
     },
     Function {
         parameters: Vec<Parameter>,
         return_type: Option<Type>,
         body: Box<Expression>,
+        location: SourceLocation,
+    },
+    Do {
+        statements: Vec<DoStatement>,
+        location: SourceLocation,
     },
 }
 
 impl Expression {
+    /// Where the whole expression is, from its first token to its last, not
+    /// counting the parentheses around it.
     pub fn location(&self) -> SourceLocation {
         match self {
             Expression::Binary { left, right, .. } => left.location().merge(&right.location()),
-            Expression::Unary { right, .. } => right.location(),
-            Expression::Call { callee, .. } => callee.1.clone(),
-            Expression::Identifier(_, location) => location.clone(),
-            Expression::Integer(_, location) => location.clone(),
-            Expression::Float(_, location) => location.clone(),
-            Expression::String(_, location) => location.clone(),
-            Expression::Bool(_, location) => location.clone(),
-            Expression::Char(_, location) => location.clone(),
-            Expression::PatternMatch { expression, .. } => expression.location(),
-            Expression::List(expressions) => expressions
-                .first()
-                .map(|expression| expression.location())
-                .unwrap_or(SourceLocation::default()),
-            Expression::If { condition, .. } => condition.location(),
             Expression::Access { name, member } => name.1.merge(&member.location()),
-            Expression::Rest(expression) => expression.location(),
-            Expression::Index { expression, index } => {
-                expression.location().merge(&index.location())
+            Expression::Identifier(_, location)
+            | Expression::Integer(_, location)
+            | Expression::Float(_, location)
+            | Expression::String(_, location)
+            | Expression::InterpolatedString(_, location)
+            | Expression::Bool(_, location)
+            | Expression::Char(_, location)
+            | Expression::List(_, location)
+            | Expression::Rest(_, location)
+            | Expression::Unary { location, .. }
+            | Expression::Call { location, .. }
+            | Expression::PatternMatch { location, .. }
+            | Expression::If { location, .. }
+            | Expression::Index { location, .. }
+            | Expression::Let { location, .. }
+            | Expression::Function { location, .. }
+            | Expression::Do { location, .. } => location.clone(),
+        }
+    }
+
+    /// Whether the expression has to be wrapped in parentheses to parse back
+    /// the same when written at `position`, going by the precedence and
+    /// associativity of the operators around it.
+    pub fn needs_parentheses(&self, position: &Position) -> bool {
+        match (position, self) {
+            (
+                Position::Left(operator),
+                Expression::Binary {
+                    operator: inner, ..
+                },
+            ) if operator.precedence() >= right_precedence(inner) => true,
+            (Position::Left(_), _) => self.is_open(),
+            (
+                Position::Right(operator),
+                Expression::Binary {
+                    operator: inner, ..
+                },
+            ) => inner.precedence() < right_precedence(operator),
+            (Position::Right(_), _) => false,
+            (Position::Unary, expression) => matches!(expression, Expression::Binary { .. }),
+            (Position::Indexed, Expression::Binary { .. } | Expression::Unary { .. }) => true,
+            (Position::Indexed, _) => self.is_open(),
+            (Position::Member, expression) => matches!(
+                expression,
+                Expression::Binary { .. }
+                    | Expression::Unary { .. }
+                    | Expression::Index { .. }
+                    | Expression::Rest(_, _)
+            ),
+        }
+    }
+
+    /// Whether the expression ends with one that takes in everything after
+    /// it, like the else branch of an `if` does, so that it needs parentheses
+    /// when an operator or index follows it.
+    fn is_open(&self) -> bool {
+        match self {
+            Expression::If { .. }
+            | Expression::Let { .. }
+            | Expression::Function { .. }
+            | Expression::Rest(_, _) => true,
+            Expression::Binary {
+                operator, right, ..
+            } => !right.needs_parentheses(&Position::Right(operator.clone())) && right.is_open(),
+            Expression::Unary { right, .. } => {
+                !right.needs_parentheses(&Position::Unary) && right.is_open()
+            }
+            Expression::Access { member, .. } => {
+                !member.needs_parentheses(&Position::Member) && member.is_open()
             }
-            Expression::Let { name, body, .. } => name.1.merge(&body.location()),
-            Expression::Function { body, .. } => body.location(),
+            _ => false,
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// Where an expression is written inside another one, which decides whether
+/// it needs parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Position {
+    /// The left operand of a binary operator.
+    Left(TokenKind),
+    /// The right operand of a binary operator.
+    Right(TokenKind),
+    /// The operand of a unary operator.
+    Unary,
+    /// The expression before `[index]`.
+    Indexed,
+    /// The member after `name.`.
+    Member,
+}
+
+/// Prints a statement as Harmony source that parses back to the same
+/// statement, apart from locations. Doc comments are printed above the
+/// declarations they document.
+impl Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Module { name, exposing } => {
+                write!(f, "module {}{}", dotted(name), exposing_list(exposing))
+            }
+            Statement::Import {
+                name,
+                alias,
+                exposing,
+            } => {
+                write!(f, "import {}", dotted(name))?;
+                if let Some((alias, _)) = alias {
+                    write!(f, " as {}", alias)?;
+                }
+                write!(f, "{}", exposing_list(exposing))
+            }
+            Statement::ForeignImport { name, exposing } => write!(
+                f,
+                "foreign import \"{}\"{}",
+                unescape_backticks(&name.0),
+                exposing_list(exposing)
+            ),
+            Statement::ForeignFunction {
+                name,
+                parameters,
+                return_type,
+                binding,
+                doc,
+            } => write!(
+                f,
+                "{}foreign fun {}{} = \"{}\"",
+                doc_comment(doc),
+                name.0,
+                signature(parameters, return_type),
+                unescape_backticks(&binding.0)
+            ),
+            Statement::Function {
+                name,
+                parameters,
+                return_type,
+                body,
+                doc,
+            } => write!(
+                f,
+                "{}fun {}{} = {}",
+                doc_comment(doc),
+                name.0,
+                signature(parameters, return_type),
+                body
+            ),
+            Statement::GenericFunction {
+                name,
+                generic_parameters,
+                constraints,
+                parameters,
+                return_type,
+                body,
+                doc,
+            } => write!(
+                f,
+                "{}fun {}<{}>{} = {}",
+                doc_comment(doc),
+                name.0,
+                generic_parameter_list(generic_parameters, constraints),
+                signature(parameters, return_type),
+                body
+            ),
+            Statement::Enum {
+                name,
+                variants,
+                doc,
+            } => write!(
+                f,
+                "{}enum {} = {}",
+                doc_comment(doc),
+                name.0,
+                join(variants, " | ")
+            ),
+            Statement::GenericEnum {
+                name,
+                generic_parameters,
+                variants,
+                doc,
+            } => write!(
+                f,
+                "{}enum {}<{}> = {}",
+                doc_comment(doc),
+                name.0,
+                generic_parameter_list(generic_parameters, &[]),
+                join(variants, " | ")
+            ),
+            Statement::Class {
+                name,
+                generic_parameter,
+                methods,
+            } => {
+                writeln!(f, "class {}<{}> where", name.0, generic_parameter.0)?;
+                for method in methods {
+                    writeln!(f, "    {}", method)?;
+                }
+                write!(f, "end")
+            }
+            Statement::Instance {
+                class,
+                type_,
+                methods,
+            } => {
+                writeln!(f, "instance {}<{}> where", class.0, type_)?;
+                for method in methods {
+                    // Only the doc comment is indented line by line, as a
+                    // string in the method's body can span lines.
+                    let code: String = method.to_string();
+                    let mut rest: &str = &code;
+                    while let (true, Some((line, after))) =
+                        (rest.starts_with("---"), rest.split_once('\n'))
+                    {
+                        writeln!(f, "    {}", line)?;
+                        rest = after;
+                    }
+                    writeln!(f, "    {}", rest)?;
+                }
+                write!(f, "end")
+            }
+            Statement::TypeAlias {
+                name,
+                generic_parameters,
+                type_,
+            } => {
+                write!(f, "type {}", name.0)?;
+                if !generic_parameters.is_empty() {
+                    write!(f, "<{}>", generic_parameter_list(generic_parameters, &[]))?;
+                }
+                write!(f, " = {}", type_)
+            }
+            Statement::Newtype { name, type_ } => {
+                write!(f, "newtype {} = {}", name.0, type_)
+            }
+            Statement::Test { name, body } => {
+                write!(f, "test \"{}\" = {}", unescape_backticks(&name.0), body)
+            }
+        }
+    }
+}
+
+/// Prints an expression on one line as Harmony source that parses back to
+/// the same expression, apart from locations, with only the parentheses its
+/// operators need. Literals are printed in one canonical spelling, so
+/// `0xFF` comes back as `255`.
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => write!(
+                f,
+                "{} {} {}",
+                operand(left, &Position::Left(operator.clone())),
+                operator,
+                operand(right, &Position::Right(operator.clone()))
+            ),
+            Expression::Unary {
+                operator, right, ..
+            } => {
+                let right: String = operand(right, &Position::Unary);
+                // `--` would start a comment.
+                if *operator == TokenKind::Minus && right.starts_with('-') {
+                    write!(f, "{} {}", operator, right)
+                } else {
+                    write!(f, "{}{}", operator, right)
+                }
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                argument_names,
+                ..
+            } => {
+                let arguments: Vec<String> = arguments
+                    .iter()
+                    .zip(argument_names)
+                    .map(|(argument, name)| match name {
+                        Some((name, _)) => format!("{} = {}", name, argument),
+                        None => argument.to_string(),
+                    })
+                    .collect();
+                write!(f, "{} ({})", callee.0, arguments.join(", "))
+            }
+            Expression::Identifier(name, _) => write!(f, "{}", name),
+            Expression::Integer(value, _) => write!(f, "{}", value),
+            Expression::Float(value, _) => write!(f, "{:?}", value),
+            Expression::String(value, _) => write!(f, "\"{}\"", unescape_backticks(value)),
+            Expression::InterpolatedString(parts, _) => write!(f, "{}", interpolated_string(parts)),
+            Expression::Bool(value, _) => write!(f, "{}", value),
+            Expression::Char(value, _) => {
+                let mut code: String = String::new();
+                let mut characters = value.chars();
+                while let Some(c) = characters.next() {
+                    match c {
+                        '\\' => {
+                            code.push(c);
+                            code.extend(characters.next());
+                        }
+                        '\'' => code.push_str("\\'"),
+                        _ => code.push(c),
+                    }
+                }
+                write!(f, "'{}'", code)
+            }
+            Expression::PatternMatch {
+                expression,
+                cases,
+                default_case,
+                ..
+            } => {
+                write!(f, "case {} of", expression)?;
+                for case in cases {
+                    write!(f, " {}", case)?;
+                }
+                if let Some(default_case) = default_case {
+                    write!(f, " | else => {}", default_case)?;
+                }
+                write!(f, " end")
+            }
+            Expression::List(elements, _) => write!(f, "[{}]", join(elements, ", ")),
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => write!(
+                f,
+                "if {} then {} else {}",
+                condition, then_branch, else_branch
+            ),
+            Expression::Access { name, member } => {
+                write!(f, "{}.{}", name.0, operand(member, &Position::Member))
+            }
+            Expression::Rest(expression, _) => write!(f, "..{}", expression),
+            Expression::Index {
+                expression, index, ..
+            } => {
+                write!(f, "{}[{}]", operand(expression, &Position::Indexed), index)
+            }
+            Expression::Let {
+                name,
+                type_annotation,
+                value,
+                body,
+                ..
+            } => {
+                write!(f, "let {}", name.0)?;
+                if let Some(type_annotation) = type_annotation {
+                    write!(f, ": {}", type_annotation)?;
+                }
+                write!(f, " = {} in {}", value, body)
+            }
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                ..
+            } => write!(f, "fun{} = {}", signature(parameters, return_type), body),
+            Expression::Do { statements, .. } => {
+                let printed: Vec<String> = statements.iter().map(ToString::to_string).collect();
+                write!(f, "do {} end", printed.join("; "))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Type {
     Unit(SourceLocation),
     Int(SourceLocation),
@@ -156,57 +549,29 @@ pub enum Type {
 }
 
 impl Display for Type {
+    /// Prints the type as it is written in source, so messages and generated
+    /// signatures can be pasted back into a program.
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Type::Unit(_) => write!(f, "()"),
+            Type::Unit(_) => write!(f, "unit"),
             Type::Int(_) => write!(f, "int"),
             Type::Float(_) => write!(f, "float"),
             Type::String(_) => write!(f, "string"),
             Type::Bool(_) => write!(f, "bool"),
             Type::Char(_) => write!(f, "char"),
-            Type::Generic(name, _, types) => {
-                let mut types_string = String::new();
-                for type_ in types {
-                    types_string.push_str(&format!("{}, ", type_));
-                }
-                if types.len() > 0 {
-                    types_string.pop();
-                    types_string.pop();
-                }
-                write!(f, "{}<{}>", name, types_string)
-            }
-            Type::GenericParameter(name, _) => write!(f, "{}", name),
-            Type::GenericArgument(name, _) => write!(f, "{}", name),
-            Type::List(type_) => {
-                if let Some(type_) = type_.as_ref() {
-                    write!(f, "[{}]", type_)
-                } else {
-                    write!(f, "[]")
-                }
+            Type::Any(_) => write!(f, "any"),
+            Type::Generic(name, _, types) | Type::GenericEnum(name, _, types) => {
+                write!(f, "{}<{}>", name, join(types, ", "))
             }
+            Type::GenericParameter(name, _)
+            | Type::GenericArgument(name, _)
+            | Type::Enum(name, _)
+            | Type::Identifier(name, _) => write!(f, "{}", name),
+            Type::List(Some(type_)) => write!(f, "[{}]", type_),
+            Type::List(None) => write!(f, "[]"),
             Type::Function(parameters, return_type) => {
-                let mut parameters_string = String::new();
-                for parameter in parameters {
-                    parameters_string.push_str(&format!("{}, ", parameter));
-                }
-                parameters_string.pop();
-                parameters_string.pop();
-                write!(f, "({} -> {})", parameters_string, return_type)
-            }
-            Type::Any(_) => write!(f, "Any"),
-            Type::Enum(name, _) => write!(f, "{}", name),
-            Type::GenericEnum(name, _, types) => {
-                let mut types_string = String::new();
-                for type_ in types {
-                    types_string.push_str(&format!("{}, ", type_));
-                }
-                if types.len() > 0 {
-                    types_string.pop();
-                    types_string.pop();
-                }
-                write!(f, "{}<{}>", name, types_string)
+                write!(f, "fun({}) -> {}", join(parameters, ", "), return_type)
             }
-            Type::Identifier(name, _) => write!(f, "{}", name),
         }
     }
 }
@@ -290,28 +655,303 @@ impl Type {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameter {
     pub name: (String, SourceLocation),
     pub type_: Type,
+    pub default: Option<Expression>,
+}
+
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name.0, self.type_)?;
+        if let Some(default) = &self.default {
+            write!(f, " = {}", default)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constraint {
+    pub parameter: String,
+    pub class: (String, SourceLocation),
+}
+
+impl Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.parameter, self.class.0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClassMethod {
+    pub name: (String, SourceLocation),
+    pub type_: Type,
 }
 
-#[derive(Debug, Clone)]
+impl Display for ClassMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.name.0, self.type_)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PatternMatchCase {
     pub pattern: Expression,
     pub directive: PatternMatchDirective,
     pub body: Expression,
 }
 
-#[derive(Debug, Clone)]
+impl Display for PatternMatchCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "| {}", self.pattern)?;
+        if let PatternMatchDirective::If(condition) = &self.directive {
+            write!(f, " if {}", condition)?;
+        }
+        write!(f, " => {}", self.body)
+    }
+}
+
+/// A piece of an interpolated string. Interpolated expressions keep the
+/// location of the `${` that opens them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StringPart {
+    Literal(String),
+    Expression(Box<Expression>, SourceLocation),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PatternMatchDirective {
     None,
-    If(Expression),
+    If(Box<Expression>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum DoStatement {
+    Bind {
+        name: (String, SourceLocation),
+        value: Expression,
+    },
+    Let {
+        name: (String, SourceLocation),
+        type_annotation: Option<Type>,
+        value: Expression,
+    },
+    Expression(Expression),
 }
 
-#[derive(Debug, Clone)]
+impl Display for DoStatement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DoStatement::Bind { name, value } => write!(f, "{} <- {}", name.0, value),
+            DoStatement::Let {
+                name,
+                type_annotation: Some(type_annotation),
+                value,
+            } => write!(f, "let {}: {} = {}", name.0, type_annotation, value),
+            DoStatement::Let { name, value, .. } => write!(f, "let {} = {}", name.0, value),
+            DoStatement::Expression(expression) => write!(f, "{}", expression),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EnumVariant {
     Unit(String, SourceLocation),
     Tuple(String, SourceLocation, Vec<Type>),
     // Struct(String, SourceLocation, Vec<(String, Type)>),
 }
+
+impl Display for EnumVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnumVariant::Unit(name, _) => write!(f, "{}", name),
+            EnumVariant::Tuple(name, _, types) => write!(f, "{}({})", name, join(types, ", ")),
+        }
+    }
+}
+
+/// Prints `expression` inside another one at `position`, in parentheses if
+/// it needs them there.
+fn operand(expression: &Expression, position: &Position) -> String {
+    if expression.needs_parentheses(position) {
+        format!("({})", expression)
+    } else {
+        expression.to_string()
+    }
+}
+
+/// The lowest precedence an operator in the right operand of `operator` can
+/// have to be parsed as part of it, as the parser's binary expressions do.
+fn right_precedence(operator: &TokenKind) -> u8 {
+    if operator.is_right_associative() {
+        operator.precedence()
+    } else {
+        operator.precedence() + 1
+    }
+}
+
+/// `(parameters) -> return_type` after the name of a function, or nothing
+/// for one declared without them.
+fn signature(parameters: &[Parameter], return_type: &Option<Type>) -> String {
+    match return_type {
+        None if parameters.is_empty() => String::new(),
+        _ => format!(
+            "({}) -> {}",
+            join(parameters, ", "),
+            match return_type {
+                Some(return_type) => return_type.to_string(),
+                None => "unit".to_string(),
+            }
+        ),
+    }
+}
+
+/// The `---` lines of a doc comment, each ending with a newline.
+fn doc_comment(doc: &Option<String>) -> String {
+    let Some(doc) = doc else {
+        return String::new();
+    };
+    doc.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                "---\n".to_string()
+            } else {
+                format!("--- {}\n", line)
+            }
+        })
+        .collect()
+}
+
+/// The literal parts of strings are kept as the source of JavaScript
+/// template literals, where backticks are escaped. Harmony strings have
+/// them unescaped, and the rest of their escapes are the same.
+fn unescape_backticks(value: &str) -> String {
+    let mut code: String = String::new();
+    let mut characters = value.chars();
+    while let Some(c) = characters.next() {
+        match c {
+            '\\' => match characters.next() {
+                Some('`') => code.push('`'),
+                next => {
+                    code.push(c);
+                    code.extend(next);
+                }
+            },
+            _ => code.push(c),
+        }
+    }
+    code
+}
+
+/// Prints an interpolated string as a string that reads back to the same
+/// parts: a raw string when there is nothing to interpolate and it can be
+/// written raw, a triple-quoted one when there is nothing to interpolate or a
+/// part has a quote, and a plain one otherwise. The tokenizer has already
+/// taken the indentation out of triple-quoted strings, so putting it back in
+/// isn't needed.
+fn interpolated_string(parts: &[StringPart]) -> String {
+    if let [StringPart::Literal(value)] = parts {
+        if let Some(raw) = raw_string(value) {
+            return format!("r\"{}\"", raw);
+        }
+    }
+    let interpolates: bool = parts
+        .iter()
+        .any(|part| matches!(part, StringPart::Expression(..)));
+    let mut code: String = String::new();
+    let mut quoted: bool = false;
+    for part in parts {
+        match part {
+            StringPart::Literal(value) => {
+                let value: String = unescape_backticks(value);
+                quoted |= has_quote(&value);
+                code.push_str(&value);
+            }
+            StringPart::Expression(expression, _) => {
+                code.push_str(&format!("${{{}}}", expression));
+            }
+        }
+    }
+    if quoted || !interpolates {
+        format!("\"\"\"\n{}\n\"\"\"", code)
+    } else {
+        format!("\"{}\"", code)
+    }
+}
+
+/// The contents of a raw string with the value `value`, if it has one.
+fn raw_string(value: &str) -> Option<String> {
+    let mut code: String = String::new();
+    let mut characters = value.chars();
+    while let Some(c) = characters.next() {
+        match c {
+            '\\' => match characters.next()? {
+                c @ ('\\' | '$' | '`') => code.push(c),
+                _ => return None,
+            },
+            '"' | '$' => return None,
+            _ => code.push(c),
+        }
+    }
+    Some(code)
+}
+
+/// Whether Harmony string contents have an unescaped `"`.
+fn has_quote(code: &str) -> bool {
+    let mut characters = code.chars();
+    while let Some(c) = characters.next() {
+        match c {
+            '\\' => {
+                characters.next();
+            }
+            '"' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+pub fn generic_parameter_list(parameters: &[Type], constraints: &[Constraint]) -> String {
+    parameters
+        .iter()
+        .map(|parameter| {
+            let parameter: String = parameter.to_string();
+            let classes: Vec<String> = constraints
+                .iter()
+                .filter(|constraint| constraint.parameter == parameter)
+                .map(|constraint| constraint.class.0.clone())
+                .collect();
+            if classes.is_empty() {
+                parameter
+            } else {
+                format!("{}: {}", parameter, classes.join(" + "))
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+pub fn dotted(name: &[(String, SourceLocation)]) -> String {
+    name.iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<String>>()
+        .join(".")
+}
+
+pub fn exposing_list(exposing: &[(String, SourceLocation)]) -> String {
+    if exposing.is_empty() {
+        return String::new();
+    }
+    let names: Vec<&str> = exposing.iter().map(|(name, _)| name.as_str()).collect();
+    format!(" exposing ({})", names.join(", "))
+}
+
+fn join<T: Display>(items: &[T], separator: &str) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
@@ -0,0 +1,223 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    ast::EnumVariant,
+    checker::{Function, Scope},
+    error::HarmonyError,
+    token::SourceLocation,
+};
+
+/// Where the `harmony` command keeps its cache, in the working directory.
+pub const DIRECTORY: &str = ".harmony-cache";
+
+/// What compiling a module produced, saved so it isn't compiled again until
+/// its source or the interface of a module it imports changes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The version of the compiler that compiled the module.
+    pub version: String,
+    /// The hash of the module's source.
+    pub source: u64,
+    pub test: bool,
+    pub dependencies: Vec<Dependency>,
+    /// What the module declares itself, without what it merged in from its
+    /// imports, which `restore` takes from their current scopes instead.
+    pub scope: Scope,
+    pub js: String,
+    pub warnings: Vec<HarmonyError>,
+}
+
+/// A module imported by a cached module, with the hash of its interface when
+/// the module was compiled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dependency {
+    pub name: String,
+    /// Where it's imported, for the errors of an import that changed.
+    pub location: SourceLocation,
+    pub path: String,
+    pub interface: u64,
+}
+
+/// Compiled modules saved between runs, one file per module.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    pub directory: PathBuf,
+}
+
+impl Cache {
+    pub fn new(directory: impl Into<PathBuf>) -> Cache {
+        Cache {
+            directory: directory.into(),
+        }
+    }
+
+    /// The entry saved for the module at `file` by this version of the
+    /// compiler, if its source still hashes to `source`.
+    pub fn load(&self, file: &str, source: u64, test: bool) -> Option<CacheEntry> {
+        let contents: String = std::fs::read_to_string(self.path(file, test)).ok()?;
+        let entry: CacheEntry = serde_json::from_str(&contents).ok()?;
+        if entry.version != env!("CARGO_PKG_VERSION") || entry.source != source {
+            return None;
+        }
+        Some(entry)
+    }
+
+    /// Saves `entry` for the module at `file`. The cache only saves work, so
+    /// failing to write it isn't an error.
+    pub fn save(&self, file: &str, entry: &CacheEntry) {
+        let Ok(contents) = serde_json::to_string(entry) else {
+            return;
+        };
+        if std::fs::create_dir_all(&self.directory).is_ok() {
+            let _ = std::fs::write(self.path(file, entry.test), contents);
+        }
+    }
+
+    fn path(&self, file: &str, test: bool) -> PathBuf {
+        let key: String = if test {
+            format!("{} test", file)
+        } else {
+            file.to_string()
+        };
+        self.directory.join(format!("{:016x}.json", hash(&key)))
+    }
+}
+
+/// The 64-bit FNV-1a hash of `text`, which unlike `std`'s hashers is the
+/// same in every run.
+pub fn hash(text: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The hash of what importing a module brings into scope. Function bodies
+/// are left out, since importers only see their types, and so are locations
+/// and doc comments, so that editing a body doesn't change it. Enums and
+/// functions are keyed by name, since their ids depend on the order in which
+/// the scopes they came from were merged.
+pub fn interface(scope: &Scope) -> u64 {
+    let mut value: Value = serde_json::to_value(scope).unwrap();
+    let mut enums: serde_json::Map<String, Value> = serde_json::Map::new();
+    for (name, id) in &scope.enum_names {
+        let mut enum_: Value = serde_json::to_value(&scope.enums[id]).unwrap();
+        enum_["doc"] = Value::Null;
+        enums.insert(name.clone(), enum_);
+    }
+    let mut functions: serde_json::Map<String, Value> = serde_json::Map::new();
+    for (name, id) in &scope.function_names {
+        let mut function: Value = serde_json::to_value(&scope.functions[id]).unwrap();
+        function["body"] = Value::Null;
+        function["local_scope"] = Value::Null;
+        function["doc"] = Value::Null;
+        functions.insert(name.clone(), function);
+    }
+    let value = value.as_object_mut().unwrap();
+    value.insert("enums".to_string(), Value::Object(enums));
+    value.insert("functions".to_string(), Value::Object(functions));
+    value.remove("enum_names");
+    value.remove("enum_variants");
+    value.remove("function_names");
+    let value: Value = SourceLocation::remove_from(Value::Object(value.clone()));
+    hash(&serde_json::to_string(&value).unwrap())
+}
+
+/// The declarations of `scope` made in `file` itself, which is what gets
+/// cached of a module's scope.
+pub fn declared(scope: &Scope, file: &str) -> Scope {
+    let mut declared: Scope = Scope::new();
+    declared.module = scope.module.clone();
+    declared.imports = scope.imports.clone();
+    let mut enum_names: Vec<(&String, &usize)> = scope.enum_names.iter().collect();
+    enum_names.sort_by_key(|(_, id)| **id);
+    for (name, id) in enum_names {
+        if scope.enums[id].location.file == file {
+            declared
+                .enum_names
+                .insert(name.clone(), declared.enums.len());
+            declared
+                .enums
+                .insert(declared.enums.len(), scope.enums[id].clone());
+        }
+    }
+    let mut function_names: Vec<(&String, &usize)> = scope.function_names.iter().collect();
+    function_names.sort_by_key(|(_, id)| **id);
+    for (name, id) in function_names {
+        if scope.functions[id].location.file == file {
+            declared
+                .function_names
+                .insert(name.clone(), declared.functions.len());
+            declared
+                .functions
+                .insert(declared.functions.len(), scope.functions[id].clone());
+        }
+    }
+    for (name, class) in &scope.classes {
+        if class.location.file == file {
+            declared.classes.insert(name.clone(), class.clone());
+        }
+    }
+    for (method, class) in &scope.class_methods {
+        if declared.classes.contains_key(class) {
+            declared.class_methods.insert(method.clone(), class.clone());
+        }
+    }
+    declared.instances = scope
+        .instances
+        .iter()
+        .filter(|instance| instance.location.file == file)
+        .cloned()
+        .collect();
+    for (name, alias) in &scope.type_aliases {
+        if alias.location.file == file {
+            declared.type_aliases.insert(name.clone(), alias.clone());
+        }
+    }
+    for (name, newtype) in &scope.newtypes {
+        if newtype.location.file == file {
+            declared.newtypes.insert(name.clone(), newtype.clone());
+        }
+    }
+    declared
+}
+
+/// The scope a cached module would have been checked into: what its imports
+/// bring in now, in the order it imports them, with its own `declared`
+/// declarations on top.
+pub fn restore(declared: &Scope, imports: &[&Scope]) -> Scope {
+    let mut scope: Scope = Scope::new();
+    scope.add_builtin_classes();
+    for import in imports {
+        scope.merge(import);
+    }
+    scope.module = declared.module.clone();
+    scope.imports = declared.imports.clone();
+    for (name, id) in &declared.enum_names {
+        let enum_id: usize = scope.enums.len();
+        let enum_ = declared.enums[id].clone();
+        for variant in &enum_.variants {
+            let (EnumVariant::Unit(variant, _) | EnumVariant::Tuple(variant, _, _)) = variant;
+            scope.enum_variants.insert(variant.clone(), enum_id);
+        }
+        scope.enum_names.insert(name.clone(), enum_id);
+        scope.enums.insert(enum_id, enum_);
+    }
+    for (name, id) in &declared.function_names {
+        let function_id: usize = scope.functions.len();
+        let function: Function = declared.functions[id].clone();
+        scope.function_names.insert(name.clone(), function_id);
+        scope.functions.insert(function_id, function);
+    }
+    scope.classes.extend(declared.classes.clone());
+    scope.class_methods.extend(declared.class_methods.clone());
+    scope.instances.extend(declared.instances.clone());
+    scope.type_aliases.extend(declared.type_aliases.clone());
+    scope.newtypes.extend(declared.newtypes.clone());
+    scope
+}
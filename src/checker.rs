@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    ast::{EnumVariant, Expression, Parameter, PatternMatchDirective, Statement, Type},
-    compiler::Compiler,
+    ast::{
+        Constraint, DoStatement, EnumVariant, Expression, Parameter, PatternMatchCase,
+        PatternMatchDirective, Statement, StringPart, Type,
+    },
     error::{HarmonyError, HarmonyErrorKind},
     token::{SourceLocation, TokenKind},
 };
@@ -10,7 +14,7 @@ use crate::{
 type EnumId = usize;
 type FunctionId = usize;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Scope {
     pub parent: Option<Box<Scope>>,
     pub module: Option<(String, SourceLocation)>,
@@ -20,9 +24,14 @@ pub struct Scope {
     pub enums: HashMap<EnumId, Enum>,
     pub function_names: HashMap<String, FunctionId>,
     pub functions: HashMap<FunctionId, Function>,
+    pub classes: HashMap<String, Class>,
+    pub class_methods: HashMap<String, String>,
+    pub instances: Vec<Instance>,
+    pub type_aliases: HashMap<String, TypeAlias>,
+    pub newtypes: HashMap<String, Newtype>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalScope {
     pub variables: HashMap<String, Variable>,
 }
@@ -38,6 +47,94 @@ impl Scope {
             enums: HashMap::new(),
             function_names: HashMap::new(),
             functions: HashMap::new(),
+            classes: HashMap::new(),
+            class_methods: HashMap::new(),
+            instances: Vec::new(),
+            type_aliases: HashMap::new(),
+            newtypes: HashMap::new(),
+        }
+    }
+
+    /// Codegen maps the primitive instances onto JavaScript's own operators.
+    pub fn add_builtin_classes(&mut self) {
+        let location: SourceLocation = SourceLocation::default();
+        let parameter = || Type::GenericParameter("T".to_string(), SourceLocation::default());
+        let binary = |return_type: Type| {
+            Type::Function(vec![parameter(), parameter()], Box::new(return_type))
+        };
+        type BuiltinClass<'a> = (&'a str, Vec<(&'a str, Type)>, Vec<Type>);
+        let classes: Vec<BuiltinClass> = vec![
+            (
+                "Eq",
+                vec![("eq", binary(Type::Bool(location.clone())))],
+                vec![
+                    Type::Int(location.clone()),
+                    Type::Float(location.clone()),
+                    Type::String(location.clone()),
+                    Type::Char(location.clone()),
+                    Type::Bool(location.clone()),
+                ],
+            ),
+            (
+                "Ord",
+                vec![("compare", binary(Type::Int(location.clone())))],
+                vec![
+                    Type::Int(location.clone()),
+                    Type::Float(location.clone()),
+                    Type::String(location.clone()),
+                    Type::Char(location.clone()),
+                ],
+            ),
+            (
+                "Show",
+                vec![(
+                    "show",
+                    Type::Function(vec![parameter()], Box::new(Type::String(location.clone()))),
+                )],
+                vec![
+                    Type::Int(location.clone()),
+                    Type::Float(location.clone()),
+                    Type::String(location.clone()),
+                    Type::Char(location.clone()),
+                    Type::Bool(location.clone()),
+                ],
+            ),
+            (
+                "Num",
+                vec![
+                    ("add", binary(parameter())),
+                    ("sub", binary(parameter())),
+                    ("mul", binary(parameter())),
+                    ("div", binary(parameter())),
+                ],
+                vec![Type::Int(location.clone()), Type::Float(location.clone())],
+            ),
+        ];
+        for (name, methods, types) in classes {
+            for (method, _) in &methods {
+                self.class_methods
+                    .insert(method.to_string(), name.to_string());
+            }
+            self.classes.insert(
+                name.to_string(),
+                Class {
+                    name: name.to_string(),
+                    parameter: "T".to_string(),
+                    methods: methods
+                        .into_iter()
+                        .map(|(method, type_)| (method.to_string(), type_))
+                        .collect(),
+                    location: location.clone(),
+                },
+            );
+            for type_ in types {
+                self.instances.push(Instance {
+                    class: name.to_string(),
+                    type_,
+                    module: None,
+                    location: location.clone(),
+                });
+            }
         }
     }
 
@@ -70,6 +167,26 @@ impl Scope {
                     .insert(id, other.functions.get(&old_id).unwrap().clone());
             }
         }
+        for (name, class) in other.classes.clone() {
+            self.classes.entry(name).or_insert(class);
+        }
+        for (method, class) in other.class_methods.clone() {
+            self.class_methods.entry(method).or_insert(class);
+        }
+        for (name, alias) in other.type_aliases.clone() {
+            self.type_aliases.entry(name).or_insert(alias);
+        }
+        for (name, newtype) in other.newtypes.clone() {
+            self.newtypes.entry(name).or_insert(newtype);
+        }
+        for instance in other.instances.clone() {
+            if !self.instances.iter().any(|existing| {
+                existing.class == instance.class
+                    && Checker::type_key(&existing.type_) == Checker::type_key(&instance.type_)
+            }) {
+                self.instances.push(instance);
+            }
+        }
     }
 }
 
@@ -83,19 +200,34 @@ impl LocalScope {
 
 #[derive(Debug, Clone)]
 pub struct Checker {
-    pub compiler: Compiler,
     pub statements: Vec<Statement>,
     pub filename: String,
     pub global_scope: Scope,
+    pub import_paths: HashMap<String, String>,
+    pub partial_applications: Vec<SourceLocation>,
+    pub do_blocks: Vec<(SourceLocation, Expression)>,
+    /// Keyed by the location of the callee or operator.
+    pub dictionaries: Vec<(SourceLocation, Vec<String>)>,
+    pub references: Vec<Reference>,
+    pub tests: Vec<String>,
+    constraints: Vec<Constraint>,
 }
 
 impl Checker {
-    pub fn new(compiler: &Compiler, statements: &Vec<Statement>, filename: &String) -> Checker {
+    pub fn new(statements: &Vec<Statement>, filename: &String) -> Checker {
+        let mut global_scope: Scope = Scope::new();
+        global_scope.add_builtin_classes();
         Checker {
-            compiler: compiler.clone(),
             statements: statements.clone(),
             filename: filename.clone(),
-            global_scope: Scope::new(),
+            global_scope,
+            import_paths: HashMap::new(),
+            partial_applications: Vec::new(),
+            do_blocks: Vec::new(),
+            dictionaries: Vec::new(),
+            references: Vec::new(),
+            tests: Vec::new(),
+            constraints: Vec::new(),
         }
     }
 
@@ -130,9 +262,10 @@ impl Checker {
                 None,
                 SourceLocation {
                     file: self.filename.clone(),
+                    start: 0,
+                    end: 0,
                     line: 0,
                     column: 0,
-                    length: 0,
                 },
             ));
         }
@@ -159,9 +292,10 @@ impl Checker {
                 None,
                 SourceLocation {
                     file: self.filename.clone(),
+                    start: 0,
+                    end: 0,
                     line: 0,
                     column: 0,
-                    length: 0,
                 },
             ));
         }
@@ -203,42 +337,6 @@ impl Checker {
         Ok(imports)
     }
 
-    pub fn get_path_of_import(&mut self, import: &Import) -> Result<String, HarmonyError> {
-        let mut path: Vec<&str> = import.name.split(".").collect::<Vec<&str>>();
-        let module: &str = path.pop().unwrap();
-        let mut module_path: String = self.compiler.root.to_str().unwrap().to_string();
-        module_path = module_path.replace("\\", "/");
-        module_path.push_str("/runtime");
-        for dir in path {
-            module_path.push_str("/");
-            module_path.push_str(dir);
-        }
-        module_path.push_str("/");
-        module_path.push_str(module);
-        module_path.push_str(".harm");
-        if !std::path::Path::new(&module_path).exists() {
-            module_path = self.compiler.root.to_str().unwrap().to_string();
-            module_path.push_str("/");
-            module_path.push_str(self.filename.as_str());
-            module_path = module_path.replace("\\", "/");
-            let mut path: Vec<&str> = module_path.split("/").collect::<Vec<&str>>();
-            path.pop();
-            module_path = path.join("/");
-            module_path.push_str("/");
-            module_path.push_str(module);
-            module_path.push_str(".harm");
-            if !std::path::Path::new(&module_path).exists() {
-                return Err(HarmonyError::new(
-                    HarmonyErrorKind::CompileTime,
-                    format!("Module {} not found", import.name),
-                    None,
-                    self.global_scope.module.as_ref().unwrap().1.clone(),
-                ));
-            }
-        }
-        Ok(module_path)
-    }
-
     fn check_statements(&mut self) -> Result<(), HarmonyError> {
         for statement in self.statements.clone() {
             self.check_statement(&statement)?;
@@ -266,7 +364,13 @@ impl Checker {
                 parameters,
                 return_type,
                 binding: _,
+                doc,
             } => {
+                let parameters: &Vec<Parameter> = &self.expand_parameters(parameters)?;
+                let return_type: &Option<Type> = &return_type
+                    .as_ref()
+                    .map(|type_| self.expand_type(type_))
+                    .transpose()?;
                 let function_id: FunctionId = self.global_scope.functions.len();
                 let (name, location) = name.clone();
 
@@ -295,17 +399,20 @@ impl Checker {
                 let function: Function = Function {
                     name: name.clone(),
                     generic_parameters: vec![],
+                    constraints: vec![],
                     parameters: parameters.clone(),
                     return_type: return_type.clone(),
                     body: None,
                     location: location.clone(),
                     local_scope: local_scope.clone(),
                     is_external: true,
+                    doc: doc.clone(),
                 };
                 self.global_scope
                     .function_names
                     .insert(name.clone(), function_id);
                 self.global_scope.functions.insert(function_id, function);
+                self.check_parameter_defaults(parameters, true)?;
 
                 Ok(())
             }
@@ -314,7 +421,13 @@ impl Checker {
                 parameters,
                 return_type,
                 body,
+                doc,
             } => {
+                let parameters: &Vec<Parameter> = &self.expand_parameters(parameters)?;
+                let return_type: &Option<Type> = &return_type
+                    .as_ref()
+                    .map(|type_| self.expand_type(type_))
+                    .transpose()?;
                 let function_id: FunctionId = self.global_scope.functions.len();
                 let (name, location) = name.clone();
 
@@ -344,17 +457,20 @@ impl Checker {
                 let function: Function = Function {
                     name: name.clone(),
                     generic_parameters: vec![],
+                    constraints: vec![],
                     parameters: parameters.clone(),
                     return_type: return_type.clone(),
                     body: Some(body.clone()),
                     location: location.clone(),
                     local_scope: local_scope.clone(),
                     is_external: false,
+                    doc: doc.clone(),
                 };
                 self.global_scope
                     .function_names
                     .insert(name.clone(), function_id);
                 self.global_scope.functions.insert(function_id, function);
+                self.check_parameter_defaults(parameters, false)?;
 
                 let body_type: Type = self.check_expression(&body, &mut local_scope)?;
                 if body_type != return_type {
@@ -374,10 +490,17 @@ impl Checker {
             Statement::GenericFunction {
                 name,
                 generic_parameters,
+                constraints,
                 parameters,
                 return_type,
                 body,
+                doc,
             } => {
+                let parameters: &Vec<Parameter> = &self.expand_parameters(parameters)?;
+                let return_type: &Option<Type> = &return_type
+                    .as_ref()
+                    .map(|type_| self.expand_type(type_))
+                    .transpose()?;
                 let mut generic_parameters_: Vec<String> = vec![];
                 for generic_parameter in generic_parameters {
                     generic_parameters_.push(match generic_parameter {
@@ -396,11 +519,35 @@ impl Checker {
                     });
                 }
 
+                for constraint in constraints {
+                    let (class, location) = constraint.class.clone();
+                    if !generic_parameters_.contains(&constraint.parameter) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Constraint refers to unknown generic parameter '{}'",
+                                constraint.parameter
+                            ),
+                            None,
+                            location,
+                        ));
+                    }
+                    if !self.global_scope.classes.contains_key(&class) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!("Class '{}' is not defined", class),
+                            None,
+                            location,
+                        ));
+                    }
+                }
+
                 let function_id: FunctionId = self.global_scope.functions.len();
                 let (name, location) = name.clone();
 
                 let mut local_scope: LocalScope = LocalScope::new();
                 let mut parameter_types: Vec<Type> = Vec::new();
+                let mut resolved_parameters: Vec<Parameter> = Vec::new();
                 for parameter in parameters {
                     let parameter_name: String = parameter.name.clone().0.clone();
                     let parameter_type: Type = parameter.type_.clone();
@@ -418,6 +565,11 @@ impl Checker {
                         ));
                     }
                     parameter_types.push(ty.clone());
+                    resolved_parameters.push(Parameter {
+                        name: parameter.name.clone(),
+                        type_: ty.clone(),
+                        default: parameter.default.clone(),
+                    });
                     local_scope.variables.insert(
                         parameter_name.clone(),
                         Variable {
@@ -438,19 +590,30 @@ impl Checker {
                 let function: Function = Function {
                     name: name.clone(),
                     generic_parameters: generic_parameters.clone(),
-                    parameters: parameters.clone(),
+                    constraints: constraints.clone(),
+                    parameters: resolved_parameters,
                     return_type: return_type.clone(),
                     body: Some(body.clone()),
                     location: location.clone(),
                     local_scope: local_scope.clone(),
                     is_external: false,
+                    doc: doc.clone(),
                 };
                 self.global_scope
                     .function_names
                     .insert(name.clone(), function_id);
                 self.global_scope.functions.insert(function_id, function);
+                self.check_parameter_defaults(
+                    &self.global_scope.functions[&function_id].parameters.clone(),
+                    false,
+                )?;
 
-                let body_type: Type = self.check_expression(&body, &mut local_scope)?;
+                let outer_constraints: Vec<Constraint> =
+                    std::mem::replace(&mut self.constraints, constraints.clone());
+                let body_type: Result<Type, HarmonyError> =
+                    self.check_expression(&body, &mut local_scope);
+                self.constraints = outer_constraints;
+                let body_type: Type = body_type?;
                 if body_type != return_type {
                     return Err(HarmonyError::new(
                         HarmonyErrorKind::Semantic,
@@ -465,7 +628,12 @@ impl Checker {
 
                 Ok(())
             }
-            Statement::Enum { name, variants } => {
+            Statement::Enum {
+                name,
+                variants,
+                doc,
+            } => {
+                let variants: &Vec<EnumVariant> = &self.expand_variants(variants)?;
                 let enum_id: EnumId = self.global_scope.enums.len();
                 let (name, location) = name.clone();
 
@@ -486,6 +654,7 @@ impl Checker {
                     variants: variants.clone(),
                     location: location.clone(),
                     generic_parameters: vec![],
+                    doc: doc.clone(),
                 };
                 self.global_scope.enum_names.insert(name.clone(), enum_id);
                 self.global_scope.enums.insert(enum_id, enum_);
@@ -501,7 +670,9 @@ impl Checker {
                 name,
                 generic_parameters,
                 variants,
+                doc,
             } => {
+                let variants: &Vec<EnumVariant> = &self.expand_variants(variants)?;
                 let enum_id: EnumId = self.global_scope.enums.len();
                 let (name, location) = name.clone();
 
@@ -545,6 +716,7 @@ impl Checker {
                     variants: variants.clone(),
                     location: location.clone(),
                     generic_parameters: generic_parameters.clone(),
+                    doc: doc.clone(),
                 };
                 self.global_scope.enum_names.insert(name.clone(), enum_id);
                 self.global_scope.enums.insert(enum_id, enum_);
@@ -556,7 +728,418 @@ impl Checker {
 
                 Ok(())
             }
+            Statement::Class {
+                name,
+                generic_parameter,
+                methods,
+            } => {
+                let (name, location) = name.clone();
+                if self.global_scope.classes.contains_key(&name) {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!("Class '{}' is already defined", name),
+                        None,
+                        location,
+                    ));
+                }
+
+                let mut methods_: Vec<(String, Type)> = vec![];
+                for method in methods {
+                    let (method_name, method_location) = method.name.clone();
+                    if let Some(class) = self.global_scope.class_methods.get(&method_name) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Method '{}' is already declared by class '{}'",
+                                method_name, class
+                            ),
+                            None,
+                            method_location,
+                        ));
+                    }
+                    let type_: Type = self.expand_type(&method.type_)?;
+                    let type_: Type =
+                        self.check_type_generic(type_, vec![generic_parameter.0.clone()])?;
+                    if !matches!(type_, Type::Function(_, _)) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Method '{}' of class '{}' must have a function type, found '{}'",
+                                method_name, name, type_
+                            ),
+                            None,
+                            method_location,
+                        ));
+                    }
+                    methods_.push((method_name, type_));
+                }
+
+                for (method_name, _) in &methods_ {
+                    self.global_scope
+                        .class_methods
+                        .insert(method_name.clone(), name.clone());
+                }
+                self.global_scope.classes.insert(
+                    name.clone(),
+                    Class {
+                        name,
+                        parameter: generic_parameter.0.clone(),
+                        methods: methods_,
+                        location,
+                    },
+                );
+
+                Ok(())
+            }
+            Statement::Instance {
+                class,
+                type_,
+                methods,
+            } => {
+                let (class_name, location) = class.clone();
+                let class: Class = match self.global_scope.classes.get(&class_name) {
+                    Some(class) => class.clone(),
+                    None => {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!("Class '{}' is not defined", class_name),
+                            None,
+                            location,
+                        ))
+                    }
+                };
+                let type_: &Type = &self.expand_type(type_)?;
+                let key: Option<String> = Checker::type_key(type_);
+                if key.is_none() {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!(
+                            "Cannot declare an instance of class '{}' for type '{}'",
+                            class_name, type_
+                        ),
+                        None,
+                        location,
+                    ));
+                }
+                if self.global_scope.instances.iter().any(|instance| {
+                    instance.class == class_name && Checker::type_key(&instance.type_) == key
+                }) {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!("Instance '{}<{}>' is already defined", class_name, type_),
+                        None,
+                        location,
+                    ));
+                }
+
+                // registered first so the methods can recurse
+                self.global_scope.instances.push(Instance {
+                    class: class_name.clone(),
+                    type_: type_.clone(),
+                    module: self
+                        .global_scope
+                        .module
+                        .as_ref()
+                        .map(|(module, _)| module.clone()),
+                    location: location.clone(),
+                });
+
+                let mut bindings: HashMap<String, Type> = HashMap::new();
+                bindings.insert(class.parameter.clone(), type_.clone());
+                let mut implemented: Vec<String> = vec![];
+                for method in methods {
+                    let (name, parameters, return_type, body) = match method {
+                        Statement::Function {
+                            name,
+                            parameters,
+                            return_type,
+                            body,
+                            doc: _,
+                        } => (
+                            name,
+                            self.expand_parameters(parameters)?,
+                            return_type
+                                .as_ref()
+                                .map(|type_| self.expand_type(type_))
+                                .transpose()?,
+                            body,
+                        ),
+                        _ => {
+                            return Err(HarmonyError::new(
+                                HarmonyErrorKind::Semantic,
+                                format!(
+                                    "Instance '{}<{}>' may only contain non-generic functions",
+                                    class_name, type_
+                                ),
+                                None,
+                                location,
+                            ))
+                        }
+                    };
+                    let (method_name, method_location) = name.clone();
+                    let expected: Type =
+                        match class.methods.iter().find(|(name, _)| *name == method_name) {
+                            Some((_, method_type)) => Checker::substitute(method_type, &bindings),
+                            None => {
+                                return Err(HarmonyError::new(
+                                    HarmonyErrorKind::Semantic,
+                                    format!(
+                                        "Method '{}' is not part of class '{}'",
+                                        method_name, class_name
+                                    ),
+                                    None,
+                                    method_location,
+                                ))
+                            }
+                        };
+                    if implemented.contains(&method_name) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Method '{}' is defined more than once in instance '{}<{}>'",
+                                method_name, class_name, type_
+                            ),
+                            None,
+                            method_location,
+                        ));
+                    }
+
+                    let mut local_scope: LocalScope = LocalScope::new();
+                    for parameter in &parameters {
+                        local_scope.variables.insert(
+                            parameter.name.0.clone(),
+                            Variable {
+                                name: parameter.name.0.clone(),
+                                type_: parameter.type_.clone(),
+                                location: parameter.name.1.clone(),
+                                value: None,
+                            },
+                        );
+                    }
+                    let return_type: Type = match return_type.clone() {
+                        Some(type_) => type_,
+                        None => Type::Unit(method_location.clone()),
+                    };
+                    let actual: Type = Type::Function(
+                        parameters
+                            .iter()
+                            .map(|parameter| parameter.type_.clone())
+                            .collect(),
+                        Box::new(return_type.clone()),
+                    );
+                    if actual != expected {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Method '{}' has type '{}', but class '{}' expects '{}'",
+                                method_name, actual, class_name, expected
+                            ),
+                            None,
+                            method_location,
+                        ));
+                    }
+                    let body_type: Type = self.check_expression(body, &mut local_scope)?;
+                    if body_type != return_type {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Method '{}' return type '{}' does not match body type '{}'",
+                                method_name, return_type, body_type
+                            ),
+                            None,
+                            method_location,
+                        ));
+                    }
+                    implemented.push(method_name);
+                }
+                for (method_name, _) in &class.methods {
+                    if !implemented.contains(method_name) {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Instance '{}<{}>' is missing method '{}'",
+                                class_name, type_, method_name
+                            ),
+                            None,
+                            location,
+                        ));
+                    }
+                }
+
+                Ok(())
+            }
+            Statement::TypeAlias {
+                name,
+                generic_parameters,
+                type_,
+            } => {
+                let (name, location) = name.clone();
+                self.check_type_name_available(&name, &location)?;
+                let type_: Type = self.expand_type(type_)?;
+                self.global_scope.type_aliases.insert(
+                    name.clone(),
+                    TypeAlias {
+                        name,
+                        generic_parameters: generic_parameters
+                            .iter()
+                            .map(|parameter| parameter.to_string())
+                            .collect(),
+                        type_,
+                        location,
+                    },
+                );
+
+                Ok(())
+            }
+            Statement::Newtype { name, type_ } => {
+                let (name, location) = name.clone();
+                self.check_type_name_available(&name, &location)?;
+                let type_: Type = self.expand_type(type_)?;
+                self.global_scope.newtypes.insert(
+                    name.clone(),
+                    Newtype {
+                        name,
+                        type_,
+                        location,
+                    },
+                );
+
+                Ok(())
+            }
+            Statement::Test { name, body } => {
+                let (name, location) = name.clone();
+                if self.tests.contains(&name) {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!("Test \"{}\" is already defined", name),
+                        None,
+                        location,
+                    ));
+                }
+                self.tests.push(name.clone());
+
+                let body_type: Type = self.check_expression(body, &mut LocalScope::new())?;
+                if body_type != Type::Bool(location.clone()) {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!("Test \"{}\" must be a bool, found '{}'", name, body_type),
+                        Some("Compare the result with the expected value using ==".to_string()),
+                        body.location(),
+                    ));
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn check_type_name_available(
+        &self,
+        name: &String,
+        location: &SourceLocation,
+    ) -> Result<(), HarmonyError> {
+        if self.global_scope.enum_names.contains_key(name)
+            || self.global_scope.type_aliases.contains_key(name)
+            || self.global_scope.newtypes.contains_key(name)
+        {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!("Type '{}' is already defined", name),
+                None,
+                location.clone(),
+            ));
+        }
+        Ok(())
+    }
+
+    fn expand_type(&self, type_: &Type) -> Result<Type, HarmonyError> {
+        match type_ {
+            Type::Identifier(name, location) | Type::GenericArgument(name, location)
+                if self.global_scope.type_aliases.contains_key(name) =>
+            {
+                self.instantiate_alias(name, location, vec![])
+            }
+            Type::Generic(name, location, arguments) => {
+                let mut arguments_: Vec<Type> = vec![];
+                for argument in arguments {
+                    arguments_.push(self.expand_type(argument)?);
+                }
+                if self.global_scope.type_aliases.contains_key(name) {
+                    return self.instantiate_alias(name, location, arguments_);
+                }
+                Ok(Type::Generic(name.clone(), location.clone(), arguments_))
+            }
+            Type::List(Some(inner)) => Ok(Type::List(Some(Box::new(self.expand_type(inner)?)))),
+            Type::Function(parameters, return_type) => {
+                let mut parameters_: Vec<Type> = vec![];
+                for parameter in parameters {
+                    parameters_.push(self.expand_type(parameter)?);
+                }
+                Ok(Type::Function(
+                    parameters_,
+                    Box::new(self.expand_type(return_type)?),
+                ))
+            }
+            _ => Ok(type_.clone()),
+        }
+    }
+
+    fn instantiate_alias(
+        &self,
+        name: &String,
+        location: &SourceLocation,
+        arguments: Vec<Type>,
+    ) -> Result<Type, HarmonyError> {
+        let alias: &TypeAlias = &self.global_scope.type_aliases[name];
+        if alias.generic_parameters.len() != arguments.len() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!(
+                    "Type alias '{}' expects {} generic arguments, found {}",
+                    name,
+                    alias.generic_parameters.len(),
+                    arguments.len()
+                ),
+                None,
+                location.clone(),
+            ));
+        }
+        let bindings: HashMap<String, Type> = alias
+            .generic_parameters
+            .iter()
+            .cloned()
+            .zip(arguments)
+            .collect();
+        Ok(Checker::substitute(&alias.type_, &bindings))
+    }
+
+    fn expand_parameters(&self, parameters: &[Parameter]) -> Result<Vec<Parameter>, HarmonyError> {
+        let mut parameters_: Vec<Parameter> = vec![];
+        for parameter in parameters {
+            parameters_.push(Parameter {
+                name: parameter.name.clone(),
+                type_: self.expand_type(&parameter.type_)?,
+                default: parameter.default.clone(),
+            });
+        }
+        Ok(parameters_)
+    }
+
+    fn expand_variants(&self, variants: &[EnumVariant]) -> Result<Vec<EnumVariant>, HarmonyError> {
+        let mut variants_: Vec<EnumVariant> = vec![];
+        for variant in variants {
+            variants_.push(match variant {
+                EnumVariant::Tuple(name, location, types) => {
+                    let mut types_: Vec<Type> = vec![];
+                    for type_ in types {
+                        types_.push(self.expand_type(type_)?);
+                    }
+                    EnumVariant::Tuple(name.clone(), location.clone(), types_)
+                }
+                EnumVariant::Unit(_, _) => variant.clone(),
+            });
         }
+        Ok(variants_)
     }
 
     fn check_type_generic(
@@ -571,6 +1154,24 @@ impl Checker {
             Type::List(inner) => Ok(Type::List(Some(Box::new(
                 self.check_type_generic(*inner.unwrap(), generic_parameters)?,
             )))),
+            Type::Function(parameters, return_type) => {
+                let mut parameters_: Vec<Type> = vec![];
+                for parameter in parameters {
+                    parameters_
+                        .push(self.check_type_generic(parameter, generic_parameters.clone())?);
+                }
+                Ok(Type::Function(
+                    parameters_,
+                    Box::new(self.check_type_generic(*return_type, generic_parameters)?),
+                ))
+            }
+            Type::Generic(name, location, arguments) => {
+                let mut arguments_: Vec<Type> = vec![];
+                for argument in arguments {
+                    arguments_.push(self.check_type_generic(argument, generic_parameters.clone())?);
+                }
+                Ok(Type::Generic(name, location, arguments_))
+            }
             _ => Ok(ty),
         }
     }
@@ -584,10 +1185,16 @@ impl Checker {
             Expression::Binary {
                 left,
                 operator,
+                operator_location,
                 right,
             } => {
                 let left_type: Type = self.check_expression(left, local_scope)?;
                 let right_type: Type = self.check_expression(right, local_scope)?;
+                if let Some(type_) =
+                    self.check_class_operator(operator, operator_location, &left_type, &right_type)?
+                {
+                    return Ok(type_);
+                }
                 match operator {
                     TokenKind::Plus
                     | TokenKind::Minus
@@ -619,9 +1226,6 @@ impl Checker {
                                 return Ok(Type::Char(loc1.merge(&loc2)));
                             }
                         }
-                        if let Type::GenericParameter(name, loc1) = left_type.clone() {
-                            return Ok(Type::GenericParameter(name, loc1));
-                        }
                         return Err(HarmonyError::new(
                             HarmonyErrorKind::Semantic,
                             format!(
@@ -733,6 +1337,58 @@ impl Checker {
                             expression.location(),
                         ));
                     }
+                    TokenKind::PipeForward | TokenKind::PipeBackward => {
+                        let (argument_type, function_type) = match operator {
+                            TokenKind::PipeForward => (left_type.clone(), right_type.clone()),
+                            _ => (right_type.clone(), left_type.clone()),
+                        };
+                        if let Type::Any(location) = function_type {
+                            return Ok(Type::Any(location));
+                        }
+                        if let Type::Function(parameters, return_type) = function_type.clone() {
+                            if parameters.len() == 1 && parameters[0] == argument_type {
+                                return Ok(*return_type);
+                            }
+                        }
+                        Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Binary operator '{}' cannot be applied to types '{}' and '{}'",
+                                operator, left_type, right_type
+                            ),
+                            Some(format!(
+                                "'{}' expects a function of one argument of type '{}'",
+                                operator, argument_type
+                            )),
+                            expression.location(),
+                        ))
+                    }
+                    TokenKind::ComposeForward | TokenKind::ComposeBackward => {
+                        let (first_type, second_type) = match operator {
+                            TokenKind::ComposeForward => (left_type.clone(), right_type.clone()),
+                            _ => (right_type.clone(), left_type.clone()),
+                        };
+                        if let (
+                            Type::Function(first_parameters, first_return_type),
+                            Type::Function(second_parameters, second_return_type),
+                        ) = (first_type, second_type)
+                        {
+                            if second_parameters.len() == 1
+                                && second_parameters[0] == *first_return_type
+                            {
+                                return Ok(Type::Function(first_parameters, second_return_type));
+                            }
+                        }
+                        Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Binary operator '{}' cannot be applied to types '{}' and '{}'",
+                                operator, left_type, right_type
+                            ),
+                            None,
+                            expression.location(),
+                        ))
+                    }
                     _ => Err(HarmonyError::new(
                         HarmonyErrorKind::Semantic,
                         format!(
@@ -745,9 +1401,16 @@ impl Checker {
                 }
             }
             Expression::Identifier(identifier, location) => {
+                self.record_reference(identifier, location, local_scope, false);
                 if let Some(variable) = local_scope.variables.get(identifier) {
                     return Ok(variable.type_.clone());
                 }
+                if let Some(newtype) = self.global_scope.newtypes.get(identifier) {
+                    return Ok(Type::Function(
+                        vec![newtype.type_.clone()],
+                        Box::new(Type::Identifier(newtype.name.clone(), location.clone())),
+                    ));
+                }
                 if let Some(enum_id) = self.global_scope.enum_variants.get(identifier) {
                     let enum_: Enum = self.global_scope.enums.get(&enum_id).unwrap().clone();
                     let name: String = enum_.name.clone();
@@ -767,13 +1430,32 @@ impl Checker {
                         .get(&function_id)
                         .unwrap()
                         .clone();
-                    let types: Vec<Type> = function
+                    if !function.constraints.is_empty() {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Semantic,
+                            format!(
+                                "Function '{}' has class constraints and must be called directly",
+                                identifier
+                            ),
+                            Some("Wrap the call in an anonymous function instead".to_string()),
+                            location.clone(),
+                        ));
+                    }
+                    let types: Vec<Type> = function
                         .parameters
                         .iter()
                         .map(|parameter| parameter.type_.clone())
                         .collect();
                     return Ok(Type::Function(types, Box::new(function.return_type)));
                 }
+                if self.global_scope.class_methods.contains_key(identifier) {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!("Class method '{}' must be called directly", identifier),
+                        Some("Wrap the call in an anonymous function instead".to_string()),
+                        location.clone(),
+                    ));
+                }
                 return Err(HarmonyError::new(
                     HarmonyErrorKind::Semantic,
                     format!("Variable '{}' is not defined", identifier),
@@ -782,21 +1464,13 @@ impl Checker {
                 ));
             }
             Expression::Char(_, location) => Ok(Type::Char(location.clone())),
-            Expression::Integer(value, location) => {
-                if *value > i32::MAX as i64 || *value < i32::MIN as i64 {
-                    return Err(HarmonyError::new(
-                        HarmonyErrorKind::Semantic,
-                        format!("Integer literal '{}' is too large", value),
-                        Some("Consider using the BigInt type instead".to_string()),
-                        location.clone(),
-                    ));
-                }
-                Ok(Type::Int(location.clone()))
-            }
+            Expression::Float(_, location) => Ok(Type::Float(location.clone())),
+            Expression::Integer(_, location) => Ok(Type::Int(location.clone())),
             Expression::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 let condition_type = self.check_expression(condition, &mut local_scope.clone())?;
                 if let Type::Bool(_) = condition_type {
@@ -829,9 +1503,77 @@ impl Checker {
                 callee,
                 generic_arguments,
                 arguments,
+                argument_names,
+                ..
             } => {
+                self.record_reference(&callee.0, &callee.1, local_scope, true);
                 let callee: String = callee.0.clone();
+                if let Some(variable) = local_scope.variables.get(&callee).cloned() {
+                    if let Type::Function(types, return_type) = variable.type_ {
+                        if let Some((name, location)) = argument_names.iter().flatten().next() {
+                            return Err(HarmonyError::new(
+                                HarmonyErrorKind::Semantic,
+                                format!(
+                                    "Named argument '{}' cannot be used when calling '{}'",
+                                    name, callee
+                                ),
+                                Some(
+                                    "Named arguments are only supported for declared functions"
+                                        .to_string(),
+                                ),
+                                location.clone(),
+                            ));
+                        }
+                        if arguments.len() > types.len() {
+                            return Err(HarmonyError::new(
+                                HarmonyErrorKind::Semantic,
+                                format!(
+                                    "Function '{}' expects {} arguments, found {}",
+                                    callee,
+                                    types.len(),
+                                    arguments.len()
+                                ),
+                                None,
+                                expression.location(),
+                            ));
+                        }
+                        for (i, argument) in arguments.iter().enumerate() {
+                            let argument_type =
+                                self.check_expression(argument, &mut local_scope.clone())?;
+                            if argument_type != types.get(i).unwrap().clone() {
+                                return Err(HarmonyError::new(
+                                    HarmonyErrorKind::Semantic,
+                                    format!(
+                                        "Function '{}' expects argument {} to have type '{}', found '{}'",
+                                        callee,
+                                        i + 1,
+                                        types.get(i).unwrap().clone(),
+                                        argument_type
+                                    ),
+                                    None,
+                                    expression.location(),
+                                ));
+                            }
+                        }
+                        if arguments.len() < types.len() {
+                            self.partial_applications.push(expression.location());
+                            return Ok(Type::Function(
+                                types[arguments.len()..].to_vec(),
+                                return_type,
+                            ));
+                        }
+                        return Ok(*return_type.clone());
+                    }
+                }
                 if !self.global_scope.function_names.contains_key(&callee) {
+                    if let Some(newtype) = self.global_scope.newtypes.get(&callee).cloned() {
+                        return self.check_newtype_call(
+                            &newtype,
+                            arguments,
+                            &expression.location(),
+                            local_scope,
+                        );
+                    }
                     if self.global_scope.enum_variants.contains_key(&callee) {
                         let enum_id: EnumId = self
                             .global_scope
@@ -846,7 +1588,6 @@ impl Checker {
                         for (i, argument) in arguments.iter().enumerate() {
                             if let Expression::Identifier(id, location) = argument.clone() {
                                 for variant in enum_.variants.clone() {
-                                    println!("{:?}", variant);
                                     if let EnumVariant::Tuple(name, _, types) = variant {
                                         if name != callee {
                                             continue;
@@ -874,43 +1615,15 @@ impl Checker {
                         }
                         return Ok(Type::Enum(name, expression.location().clone()));
                     }
-                    if local_scope.variables.contains_key(&callee) {
-                        let variable: Variable =
-                            local_scope.variables.get(&callee).unwrap().clone();
-                        if let Type::Function(types, return_type) = variable.type_ {
-                            if types.len() != arguments.len() {
-                                return Err(HarmonyError::new(
-                                    HarmonyErrorKind::Semantic,
-                                    format!(
-                                        "Function '{}' expects {} arguments, found {}",
-                                        callee,
-                                        types.len(),
-                                        arguments.len()
-                                    ),
-                                    None,
-                                    expression.location(),
-                                ));
-                            }
-                            for (i, argument) in arguments.iter().enumerate() {
-                                let argument_type =
-                                    self.check_expression(argument, &mut local_scope.clone())?;
-                                if argument_type != types.get(i).unwrap().clone() {
-                                    return Err(HarmonyError::new(
-                                        HarmonyErrorKind::Semantic,
-                                        format!(
-                                            "Function '{}' expects argument {} to have type '{}', found '{}'",
-                                            callee,
-                                            i + 1,
-                                            types.get(i).unwrap().clone(),
-                                            argument_type
-                                        ),
-                                        None,
-                                        expression.location(),
-                                    ));
-                                }
-                            }
-                            return Ok(*return_type.clone());
-                        }
+                    if let Some(class) = self.global_scope.class_methods.get(&callee).cloned() {
+                        return self.check_method_call(
+                            &class,
+                            &callee,
+                            arguments,
+                            argument_names,
+                            &expression.location(),
+                            local_scope,
+                        );
                     }
                     return Err(HarmonyError::new(
                         HarmonyErrorKind::Semantic,
@@ -925,14 +1638,13 @@ impl Checker {
                     .get(&callee)
                     .unwrap()
                     .clone();
-                let callee_type = self
+                let function: Function = self
                     .global_scope
                     .functions
                     .get(&function_id)
                     .unwrap()
-                    .clone()
-                    .return_type
                     .clone();
+                let callee_type = function.return_type.clone();
 
                 if generic_arguments.len() > 0 {
                     let generic_parameters: Vec<Type> = self
@@ -957,11 +1669,55 @@ impl Checker {
                     }
                 }
 
-                let mut argument_types = Vec::new();
-                for argument in arguments {
-                    let argument_type =
-                        self.check_expression(argument, &mut local_scope.clone())?;
-                    argument_types.push(argument_type);
+                let resolved: Vec<Argument> = Checker::resolve_arguments(
+                    &function,
+                    arguments,
+                    argument_names,
+                    &expression.location(),
+                )?;
+                for (i, (parameter, argument)) in
+                    function.parameters.iter().zip(&resolved).enumerate()
+                {
+                    if let Argument::Given(argument) = argument {
+                        let argument_type: Type =
+                            self.check_expression(argument, &mut local_scope.clone())?;
+                        if argument_type != parameter.type_ {
+                            return Err(HarmonyError::new(
+                                HarmonyErrorKind::Semantic,
+                                format!(
+                                    "Function '{}' expects argument {} to have type '{}', found '{}'",
+                                    callee,
+                                    i + 1,
+                                    parameter.type_,
+                                    argument_type
+                                ),
+                                None,
+                                argument.location(),
+                            ));
+                        }
+                    }
+                }
+                let mut callee_type: Type = callee_type;
+                if !function.constraints.is_empty() {
+                    let (dictionaries, return_type) = self.resolve_constraints(
+                        &function,
+                        &resolved,
+                        &expression.location(),
+                        local_scope,
+                    )?;
+                    self.record_dictionaries(&expression.location(), dictionaries);
+                    callee_type = return_type;
+                }
+                if resolved
+                    .iter()
+                    .any(|argument| matches!(argument, Argument::Missing))
+                {
+                    self.partial_applications.push(expression.location());
+                    let remaining: Vec<Type> = function.parameters[arguments.len()..]
+                        .iter()
+                        .map(|parameter| parameter.type_.clone())
+                        .collect();
+                    return Ok(Type::Function(remaining, Box::new(callee_type)));
                 }
 
                 Ok(callee_type)
@@ -970,6 +1726,7 @@ impl Checker {
                 expression,
                 cases,
                 default_case,
+                ..
             } => {
                 let expression_type =
                     self.check_expression(expression, &mut local_scope.clone())?;
@@ -978,7 +1735,7 @@ impl Checker {
                 for case in cases {
                     let mut case_scope = local_scope.clone();
 
-                    if let Expression::List(elements) = &case.pattern {
+                    if let Expression::List(elements, _) = &case.pattern {
                         for (_, element) in elements.iter().enumerate() {
                             if let Expression::Identifier(id, location) = element.clone() {
                                 let element_type: Type = match expression_type.clone() {
@@ -1004,7 +1761,7 @@ impl Checker {
                                         value: None,
                                     },
                                 );
-                            } else if let Expression::Rest(expr) = element.clone() {
+                            } else if let Expression::Rest(expr, _) = element.clone() {
                                 if let Expression::Identifier(id, _) = *expr.clone() {
                                     let element_type: Type = match expression_type.clone() {
                                         Type::List(inner) => *inner.unwrap(),
@@ -1135,7 +1892,9 @@ impl Checker {
                     location,
                 ))
             }
-            Expression::Index { expression, index } => {
+            Expression::Index {
+                expression, index, ..
+            } => {
                 let expression_type =
                     self.check_expression(expression, &mut local_scope.clone())?;
                 let index_type = self.check_expression(index, &mut local_scope.clone())?;
@@ -1172,16 +1931,16 @@ impl Checker {
                 ));
             }
             Expression::Bool(_, _) => Ok(Type::Bool(expression.location().clone())),
-            Expression::List(elements) => {
+            Expression::List(elements, _) => {
                 let mut element_types = Vec::new();
                 let mut found_elements: Vec<Expression> = Vec::new();
                 for element in elements {
-                    if let Expression::Rest(name) = element {
+                    if let Expression::Rest(name, location) = element {
                         // get the rest of the elements
                         let mut rest_elements = Vec::new();
                         let mut found_rest = false;
                         for element in elements {
-                            if let Expression::Rest(_) = element {
+                            if let Expression::Rest(_, _) = element {
                                 found_rest = true;
                                 continue;
                             }
@@ -1189,7 +1948,7 @@ impl Checker {
                                 rest_elements.push(element.clone());
                             }
                         }
-                        let value = Expression::List(rest_elements);
+                        let value = Expression::List(rest_elements, location.clone());
                         let rest_type = self.check_expression(&value, &mut local_scope.clone())?;
                         if let Expression::Identifier(name, loc) = *name.clone() {
                             local_scope.variables.insert(
@@ -1215,22 +1974,34 @@ impl Checker {
                     expression.location().clone(),
                 )))))
             }
-            Expression::Rest(_) => Err(HarmonyError::new(
+            Expression::Rest(_, _) => Err(HarmonyError::new(
                 HarmonyErrorKind::Semantic,
                 "Rest expression not allowed here.".to_string(),
                 Some("Rest expressions are only allowed in lists.".to_string()),
                 expression.location(),
             )),
             Expression::String(_, _) => Ok(Type::String(expression.location().clone())),
+            Expression::InterpolatedString(parts, location) => {
+                for part in parts {
+                    if let StringPart::Expression(expression, interpolation) = part {
+                        let type_: Type =
+                            self.check_expression(expression, &mut local_scope.clone())?;
+                        self.check_interpolation(&type_, interpolation, &expression.location())?;
+                    }
+                }
+                Ok(Type::String(location.clone()))
+            }
             Expression::Let {
                 name,
                 type_annotation,
                 value,
                 body,
+                ..
             } => {
                 let value_type = self.check_expression(value, &mut local_scope.clone())?;
                 if let Some(type_annotation) = type_annotation {
-                    if type_annotation != &value_type {
+                    let type_annotation: Type = self.expand_type(type_annotation)?;
+                    if type_annotation != value_type {
                         return Err(HarmonyError::new(
                             HarmonyErrorKind::Semantic,
                             format!(
@@ -1262,54 +2033,982 @@ impl Checker {
             } => {
                 let mut parameter_types = Vec::new();
                 for parameter in parameters {
-                    let parameter_type = parameter.type_.clone();
+                    let parameter_type = self.expand_type(&parameter.type_)?;
                     parameter_types.push(parameter_type);
                 }
                 let function_type = Type::Function(
                     parameter_types,
-                    Box::new(return_type.clone().unwrap().clone()),
+                    Box::new(self.expand_type(return_type.as_ref().unwrap())?),
                 );
                 Ok(function_type)
             }
+            Expression::Do {
+                statements,
+                location,
+            } => {
+                let desugared: Expression = self.desugar_do(statements, local_scope)?;
+                if !self.do_blocks.iter().any(|(block, _)| block == location) {
+                    self.do_blocks.push((location.clone(), desugared.clone()));
+                }
+                self.check_expression(&desugared, &mut local_scope.clone())
+            }
+            Expression::Unary {
+                operator, right, ..
+            } => {
+                let right_type: Type = self.check_expression(right, local_scope)?;
+                let location: SourceLocation = expression.location();
+                match (operator, right_type.clone()) {
+                    (TokenKind::Minus, Type::Int(_)) => Ok(Type::Int(location)),
+                    (TokenKind::Minus, Type::Float(_)) => Ok(Type::Float(location)),
+                    (TokenKind::Not, Type::Bool(_)) => Ok(Type::Bool(location)),
+                    _ => Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!(
+                            "Unary operator '{}' cannot be applied to type '{}'",
+                            operator, right_type
+                        ),
+                        None,
+                        location,
+                    )),
+                }
+            }
+        }
+    }
+
+    fn record_reference(
+        &mut self,
+        name: &str,
+        location: &SourceLocation,
+        local_scope: &LocalScope,
+        is_call: bool,
+    ) {
+        if self
+            .references
+            .iter()
+            .any(|reference| reference.location == *location)
+        {
+            return;
+        }
+        let variable: Option<&Variable> = local_scope.variables.get(name);
+        let reference: Option<(SourceLocation, Type, bool)> = match variable {
+            Some(variable)
+                if !is_call
+                    || matches!(variable.type_, Type::Function(_, _))
+                    || !self.global_scope.function_names.contains_key(name) =>
+            {
+                Some((variable.location.clone(), variable.type_.clone(), true))
+            }
             _ => {
-                dbg!(expression);
-                todo!("check_expression")
+                if let Some(function) = self
+                    .global_scope
+                    .function_names
+                    .get(name)
+                    .and_then(|id| self.global_scope.functions.get(id))
+                {
+                    let types: Vec<Type> = function
+                        .parameters
+                        .iter()
+                        .map(|parameter| parameter.type_.clone())
+                        .collect();
+                    Some((
+                        function.location.clone(),
+                        Type::Function(types, Box::new(function.return_type.clone())),
+                        false,
+                    ))
+                } else if let Some(enum_) = self
+                    .global_scope
+                    .enum_variants
+                    .get(name)
+                    .and_then(|id| self.global_scope.enums.get(id))
+                {
+                    let definition: SourceLocation = enum_
+                        .variants
+                        .iter()
+                        .find_map(|variant| match variant {
+                            EnumVariant::Unit(variant, location)
+                            | EnumVariant::Tuple(variant, location, _)
+                                if variant == name =>
+                            {
+                                Some(location.clone())
+                            }
+                            _ => None,
+                        })
+                        .unwrap_or(enum_.location.clone());
+                    let type_: Type = if enum_.generic_parameters.is_empty() {
+                        Type::Enum(enum_.name.clone(), location.clone())
+                    } else {
+                        Type::GenericEnum(
+                            enum_.name.clone(),
+                            location.clone(),
+                            enum_.generic_parameters.clone(),
+                        )
+                    };
+                    Some((definition, type_, false))
+                } else {
+                    self.global_scope.newtypes.get(name).map(|newtype| {
+                        (
+                            newtype.location.clone(),
+                            Type::Function(
+                                vec![newtype.type_.clone()],
+                                Box::new(Type::Identifier(newtype.name.clone(), location.clone())),
+                            ),
+                            false,
+                        )
+                    })
+                }
+            }
+        };
+        if let Some((definition, type_, local)) = reference {
+            self.references.push(Reference {
+                location: location.clone(),
+                definition,
+                type_,
+                local,
+            });
+        }
+    }
+
+    /// The dictionary is recorded under `interpolation`, where codegen looks
+    /// it up, but errors point at the expression's `location`.
+    fn check_interpolation(
+        &mut self,
+        type_: &Type,
+        interpolation: &SourceLocation,
+        location: &SourceLocation,
+    ) -> Result<(), HarmonyError> {
+        let key: Option<String> = Checker::type_key(type_);
+        if matches!(type_, Type::Any(_))
+            || self.global_scope.instances.iter().any(|instance| {
+                instance.module.is_none()
+                    && instance.class == "Show"
+                    && Checker::type_key(&instance.type_) == key
+            })
+        {
+            return Ok(());
+        }
+        match self.resolve_dictionary("Show", type_, location)? {
+            Some(dictionary) => {
+                self.record_dictionaries(interpolation, vec![dictionary]);
+                Ok(())
             }
+            None => Err(Checker::missing_instance("Show", type_, location)),
         }
     }
+
+    fn check_newtype_call(
+        &mut self,
+        newtype: &Newtype,
+        arguments: &[Expression],
+        location: &SourceLocation,
+        local_scope: &mut LocalScope,
+    ) -> Result<Type, HarmonyError> {
+        if arguments.len() != 1 {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!(
+                    "Newtype '{}' expects 1 argument, found {}",
+                    newtype.name,
+                    arguments.len()
+                ),
+                None,
+                location.clone(),
+            ));
+        }
+        match &arguments[0] {
+            Expression::Identifier(id, id_location)
+                if !local_scope.variables.contains_key(id)
+                    && !self.global_scope.function_names.contains_key(id)
+                    && !self.global_scope.enum_variants.contains_key(id) =>
+            {
+                local_scope.variables.insert(
+                    id.clone(),
+                    Variable {
+                        name: id.clone(),
+                        type_: newtype.type_.clone(),
+                        location: id_location.clone(),
+                        value: None,
+                    },
+                );
+            }
+            argument => {
+                let argument_type: Type =
+                    self.check_expression(argument, &mut local_scope.clone())?;
+                if argument_type != newtype.type_ {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!(
+                            "Newtype '{}' wraps '{}', found '{}'",
+                            newtype.name, newtype.type_, argument_type
+                        ),
+                        None,
+                        location.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(Type::Identifier(newtype.name.clone(), location.clone()))
+    }
+
+    /// `None` for operators on primitive types.
+    fn check_class_operator(
+        &mut self,
+        operator: &TokenKind,
+        location: &SourceLocation,
+        left_type: &Type,
+        right_type: &Type,
+    ) -> Result<Option<Type>, HarmonyError> {
+        let class: &str = match operator {
+            TokenKind::Plus | TokenKind::Minus | TokenKind::Asterisk | TokenKind::Slash => "Num",
+            TokenKind::LessThan
+            | TokenKind::LessThanEquals
+            | TokenKind::GreaterThan
+            | TokenKind::GreaterThanEquals => "Ord",
+            TokenKind::DoubleEquals | TokenKind::NotEquals => "Eq",
+            _ => return Ok(None),
+        };
+        if matches!(
+            left_type,
+            Type::Unit(_)
+                | Type::Int(_)
+                | Type::Float(_)
+                | Type::String(_)
+                | Type::Char(_)
+                | Type::Bool(_)
+        ) || left_type != right_type
+        {
+            return Ok(None);
+        }
+        match self.resolve_dictionary(class, left_type, location)? {
+            Some(dictionary) => {
+                self.record_dictionaries(location, vec![dictionary]);
+                Ok(Some(match class {
+                    "Num" => left_type.clone(),
+                    _ => Type::Bool(location.clone()),
+                }))
+            }
+            None => Err(Checker::missing_instance(class, left_type, location)),
+        }
+    }
+
+    fn check_method_call(
+        &mut self,
+        class: &str,
+        method: &str,
+        arguments: &[Expression],
+        argument_names: &[Option<(String, SourceLocation)>],
+        location: &SourceLocation,
+        local_scope: &mut LocalScope,
+    ) -> Result<Type, HarmonyError> {
+        let class: Class = self.global_scope.classes[class].clone();
+        let (parameters, return_type) = match class.methods.iter().find(|(name, _)| name == method)
+        {
+            Some((_, Type::Function(parameters, return_type))) => {
+                (parameters.clone(), *return_type.clone())
+            }
+            _ => unreachable!(),
+        };
+        if let Some((name, location)) = argument_names.iter().flatten().next() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!(
+                    "Named argument '{}' cannot be used when calling '{}'",
+                    name, method
+                ),
+                Some("Named arguments are only supported for declared functions".to_string()),
+                location.clone(),
+            ));
+        }
+        if arguments.len() != parameters.len() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!(
+                    "Method '{}' expects {} arguments, found {}",
+                    method,
+                    parameters.len(),
+                    arguments.len()
+                ),
+                None,
+                location.clone(),
+            ));
+        }
+
+        let mut bindings: HashMap<String, Type> = HashMap::new();
+        for (i, argument) in arguments.iter().enumerate() {
+            let argument_type: Type = self.check_expression(argument, &mut local_scope.clone())?;
+            if argument_type != parameters[i] {
+                return Err(HarmonyError::new(
+                    HarmonyErrorKind::Semantic,
+                    format!(
+                        "Method '{}' expects argument {} to have type '{}', found '{}'",
+                        method,
+                        i + 1,
+                        parameters[i],
+                        argument_type
+                    ),
+                    None,
+                    location.clone(),
+                ));
+            }
+            Checker::unify(
+                &parameters[i],
+                &argument_type,
+                std::slice::from_ref(&class.parameter),
+                &mut bindings,
+            );
+        }
+        let type_: Type = match bindings.get(&class.parameter) {
+            Some(type_) => type_.clone(),
+            None => {
+                return Err(HarmonyError::new(
+                    HarmonyErrorKind::Semantic,
+                    format!(
+                        "Cannot infer which instance of class '{}' to use for '{}'",
+                        class.name, method
+                    ),
+                    None,
+                    location.clone(),
+                ))
+            }
+        };
+        let dictionary: String = match self.resolve_dictionary(&class.name, &type_, location)? {
+            Some(dictionary) => dictionary,
+            None => return Err(Checker::missing_instance(&class.name, &type_, location)),
+        };
+        self.record_dictionaries(location, vec![dictionary]);
+        Ok(Checker::substitute(&return_type, &bindings))
+    }
+
+    fn resolve_constraints(
+        &mut self,
+        function: &Function,
+        arguments: &[Argument],
+        location: &SourceLocation,
+        local_scope: &mut LocalScope,
+    ) -> Result<(Vec<String>, Type), HarmonyError> {
+        let names: Vec<String> = function
+            .generic_parameters
+            .iter()
+            .map(|parameter| parameter.to_string())
+            .collect();
+        let mut bindings: HashMap<String, Type> = HashMap::new();
+        for (parameter, argument) in function.parameters.iter().zip(arguments) {
+            if let Argument::Given(argument) = argument {
+                let argument_type: Type =
+                    self.check_expression(argument, &mut local_scope.clone())?;
+                Checker::unify(&parameter.type_, &argument_type, &names, &mut bindings);
+            }
+        }
+
+        let mut dictionaries: Vec<String> = vec![];
+        for constraint in &function.constraints {
+            let type_: Type = match bindings.get(&constraint.parameter) {
+                Some(type_) => type_.clone(),
+                None => {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!(
+                            "Cannot infer generic parameter '{}' of function '{}'",
+                            constraint.parameter, function.name
+                        ),
+                        None,
+                        location.clone(),
+                    ))
+                }
+            };
+            match self.resolve_dictionary(&constraint.class.0, &type_, location)? {
+                Some(dictionary) => dictionaries.push(dictionary),
+                None => {
+                    return Err(Checker::missing_instance(
+                        &constraint.class.0,
+                        &type_,
+                        location,
+                    ))
+                }
+            }
+        }
+        Ok((
+            dictionaries,
+            Checker::substitute(&function.return_type, &bindings),
+        ))
+    }
+
+    fn resolve_dictionary(
+        &mut self,
+        class: &str,
+        type_: &Type,
+        location: &SourceLocation,
+    ) -> Result<Option<String>, HarmonyError> {
+        if let Type::GenericParameter(name, _) = type_ {
+            if self
+                .constraints
+                .iter()
+                .any(|constraint| constraint.parameter == *name && constraint.class.0 == class)
+            {
+                return Ok(Some(Checker::dictionary_parameter(class, name)));
+            }
+            return Ok(None);
+        }
+        let key: String = match Checker::type_key(type_) {
+            Some(key) => key,
+            None => return Ok(None),
+        };
+        let instance: Instance = match self.global_scope.instances.iter().find(|instance| {
+            instance.class == class && Checker::type_key(&instance.type_).as_ref() == Some(&key)
+        }) {
+            Some(instance) => instance.clone(),
+            None => return Ok(None),
+        };
+        let dictionary: String = Checker::dictionary_name(class, &key);
+        let module: String = match instance.module {
+            Some(module) => module,
+            None => return Ok(Some(dictionary)),
+        };
+        if self.global_scope.module.as_ref().map(|(name, _)| name) == Some(&module) {
+            return Ok(Some(dictionary));
+        }
+        for import in self.lookup_imports()? {
+            if import.name != module {
+                continue;
+            }
+            if !import.exposing.is_empty() {
+                return Ok(Some(dictionary));
+            }
+            if let Some(alias) = import.alias {
+                return Ok(Some(format!("{}.{}", alias, dictionary)));
+            }
+        }
+        Err(HarmonyError::new(
+            HarmonyErrorKind::Semantic,
+            format!(
+                "Instance '{}<{}>' is defined in module '{}', which is not imported",
+                class, type_, module
+            ),
+            Some(format!("Add 'import {} as ...' to use it", module)),
+            location.clone(),
+        ))
+    }
+
+    fn missing_instance(class: &str, type_: &Type, location: &SourceLocation) -> HarmonyError {
+        let hint: String = match type_ {
+            Type::GenericParameter(name, _) => {
+                format!("Add the constraint '<{}: {}>'", name, class)
+            }
+            _ => format!(
+                "Declare one with 'instance {}<{}> where ... end'",
+                class, type_
+            ),
+        };
+        HarmonyError::new(
+            HarmonyErrorKind::Semantic,
+            format!("No instance of class '{}' for type '{}'", class, type_),
+            Some(hint),
+            location.clone(),
+        )
+    }
+
+    fn record_dictionaries(&mut self, location: &SourceLocation, dictionaries: Vec<String>) {
+        if !self.dictionaries.iter().any(|(key, _)| key == location) {
+            self.dictionaries.push((location.clone(), dictionaries));
+        }
+    }
+
+    pub fn type_key(type_: &Type) -> Option<String> {
+        match type_ {
+            Type::Unit(_) => Some("unit".to_string()),
+            Type::Int(_) => Some("int".to_string()),
+            Type::Float(_) => Some("float".to_string()),
+            Type::String(_) => Some("string".to_string()),
+            Type::Bool(_) => Some("bool".to_string()),
+            Type::Char(_) => Some("char".to_string()),
+            Type::List(_) => Some("List".to_string()),
+            Type::Generic(name, _, _)
+            | Type::Enum(name, _)
+            | Type::GenericEnum(name, _, _)
+            | Type::Identifier(name, _) => Some(name.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn dictionary_name(class: &str, key: &str) -> String {
+        format!("{}${}", class, key)
+    }
+
+    pub fn dictionary_parameter(class: &str, parameter: &str) -> String {
+        format!("__{}_{}", class, parameter)
+    }
+
+    fn unify(
+        pattern: &Type,
+        actual: &Type,
+        names: &[String],
+        bindings: &mut HashMap<String, Type>,
+    ) {
+        match (pattern, actual) {
+            (
+                Type::GenericParameter(name, _)
+                | Type::GenericArgument(name, _)
+                | Type::Identifier(name, _),
+                _,
+            ) if names.contains(name) => {
+                bindings
+                    .entry(name.clone())
+                    .or_insert_with(|| actual.clone());
+            }
+            (Type::List(Some(pattern)), Type::List(Some(actual))) => {
+                Checker::unify(pattern, actual, names, bindings);
+            }
+            (Type::Function(patterns, pattern), Type::Function(actuals, actual)) => {
+                for (pattern, actual) in patterns.iter().zip(actuals) {
+                    Checker::unify(pattern, actual, names, bindings);
+                }
+                Checker::unify(pattern, actual, names, bindings);
+            }
+            (
+                Type::Generic(_, _, patterns) | Type::GenericEnum(_, _, patterns),
+                Type::Generic(_, _, actuals) | Type::GenericEnum(_, _, actuals),
+            ) => {
+                for (pattern, actual) in patterns.iter().zip(actuals) {
+                    Checker::unify(pattern, actual, names, bindings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn substitute(type_: &Type, bindings: &HashMap<String, Type>) -> Type {
+        match type_ {
+            Type::GenericParameter(name, _)
+            | Type::GenericArgument(name, _)
+            | Type::Identifier(name, _)
+                if bindings.contains_key(name) =>
+            {
+                bindings[name].clone()
+            }
+            Type::List(Some(inner)) => {
+                Type::List(Some(Box::new(Checker::substitute(inner, bindings))))
+            }
+            Type::Function(parameters, return_type) => Type::Function(
+                parameters
+                    .iter()
+                    .map(|parameter| Checker::substitute(parameter, bindings))
+                    .collect(),
+                Box::new(Checker::substitute(return_type, bindings)),
+            ),
+            Type::Generic(name, location, arguments) => Type::Generic(
+                name.clone(),
+                location.clone(),
+                arguments
+                    .iter()
+                    .map(|argument| Checker::substitute(argument, bindings))
+                    .collect(),
+            ),
+            Type::GenericEnum(name, location, arguments) => Type::GenericEnum(
+                name.clone(),
+                location.clone(),
+                arguments
+                    .iter()
+                    .map(|argument| Checker::substitute(argument, bindings))
+                    .collect(),
+            ),
+            _ => type_.clone(),
+        }
+    }
+
+    /// Checks the default values of `parameters`. A default may use the
+    /// parameters before it, as JavaScript evaluates defaults left to right,
+    /// except in foreign functions, whose parameters are renamed to `arg0`,
+    /// `arg1`, ... in the generated code.
+    fn check_parameter_defaults(
+        &mut self,
+        parameters: &[Parameter],
+        is_foreign: bool,
+    ) -> Result<(), HarmonyError> {
+        let mut scope: LocalScope = LocalScope::new();
+        for parameter in parameters {
+            if let Some(default) = &parameter.default {
+                let default_type: Type = self.check_expression(default, &mut scope.clone())?;
+                if default_type != parameter.type_ {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        format!(
+                            "Default value of parameter '{}' has type '{}', expected '{}'",
+                            parameter.name.0, default_type, parameter.type_
+                        ),
+                        None,
+                        default.location(),
+                    ));
+                }
+            }
+            if !is_foreign {
+                scope.variables.insert(
+                    parameter.name.0.clone(),
+                    Variable {
+                        name: parameter.name.0.clone(),
+                        type_: parameter.type_.clone(),
+                        location: parameter.name.1.clone(),
+                        value: None,
+                    },
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn resolve_arguments(
+        function: &Function,
+        arguments: &[Expression],
+        argument_names: &[Option<(String, SourceLocation)>],
+        location: &SourceLocation,
+    ) -> Result<Vec<Argument>, HarmonyError> {
+        if arguments.len() > function.parameters.len() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Semantic,
+                format!(
+                    "Function '{}' expects {} arguments, found {}",
+                    function.name,
+                    function.parameters.len(),
+                    arguments.len()
+                ),
+                None,
+                location.clone(),
+            ));
+        }
+
+        let mut resolved: Vec<Argument> = vec![Argument::Missing; function.parameters.len()];
+        let mut is_named: bool = false;
+        for (i, argument) in arguments.iter().enumerate() {
+            let index: usize = match argument_names.get(i).cloned().flatten() {
+                Some((name, name_location)) => {
+                    is_named = true;
+                    match function
+                        .parameters
+                        .iter()
+                        .position(|parameter| parameter.name.0 == name)
+                    {
+                        Some(index) => index,
+                        None => {
+                            return Err(HarmonyError::new(
+                                HarmonyErrorKind::Semantic,
+                                format!(
+                                    "Function '{}' has no parameter named '{}'",
+                                    function.name, name
+                                ),
+                                None,
+                                name_location,
+                            ))
+                        }
+                    }
+                }
+                None if is_named => {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::Semantic,
+                        "Positional arguments cannot follow named arguments".to_string(),
+                        None,
+                        argument.location(),
+                    ))
+                }
+                None => i,
+            };
+            if !matches!(resolved[index], Argument::Missing) {
+                return Err(HarmonyError::new(
+                    HarmonyErrorKind::Semantic,
+                    format!(
+                        "Parameter '{}' of function '{}' is given more than once",
+                        function.parameters[index].name.0, function.name
+                    ),
+                    None,
+                    argument.location(),
+                ));
+            }
+            resolved[index] = Argument::Given(Box::new(argument.clone()));
+        }
+
+        let supplied: usize = arguments.len();
+        for (i, parameter) in function.parameters.iter().enumerate() {
+            if !matches!(resolved[i], Argument::Missing) {
+                continue;
+            }
+            if parameter.default.is_some() {
+                if is_named || !Checker::is_partial(function, supplied) {
+                    resolved[i] = Argument::Default;
+                }
+            } else if is_named {
+                return Err(HarmonyError::new(
+                    HarmonyErrorKind::Semantic,
+                    format!(
+                        "Missing argument for parameter '{}' of function '{}'",
+                        parameter.name.0, function.name
+                    ),
+                    None,
+                    location.clone(),
+                ));
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    fn is_partial(function: &Function, supplied: usize) -> bool {
+        function.parameters[supplied..]
+            .iter()
+            .any(|parameter| parameter.default.is_none())
+    }
+
+    fn desugar_do(
+        &mut self,
+        statements: &[DoStatement],
+        local_scope: &LocalScope,
+    ) -> Result<Expression, HarmonyError> {
+        let mut scope: LocalScope = local_scope.clone();
+        let mut steps: Vec<(DoStatement, Option<String>)> = Vec::new();
+        let mut monad: Option<&str> = None;
+        let mut plain_binds: Vec<(SourceLocation, Type)> = Vec::new();
+        for statement in &statements[..statements.len() - 1] {
+            let (name, value): ((String, SourceLocation), &Expression) = match statement {
+                DoStatement::Bind { name, value } => (name.clone(), value),
+                DoStatement::Let { name, value, .. } => (name.clone(), value),
+                DoStatement::Expression(value) => (("_".to_string(), value.location()), value),
+            };
+            let value_type: Type = self.check_expression(value, &mut scope.clone())?;
+            let variant: Option<String> = match statement {
+                DoStatement::Let { .. } => None,
+                _ => self.bind_variant(&value_type),
+            };
+            let location: SourceLocation = name.1.merge(&value.location());
+            match (Checker::monad(&variant), &monad) {
+                (Some(name), None) => monad = Some(name),
+                (Some(name), Some(block)) if name != *block => {
+                    return Err(Checker::do_bind_mismatch(block, &value_type, location));
+                }
+                (None, _) if matches!(statement, DoStatement::Bind { .. }) => {
+                    plain_binds.push((location, value_type.clone()));
+                }
+                _ => {}
+            }
+            let type_: Type = match (&variant, &value_type) {
+                (Some(_), Type::Generic(_, _, arguments)) if !arguments.is_empty() => {
+                    arguments[0].clone()
+                }
+                (Some(_), _) => Type::Any(name.1.clone()),
+                (None, _) => value_type,
+            };
+            scope.variables.insert(
+                name.0.clone(),
+                Variable {
+                    name: name.0.clone(),
+                    location: name.1.clone(),
+                    type_,
+                    value: Some(value.clone()),
+                },
+            );
+            steps.push((statement.clone(), variant));
+        }
+
+        let mut body: Expression = match statements.last() {
+            Some(DoStatement::Expression(expression)) => expression.clone(),
+            _ => unreachable!("the parser guarantees a do block ends with an expression"),
+        };
+        if let Some(block) = monad {
+            if let Some((location, type_)) = plain_binds.into_iter().next() {
+                return Err(Checker::do_bind_mismatch(block, &type_, location));
+            }
+            let type_: Type = self.check_expression(&body, &mut scope.clone())?;
+            let variant: Option<String> = self.bind_variant(&type_);
+            if Checker::monad(&variant) != Some(block) {
+                return Err(HarmonyError::new(
+                    HarmonyErrorKind::Semantic,
+                    format!(
+                        "The last statement of a do block over '{}' must have type '{}', found '{}'",
+                        block, block, type_
+                    ),
+                    Some(format!(
+                        "Wrap it in '{}'",
+                        if block == "Maybe" { "Just" } else { "Success" }
+                    )),
+                    body.location(),
+                ));
+            }
+        }
+        for (i, (statement, variant)) in steps.into_iter().enumerate().rev() {
+            let (name, type_annotation, value) = match statement {
+                DoStatement::Bind { name, value } => (name, None, value),
+                DoStatement::Let {
+                    name,
+                    type_annotation,
+                    value,
+                } => (name, type_annotation, value),
+                DoStatement::Expression(value) => {
+                    (("_".to_string(), value.location()), None, value)
+                }
+            };
+            let location: SourceLocation = name.1.merge(&body.location());
+            body = match variant {
+                Some(variant) => {
+                    let temporary: (String, SourceLocation) =
+                        (format!("__do{}", i), name.1.clone());
+                    Expression::Let {
+                        name: temporary.clone(),
+                        type_annotation: None,
+                        value: Box::new(value),
+                        body: Box::new(Expression::PatternMatch {
+                            expression: Box::new(Expression::Identifier(
+                                temporary.0.clone(),
+                                temporary.1.clone(),
+                            )),
+                            cases: vec![PatternMatchCase {
+                                pattern: Expression::Call {
+                                    callee: (variant, name.1.clone()),
+                                    generic_arguments: vec![],
+                                    arguments: vec![Expression::Identifier(name.0, name.1.clone())],
+                                    argument_names: vec![None],
+                                    location: name.1,
+                                },
+                                directive: PatternMatchDirective::None,
+                                body,
+                            }],
+                            default_case: Some(Box::new(Expression::Identifier(
+                                temporary.0,
+                                temporary.1,
+                            ))),
+                            location: location.clone(),
+                        }),
+                        location,
+                    }
+                }
+                None => Expression::Let {
+                    name,
+                    type_annotation,
+                    value: Box::new(value),
+                    body: Box::new(body),
+                    location,
+                },
+            };
+        }
+        Ok(body)
+    }
+
+    /// The monad a `bind_variant` unwraps.
+    fn monad(variant: &Option<String>) -> Option<&'static str> {
+        match variant.as_deref() {
+            Some("Just") => Some("Maybe"),
+            Some("Success") => Some("Result"),
+            _ => None,
+        }
+    }
+
+    fn do_bind_mismatch(block: &str, type_: &Type, location: SourceLocation) -> HarmonyError {
+        HarmonyError::new(
+            HarmonyErrorKind::Semantic,
+            format!(
+                "Cannot bind a value of type '{}' in a do block over '{}'",
+                type_, block
+            ),
+            Some("Every statement bound in a do block has to be of the same monad".to_string()),
+            location,
+        )
+    }
+
+    fn bind_variant(&self, type_: &Type) -> Option<String> {
+        let name: &String = match type_ {
+            Type::Generic(name, _, _)
+            | Type::GenericEnum(name, _, _)
+            | Type::Enum(name, _)
+            | Type::Identifier(name, _) => name,
+            _ => return None,
+        };
+        let variant: &str = match name.as_str() {
+            "Maybe" => "Just",
+            "Result" => "Success",
+            _ => return None,
+        };
+        if self.global_scope.enum_variants.contains_key(variant) {
+            return Some(variant.to_string());
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub location: SourceLocation,
+    pub definition: SourceLocation,
+    pub type_: Type,
+    pub local: bool,
 }
 
 #[derive(Debug, Clone)]
+pub enum Argument {
+    Given(Box<Expression>),
+    /// The callee evaluates the default value.
+    Default,
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Import {
     pub name: String,
     pub alias: Option<String>,
     pub exposing: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Enum {
     pub name: String,
     pub variants: Vec<EnumVariant>,
     pub location: SourceLocation,
     pub generic_parameters: Vec<Type>,
+    pub doc: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Function {
     pub name: String,
     pub location: SourceLocation,
     pub generic_parameters: Vec<Type>,
+    pub constraints: Vec<Constraint>,
     pub parameters: Vec<Parameter>,
     pub return_type: Type,
     pub body: Option<Expression>,
     pub local_scope: LocalScope,
     pub is_external: bool,
+    pub doc: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Variable {
     pub name: String,
     pub location: SourceLocation,
     pub type_: Type,
     pub value: Option<Expression>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeAlias {
+    pub name: String,
+    pub generic_parameters: Vec<String>,
+    pub type_: Type,
+    pub location: SourceLocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Newtype {
+    pub name: String,
+    pub type_: Type,
+    pub location: SourceLocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Class {
+    pub name: String,
+    pub parameter: String,
+    pub methods: Vec<(String, Type)>,
+    pub location: SourceLocation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instance {
+    pub class: String,
+    pub type_: Type,
+    pub module: Option<String>,
+    pub location: SourceLocation,
+}
@@ -1,7 +1,9 @@
 use crate::{
-    ast::{EnumVariant, Expression, PatternMatchDirective, Statement},
-    checker::Checker,
-    token::TokenKind,
+    ast::{
+        EnumVariant, Expression, Parameter, PatternMatchDirective, Position, Statement, StringPart,
+    },
+    checker::{Argument, Checker},
+    token::{SourceLocation, TokenKind},
 };
 
 #[derive(Debug, Clone)]
@@ -9,6 +11,9 @@ pub struct Codegen {
     pub statements: Vec<Statement>,
     pub checker: Checker,
     pub names: Vec<String>,
+    /// Whether to export the module's tests for `harmony test`, instead of
+    /// running `main`.
+    pub test: bool,
 }
 
 impl Codegen {
@@ -17,6 +22,7 @@ impl Codegen {
             statements: statements.clone(),
             checker: checker.clone(),
             names: Vec::new(),
+            test: false,
         }
     }
 
@@ -26,7 +32,90 @@ impl Codegen {
         for statement in self.statements.clone() {
             code.push_str(self.generate_statement(&statement).as_str());
         }
+        if self.test {
+            code.push_str(self.generate_tests().as_str());
+        }
+
+        self.generate_builtin_dictionaries() + code.as_str()
+    }
 
+    /// Exports the module's tests as `__tests`. Each test runs to an object
+    /// telling whether it passed, and when it is an `==` comparison, both
+    /// sides so a failure can show them. That comparison is structural unless
+    /// it goes through an `Eq` instance, so lists and variants compare by
+    /// their contents.
+    fn generate_tests(&mut self) -> String {
+        let mut code: String = String::from(
+            "import { isDeepStrictEqual } from \"node:util\";\nexport const __tests = [\n",
+        );
+        for statement in self.statements.clone() {
+            let Statement::Test { name, body } = statement else {
+                continue;
+            };
+            let run: String = match &body {
+                Expression::Binary {
+                    left,
+                    operator: TokenKind::DoubleEquals,
+                    operator_location,
+                    right,
+                } => {
+                    let comparison: String = match self.dictionaries_at(operator_location) {
+                        Some(dictionary) => format!("{}.eq(__left, __right)", dictionary[0]),
+                        None => "isDeepStrictEqual(__left, __right)".to_string(),
+                    };
+                    format!(
+                        "() => {{ const __left = {}; const __right = {}; return {{ passed: {}, left: __left, right: __right }}; }}",
+                        self.generate_expression(left),
+                        self.generate_expression(right),
+                        comparison
+                    )
+                }
+                _ => format!("() => ({{ passed: {} }})", self.generate_expression(&body)),
+            };
+            code.push_str(
+                format!(
+                    "    {{ name: \"{}\", location: \"{}\", run: {} }},\n",
+                    name.0,
+                    name.1.to_string().replace('\\', "/"),
+                    run
+                )
+                .as_str(),
+            );
+        }
+        code.push_str("];\n");
+        code
+    }
+
+    /// Emits the dictionaries of the built-in instances this module uses.
+    fn generate_builtin_dictionaries(&self) -> String {
+        let mut code: String = String::new();
+        let mut generated: Vec<String> = Vec::new();
+        for instance in &self.checker.global_scope.instances {
+            if instance.module.is_some() {
+                continue;
+            }
+            let key: String = Checker::type_key(&instance.type_).unwrap();
+            let name: String = Checker::dictionary_name(&instance.class, &key);
+            let used: bool = self
+                .checker
+                .dictionaries
+                .iter()
+                .any(|(_, dictionaries)| dictionaries.contains(&name));
+            if !used || generated.contains(&name) {
+                continue;
+            }
+            let methods: &str = match instance.class.as_str() {
+                "Eq" => "eq: (a, b) => a === b",
+                "Ord" => "compare: (a, b) => (a < b ? -1 : a > b ? 1 : 0)",
+                "Show" => "show: (x) => String(x)",
+                "Num" => {
+                    "add: (a, b) => a + b, sub: (a, b) => a - b, mul: (a, b) => a * b, div: (a, b) => a / b"
+                }
+                _ => unreachable!(),
+            };
+            code.push_str(format!("const {} = {{ {} }};\n", name, methods).as_str());
+            generated.push(name);
+        }
         code
     }
 
@@ -46,20 +135,32 @@ impl Codegen {
                 let mut imported: Vec<String> = Vec::new();
                 for import in self.checker.global_scope.imports.clone() {
                     if import.name == full_name.clone() && !imported.contains(&full_name.clone()) {
-                        let full_path = self
-                            .checker
-                            .get_path_of_import(&import)
-                            .unwrap()
-                            .replace(".harm", ".mjs");
+                        let full_path =
+                            self.checker.import_paths[&import.name].replace(".harm", ".mjs");
                         if exposing.len() > 0 {
+                            // Aliases and newtypes only exist in the checker.
+                            let mut names: Vec<String> = exposing
+                                .iter()
+                                .map(|(name, _)| name.to_string())
+                                .filter(|name| {
+                                    !self.checker.global_scope.type_aliases.contains_key(name)
+                                        && !self.checker.global_scope.newtypes.contains_key(name)
+                                })
+                                .collect::<Vec<String>>();
+                            // Instances are always visible to importers, so
+                            // their dictionaries come along with the exposed names.
+                            for instance in &self.checker.global_scope.instances {
+                                if instance.module.as_ref() == Some(&full_name) {
+                                    names.push(Checker::dictionary_name(
+                                        &instance.class,
+                                        &Checker::type_key(&instance.type_).unwrap(),
+                                    ));
+                                }
+                            }
                             code.push_str(
                                 format!(
                                     "import {{ {} }} from \"file:///{}\";\n",
-                                    exposing
-                                        .iter()
-                                        .map(|(name, _)| name.to_string())
-                                        .collect::<Vec<String>>()
-                                        .join(", "),
+                                    names.join(", "),
                                     full_path
                                 )
                                 .as_str(),
@@ -92,7 +193,11 @@ impl Codegen {
                     .as_str(),
                 );
             }
-            Statement::Enum { name, variants } => {
+            Statement::Enum {
+                name,
+                variants,
+                doc: _,
+            } => {
                 code.push_str(format!("export const {} = {{\n", name.0.clone()).as_str());
                 for variant in variants {
                     match variant {
@@ -139,6 +244,7 @@ impl Codegen {
                 name,
                 generic_parameters: _,
                 variants,
+                doc: _,
             } => {
                 code.push_str(format!("export const {} = {{\n", name.0.clone()).as_str());
                 for variant in variants {
@@ -187,13 +293,14 @@ impl Codegen {
                 parameters,
                 return_type: _,
                 binding,
+                doc: _,
             } => {
                 let name: String = name.clone().0;
                 self.names.push(name.clone());
                 let binding: String = binding.clone().0;
                 let mut args: Vec<String> = Vec::new();
-                for (i, _) in parameters.iter().enumerate() {
-                    args.push(format!("arg{}", i));
+                for (i, parameter) in parameters.iter().enumerate() {
+                    args.push(self.generate_parameter(&format!("arg{}", i), parameter));
                 }
                 code.push_str(
                     format!("export var {} = ({}) => {{\n", name, args.join(", ")).as_str(),
@@ -201,6 +308,39 @@ impl Codegen {
                 code.push_str(format!("    return {};\n", binding.replace("%", "arg")).as_str());
                 code.push_str("}\n");
             }
+            Statement::Instance {
+                class,
+                type_,
+                methods,
+            } => {
+                let name: String =
+                    Checker::dictionary_name(&class.0, &Checker::type_key(type_).unwrap());
+                code.push_str(format!("export const {} = {{\n", name).as_str());
+                for method in methods {
+                    if let Statement::Function {
+                        name,
+                        parameters,
+                        body,
+                        ..
+                    } = method
+                    {
+                        let args: Vec<String> = parameters
+                            .iter()
+                            .map(|parameter| parameter.name.0.clone())
+                            .collect();
+                        code.push_str(
+                            format!(
+                                "    {}: ({}) => {},\n",
+                                name.0,
+                                args.join(", "),
+                                self.generate_expression(body)
+                            )
+                            .as_str(),
+                        );
+                    }
+                }
+                code.push_str("};\n");
+            }
             Statement::Function {
                 name,
                 parameters,
@@ -216,6 +356,10 @@ impl Codegen {
                 let name: String = name.clone().0;
                 self.names.push(name.clone());
                 if name == "main" && parameters.len() == 0 {
+                    // Tests run on their own, without the program.
+                    if self.test {
+                        return code;
+                    }
                     code.push_str("var main = (() => {\n");
                     code.push_str(
                         format!("    return {};\n", self.generate_expression(body)).as_str(),
@@ -224,8 +368,16 @@ impl Codegen {
                     return code;
                 }
                 let mut args: Vec<String> = Vec::new();
+                if let Statement::GenericFunction { constraints, .. } = statement {
+                    for constraint in constraints {
+                        args.push(Checker::dictionary_parameter(
+                            &constraint.class.0,
+                            &constraint.parameter,
+                        ));
+                    }
+                }
                 for parameter in parameters {
-                    args.push(format!("{}", parameter.name.0.clone()));
+                    args.push(self.generate_parameter(&parameter.name.0, parameter));
                 }
                 code.push_str(
                     format!("export var {} = ({}) => {{\n", name, args.join(", ")).as_str(),
@@ -243,17 +395,38 @@ impl Codegen {
             Expression::Binary {
                 left,
                 operator,
+                operator_location,
                 right,
             } => {
-                if let Expression::List(_) = *right.clone() {
+                if let Some(dictionary) = self.dictionaries_at(operator_location) {
+                    let left: String = self.generate_expression(left);
+                    let right: String = self.generate_expression(right);
+                    let dictionary: &String = &dictionary[0];
+                    return match operator {
+                        TokenKind::Plus => format!("{}.add({}, {})", dictionary, left, right),
+                        TokenKind::Minus => format!("{}.sub({}, {})", dictionary, left, right),
+                        TokenKind::Asterisk => format!("{}.mul({}, {})", dictionary, left, right),
+                        TokenKind::Slash => format!("{}.div({}, {})", dictionary, left, right),
+                        TokenKind::DoubleEquals => {
+                            format!("{}.eq({}, {})", dictionary, left, right)
+                        }
+                        TokenKind::NotEquals => format!("!{}.eq({}, {})", dictionary, left, right),
+                        _ => format!(
+                            "({}.compare({}, {}) {} 0)",
+                            dictionary, left, right, operator
+                        ),
+                    };
+                }
+                if let Expression::List(_, _) = *right.clone() {
                     if operator == &TokenKind::PlusPlus {
-                        let left: String = self.generate_expression(left);
+                        let left: String = self.generate_operand(left, &Position::Indexed);
                         let right: String = self.generate_expression(right);
                         return format!("{}.concat({})", left, right);
                     }
                 }
-                let left: String = self.generate_expression(left);
-                let right: String = self.generate_expression(right);
+                let left: String = self.generate_operand(left, &Position::Left(operator.clone()));
+                let right: String =
+                    self.generate_operand(right, &Position::Right(operator.clone()));
                 match operator {
                     TokenKind::Plus => format!("{} + {}", left, right),
                     TokenKind::Minus => format!("{} - {}", left, right),
@@ -269,34 +442,40 @@ impl Codegen {
                     TokenKind::And => format!("{} && {}", left, right),
                     TokenKind::Or => format!("{} || {}", left, right),
                     TokenKind::PlusPlus => format!("{} + {}", left, right),
+                    TokenKind::PipeForward => format!("({})({})", right, left),
+                    TokenKind::PipeBackward => format!("({})({})", left, right),
+                    TokenKind::ComposeForward => {
+                        format!("((...__args) => ({})(({})(...__args)))", right, left)
+                    }
+                    TokenKind::ComposeBackward => {
+                        format!("((...__args) => ({})(({})(...__args)))", left, right)
+                    }
                     _ => unreachable!(),
                 }
             }
+            Expression::Identifier(id, _) if self.is_newtype(id) => {
+                "((value) => value)".to_string()
+            }
             Expression::Identifier(id, _) => id.clone(),
             Expression::Char(value, _) => format!("'{}'", value),
             Expression::Integer(value, _) => value.clone().to_string(),
+            Expression::Float(value, _) => format!("{:?}", value),
             Expression::If {
                 condition,
                 then_branch,
                 else_branch,
+                ..
             } => {
                 let condition: String = self.generate_expression(condition);
                 let then_branch: String = self.generate_expression(then_branch);
                 let else_branch: String = self.generate_expression(else_branch);
                 format!("{} ? {} : {}", condition, then_branch, else_branch)
             }
-            Expression::Call {
-                callee, arguments, ..
+            Expression::Call { .. } => self.generate_call(expression),
+            Expression::Index {
+                expression, index, ..
             } => {
-                let callee: String = callee.0.clone();
-                let mut args: Vec<String> = Vec::new();
-                for arg in arguments {
-                    args.push(self.generate_expression(arg));
-                }
-                format!("{}({})", callee, args.join(", "))
-            }
-            Expression::Index { expression, index } => {
-                let expression: String = self.generate_expression(expression);
+                let expression: String = self.generate_operand(expression, &Position::Indexed);
                 let index: String = self.generate_expression(index);
                 format!("{}[{}]", expression, index)
             }
@@ -304,6 +483,7 @@ impl Codegen {
                 expression,
                 cases,
                 default_case,
+                ..
             } => {
                 let mut code: String = String::new();
                 code.push_str("(() => {\n");
@@ -359,6 +539,38 @@ impl Codegen {
                                 }
                                 PatternMatchDirective::None => {}
                             }
+                        } else if self.is_newtype(&callee.0) {
+                            // Newtypes are erased, so the pattern matches the
+                            // wrapped value itself.
+                            match &arguments[0] {
+                                Expression::Identifier(id, _) => {
+                                    code.push_str("    if (true) {\n");
+                                    if id != "_" {
+                                        code.push_str(
+                                            format!("        const {} = __condition;\n", id)
+                                                .as_str(),
+                                        );
+                                    }
+                                }
+                                argument => {
+                                    code.push_str(
+                                        format!(
+                                            "    if (__condition === {}) {{\n",
+                                            self.generate_expression(argument)
+                                        )
+                                        .as_str(),
+                                    );
+                                }
+                            }
+                            if let PatternMatchDirective::If(expression) = &directive {
+                                code.push_str(
+                                    format!(
+                                        "        if ({}) {{\n",
+                                        self.generate_expression(expression)
+                                    )
+                                    .as_str(),
+                                );
+                            }
                         } else {
                             code.push_str(
                                 format!(
@@ -383,13 +595,13 @@ impl Codegen {
                                 .as_str(),
                             );
                         }
-                    } else if let Expression::List(elements) = pattern.clone() {
+                    } else if let Expression::List(elements, _) = pattern.clone() {
                         if elements.len() == 0 {
                             code.push_str("    if (__condition.length === 0) {\n");
                         } else {
                             let mut conds: Vec<String> = Vec::new();
                             for (i, element) in elements.iter().enumerate() {
-                                if let Expression::Rest(_) = element {
+                                if let Expression::Rest(_, _) = element {
                                     conds.push(format!("__condition.length >= {}", i));
                                 } else {
                                     conds.push(format!("__condition[{}] !== undefined", i));
@@ -397,7 +609,7 @@ impl Codegen {
                             }
                             code.push_str(format!("    if ({}) {{\n", conds.join(" && ")).as_str());
                             for (i, element) in elements.iter().enumerate() {
-                                if let Expression::Rest(id_expr) = element {
+                                if let Expression::Rest(id_expr, _) = element {
                                     let id: String = self.generate_expression(id_expr);
                                     code.push_str(
                                         format!(
@@ -457,28 +669,24 @@ impl Codegen {
                 code
             }
             Expression::Access { name, member } => {
+                if let Expression::Call {
+                    callee: (id, _), ..
+                }
+                | Expression::Identifier(id, _) = &**member
+                {
+                    if self.is_newtype(id) {
+                        return self.generate_expression(member);
+                    }
+                }
                 let name: String = name.0.clone();
                 let member: String = match *member.clone() {
-                    Expression::Call {
-                        callee, arguments, ..
-                    } => {
-                        let mut code: String = String::new();
-                        code.push_str(format!("{}(", callee.0.clone()).as_str());
-                        for (i, argument) in arguments.iter().enumerate() {
-                            if i > 0 {
-                                code.push_str(", ");
-                            }
-                            code.push_str(self.generate_expression(argument).as_str());
-                        }
-                        code.push_str(")");
-                        code
-                    }
+                    Expression::Call { .. } => self.generate_call(member),
                     _ => self.generate_expression(&*member),
                 };
                 format!("{}.{}", name, member)
             }
             Expression::Bool(value, _) => value.clone().to_string(),
-            Expression::List(elements) => {
+            Expression::List(elements, _) => {
                 let mut code: String = String::new();
                 code.push_str("[");
                 for (i, element) in elements.iter().enumerate() {
@@ -491,7 +699,27 @@ impl Codegen {
                 code
             }
             Expression::String(value, _) => format!("\"{}\"", value),
-            Expression::Rest(name) => {
+            Expression::InterpolatedString(parts, _) => {
+                let mut code: String = String::new();
+                code.push('`');
+                for part in parts {
+                    match part {
+                        StringPart::Literal(value) => code.push_str(value),
+                        StringPart::Expression(expression, interpolation) => {
+                            let value: String = self.generate_expression(expression);
+                            match self.dictionaries_at(interpolation) {
+                                Some(dictionaries) => code.push_str(
+                                    format!("${{{}.show({})}}", dictionaries[0], value).as_str(),
+                                ),
+                                None => code.push_str(format!("${{{}}}", value).as_str()),
+                            }
+                        }
+                    }
+                }
+                code.push('`');
+                code
+            }
+            Expression::Rest(name, _) => {
                 let name: String = self.generate_expression(&*name);
                 format!("...{}", name)
             }
@@ -500,6 +728,7 @@ impl Codegen {
                 type_annotation: _,
                 value,
                 body,
+                ..
             } => {
                 let name: String = name.0.clone();
                 let value: String = self.generate_expression(value);
@@ -521,7 +750,160 @@ impl Codegen {
                 code.push_str(self.generate_expression(body).as_str());
                 code
             }
-            _ => todo!("generate_expression not implemented for {:?}", expression),
+            Expression::Do { location, .. } => {
+                let (_, desugared) = self
+                    .checker
+                    .do_blocks
+                    .iter()
+                    .find(|(block, _)| block == location)
+                    .unwrap()
+                    .clone();
+                self.generate_expression(&desugared)
+            }
+            Expression::Unary {
+                operator, right, ..
+            } => {
+                let right: String = self.generate_operand(right, &Position::Unary);
+                // `--` would decrement.
+                if right.starts_with('-') {
+                    format!("{} {}", operator, right)
+                } else {
+                    format!("{}{}", operator, right)
+                }
+            }
         }
     }
+
+    /// Generates an expression written at `position` in another one, in
+    /// parentheses where it needed them in Harmony, and around an `if`, as
+    /// the conditional operator it becomes binds looser than the others.
+    fn generate_operand(&mut self, expression: &Expression, position: &Position) -> String {
+        let code: String = self.generate_expression(expression);
+        if expression.needs_parentheses(position) || matches!(expression, Expression::If { .. }) {
+            format!("({})", code)
+        } else {
+            code
+        }
+    }
+
+    fn generate_call(&mut self, call: &Expression) -> String {
+        let (callee, arguments, argument_names) = match call {
+            Expression::Call {
+                callee,
+                arguments,
+                argument_names,
+                ..
+            } => (callee.0.clone(), arguments, argument_names),
+            _ => unreachable!(),
+        };
+        if self.is_newtype(&callee) {
+            return self.generate_expression(&arguments[0]);
+        }
+        let mut args: Vec<String> =
+            self.positional_arguments(call, &callee, arguments, argument_names);
+        if let Some(dictionaries) = self.dictionaries_at(&call.location()) {
+            if self
+                .checker
+                .global_scope
+                .class_methods
+                .contains_key(&callee)
+                && !self
+                    .checker
+                    .global_scope
+                    .function_names
+                    .contains_key(&callee)
+            {
+                return format!("{}.{}({})", dictionaries[0], callee, args.join(", "));
+            }
+            args.splice(0..0, dictionaries);
+        }
+        if self.is_partial_application(call) {
+            return self.generate_partial_application(&callee, args);
+        }
+        format!("{}({})", callee, args.join(", "))
+    }
+
+    /// Generates a parameter called `name`, with its default value when it has
+    /// one. Callers pass `undefined` for the parameters they leave out.
+    fn generate_parameter(&mut self, name: &str, parameter: &Parameter) -> String {
+        match &parameter.default {
+            Some(default) => format!("{} = {}", name, self.generate_expression(default)),
+            None => name.to_string(),
+        }
+    }
+
+    fn is_newtype(&self, name: &String) -> bool {
+        self.checker.global_scope.newtypes.contains_key(name)
+    }
+
+    fn dictionaries_at(&self, location: &SourceLocation) -> Option<Vec<String>> {
+        self.checker
+            .dictionaries
+            .iter()
+            .find(|(key, _)| key == location)
+            .map(|(_, dictionaries)| dictionaries.clone())
+    }
+
+    /// Generates the arguments of a call in parameter order, with named
+    /// arguments moved into place, so the emitted call is purely positional.
+    /// Omitted parameters are passed as `undefined`, which makes the callee
+    /// evaluate their default value in its own module.
+    fn positional_arguments(
+        &mut self,
+        call: &Expression,
+        callee: &str,
+        arguments: &[Expression],
+        argument_names: &[Option<(String, SourceLocation)>],
+    ) -> Vec<String> {
+        let function_id: Option<&usize> = match call {
+            Expression::Call { callee, .. } if self.is_local(&callee.1) => None,
+            _ => self.checker.global_scope.function_names.get(callee),
+        };
+        let resolved: Vec<Argument> = match function_id {
+            Some(function_id) => Checker::resolve_arguments(
+                &self.checker.global_scope.functions[function_id],
+                arguments,
+                argument_names,
+                &call.location(),
+            )
+            .unwrap(),
+            None => arguments
+                .iter()
+                .map(|argument| Argument::Given(Box::new(argument.clone())))
+                .collect(),
+        };
+        let given: usize = resolved
+            .iter()
+            .rposition(|argument| matches!(argument, Argument::Given(_)))
+            .map_or(0, |last| last + 1);
+        let mut args: Vec<String> = Vec::new();
+        for argument in &resolved[..given] {
+            match argument {
+                Argument::Given(argument) => args.push(self.generate_expression(argument)),
+                Argument::Default => args.push("undefined".to_string()),
+                Argument::Missing => {}
+            }
+        }
+        args
+    }
+
+    /// Whether the name at `location` refers to a local rather than a global.
+    fn is_local(&self, location: &SourceLocation) -> bool {
+        self.checker
+            .references
+            .iter()
+            .any(|reference| reference.location == *location && reference.local)
+    }
+
+    fn is_partial_application(&self, call: &Expression) -> bool {
+        self.checker.partial_applications.contains(&call.location())
+    }
+
+    fn generate_partial_application(&self, callee: &str, args: Vec<String>) -> String {
+        // `bind` evaluates the supplied arguments once and works the same for
+        // Harmony functions and `foreign fun` wrappers.
+        let mut bound: Vec<String> = vec!["null".to_string()];
+        bound.extend(args);
+        format!("{}.bind({})", callee, bound.join(", "))
+    }
 }
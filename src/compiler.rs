@@ -1,22 +1,85 @@
-use std::{collections::HashMap, ops::ControlFlow, path::PathBuf, process::Command, time::Instant};
+use std::{
+    collections::HashMap,
+    ops::ControlFlow,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
     ast::Statement,
+    cache::{self, Cache, CacheEntry, Dependency},
     checker::{Checker, Import, Scope},
     codegen::Codegen,
+    dump::Dump,
     error::{HarmonyError, HarmonyErrorKind},
+    log::{Level, Logger},
     parser::Parser,
-    token::{Token, TokenKind},
+    resolver::{FileResolver, ModuleResolver},
+    token::{SourceLocation, Token, TokenKind},
     tokenizer::Tokenizer,
 };
 
+/// As deep as the main thread's, since checking recurses.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct CompiledModule {
+    pub name: String,
+    pub path: String,
+    pub js: String,
+}
+
 #[derive(Debug, Clone)]
+pub struct Output {
+    /// Dependencies first, so the compiled module comes last.
+    pub modules: Vec<CompiledModule>,
+    pub warnings: Vec<HarmonyError>,
+}
+
+/// Compiles `source` to JavaScript in memory, reading imports only through
+/// `resolver`.
+pub fn compile_source<R: ModuleResolver + 'static>(
+    path: &str,
+    source: &str,
+    resolver: R,
+) -> Result<Output, Vec<HarmonyError>> {
+    let mut options: CompilerOptions = CompilerOptions::new(vec![]);
+    options.level = Level::Silent;
+    let mut compiler: Compiler = Compiler::new(&options, &vec![], vec![]);
+    compiler.resolver = Box::new(resolver);
+    let _ = compiler.compile_root(&path.to_string(), Some(source.to_string()));
+    if !compiler.errors.is_empty() {
+        let mut diagnostics: Vec<HarmonyError> = compiler.warnings;
+        diagnostics.extend(compiler.errors);
+        return Err(diagnostics);
+    }
+    Ok(Output {
+        modules: compiler.modules,
+        warnings: compiler.warnings,
+    })
+}
+
 pub struct Compiler {
     pub args: Vec<String>,
     pub options: CompilerOptions,
     pub files: Vec<String>,
-    pub root: PathBuf,
+    pub resolver: Box<dyn ModuleResolver>,
     pub compiled_files: HashMap<String, Scope>,
+    pub modules: Vec<CompiledModule>,
+    pub errors: Vec<HarmonyError>,
+    pub warnings: Vec<HarmonyError>,
+    /// Every file read so far, for `--watch`.
+    pub sources: Vec<String>,
+    pub timings: Vec<Timing>,
+    pub logger: Logger,
+    pub cache: Option<Cache>,
+    /// The chain of imports being checked, to catch cycles.
+    importing: Vec<String>,
 }
 
 impl Compiler {
@@ -25,37 +88,219 @@ impl Compiler {
             args: args.clone(),
             options: options.clone(),
             files: files.clone(),
-            root: std::env::current_dir().unwrap(),
+            resolver: Box::new(FileResolver::on_disk(&options.root)),
             compiled_files: HashMap::new(),
+            modules: vec![],
+            errors: vec![],
+            warnings: vec![],
+            sources: vec![],
+            timings: vec![],
+            logger: Logger::new(options.level),
+            cache: None,
+            importing: vec![],
         }
     }
 
-    pub fn compile(&mut self) {
+    /// Returns the exit code of the last program that failed, or 1 if a
+    /// file didn't compile.
+    pub fn compile(&mut self) -> i32 {
+        let mut code: i32 = 0;
         for file in self.files.clone() {
-            if let ControlFlow::Break(_) = self.compile_file(&file, false) {
+            if let ControlFlow::Break(_) = self.compile_file(&file) {
+                code = 1;
                 continue;
             }
+            self.write();
+            match self.run(&file) {
+                0 => {}
+                failed => code = failed,
+            }
         }
+        self.print_timings();
 
-        for file in self.compiled_files.keys() {
-            if !self.options.keep {
-                std::fs::remove_file(file.clone().replace(".harm", ".mjs")).unwrap();
-            }
+        if !self.options.keep {
+            self.clean();
         }
+        code
     }
 
-    fn compile_file(&mut self, file: &String, is_import: bool) -> ControlFlow<()> {
-        let now: Instant = Instant::now();
+    /// Leaves each module's scope in `compiled_files`.
+    pub fn check(&mut self) {
+        for file in self.files.clone() {
+            let _ = self.compile_file(&file);
+        }
+        self.print_timings();
+    }
+
+    pub fn build(&mut self) {
+        self.check();
+        self.write();
+    }
+
+    pub fn write(&self) {
+        for module in &self.modules {
+            std::fs::write(module.path.replace(".harm", ".mjs"), &module.js).unwrap();
+        }
+    }
+
+    pub fn clean(&self) {
+        for module in &self.modules {
+            let _ = std::fs::remove_file(module.path.replace(".harm", ".mjs"));
+        }
+    }
+
+    pub fn check_module(
+        &mut self,
+        file: &String,
+        statements: &Vec<Statement>,
+    ) -> Result<Checker, HarmonyError> {
+        let mut modules: Vec<Module> = vec![];
+        self.importing.push(file.clone());
+        let (imports, error) = self.discover_imports(file, import_names(statements), &mut modules);
+        self.importing.pop();
+        self.compile_layers(modules);
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let mut checker: Checker =
+            importing_checker(file, statements, &imports, &self.compiled_files);
+        let check_result: Result<(), HarmonyError> = checker.analyze();
+        self.compiled_files
+            .insert(file.clone(), checker.global_scope.clone());
+        check_result?;
+        Ok(checker)
+    }
+
+    fn fail(&mut self, error: HarmonyError) {
+        self.logger.error(error.to_string());
+        self.errors.push(error);
+    }
+
+    fn compile_file(&mut self, file: &String) -> ControlFlow<()> {
+        self.compile_root(file, None)
+    }
+
+    /// Breaks if the module at `file` itself didn't compile.
+    fn compile_root(&mut self, file: &String, source: Option<String>) -> ControlFlow<()> {
         if self.compiled_files.contains_key(file) {
-            println!("Already compiled {}", file);
+            self.logger.trace(format!("Already compiled {}", file));
             return ControlFlow::Continue(());
         }
-        println!("Compiling {}..", file);
-        let source: String = std::fs::read_to_string(file).unwrap();
-        println!(" -> Tokenizing {}..", file);
-        let mut tokenizer: Tokenizer = Tokenizer::new(file, &source);
+        let mut modules: Vec<Module> = vec![];
+        self.discover(file, source, &mut modules);
+        self.compile_layers(modules);
+        if self.modules.iter().any(|module| module.path == *file) {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+
+    /// Adds `file` to `modules` after the modules it imports. A module whose
+    /// source is unchanged since it was cached isn't parsed.
+    fn discover(&mut self, file: &String, source: Option<String>, modules: &mut Vec<Module>) {
+        self.sources.push(file.clone());
+        let mut module: Module = Module::new(file);
+        let source: String = match source.map_or_else(|| self.resolver.read(file), Ok) {
+            Ok(source) => source,
+            Err(error) => {
+                self.fail(HarmonyError::new(
+                    HarmonyErrorKind::CompileTime,
+                    format!("Could not read {}: {}", file, error),
+                    None,
+                    SourceLocation {
+                        file: file.clone(),
+                        start: 0,
+                        end: 0,
+                        line: 0,
+                        column: 0,
+                    },
+                ));
+                module.failed = true;
+                modules.push(module);
+                return;
+            }
+        };
+
+        let mut imports: Vec<(String, SourceLocation)> = vec![];
+        if let Some(entry) = self.load_cached(file, &source) {
+            imports = entry
+                .dependencies
+                .iter()
+                .map(|dependency| (dependency.name.clone(), dependency.location.clone()))
+                .collect();
+            module.entry = Some(entry);
+        } else {
+            self.parse(&mut module, &source);
+            if let Some(statements) = &module.statements {
+                imports = import_names(statements);
+            }
+        }
+        module.source = source;
+
+        self.importing.push(file.clone());
+        let (imports, error) = self.discover_imports(file, imports, modules);
+        self.importing.pop();
+        module.imports = imports;
+        module.error = error;
+        modules.push(module);
+    }
+
+    /// Stops at the first import that isn't found or that imports `file`
+    /// back.
+    #[allow(clippy::type_complexity)]
+    fn discover_imports(
+        &mut self,
+        file: &str,
+        imports: Vec<(String, SourceLocation)>,
+        modules: &mut Vec<Module>,
+    ) -> (Vec<(String, SourceLocation, String)>, Option<HarmonyError>) {
+        let mut resolved: Vec<(String, SourceLocation, String)> = vec![];
+        for (name, location) in imports {
+            let Some(path) = self.resolver.resolve(&name, file) else {
+                let error: HarmonyError = HarmonyError::new(
+                    HarmonyErrorKind::CompileTime,
+                    format!("Module {} not found", name),
+                    None,
+                    location,
+                );
+                return (resolved, Some(error));
+            };
+            if let Some(start) = self
+                .importing
+                .iter()
+                .position(|importing| *importing == path)
+            {
+                let mut cycle: Vec<String> = self.importing[start..].to_vec();
+                cycle.push(path);
+                let error: HarmonyError = HarmonyError::new(
+                    HarmonyErrorKind::CompileTime,
+                    format!("Import cycle: {}", cycle.join(" -> ")),
+                    None,
+                    location,
+                );
+                return (resolved, Some(error));
+            }
+            if !self.compiled_files.contains_key(&path)
+                && !modules.iter().any(|module| module.path == path)
+            {
+                self.discover(&path, None, modules);
+            }
+            resolved.push((name, location, path));
+        }
+        (resolved, None)
+    }
+
+    fn parse(&mut self, module: &mut Module, source: &str) {
+        let file: &String = &module.path;
+        self.logger.verbose(format!("Compiling {}..", file));
+        self.logger.trace(format!(" -> Tokenizing {}..", file));
+        let now: Instant = Instant::now();
+        let mut tokenizer: Tokenizer = Tokenizer::new(file, &source.to_string());
         let tokens: Vec<Token> = tokenizer.tokenize();
-        let mut syntax_errors: Vec<HarmonyError> = vec![];
+        module.timing.tokenize = now.elapsed();
+        let mut syntax_errors: Vec<HarmonyError> = tokenizer.errors.clone();
         for token in &tokens {
             if token.kind == TokenKind::Unknown {
                 syntax_errors.push(HarmonyError::new(
@@ -66,104 +311,278 @@ impl Compiler {
                 ));
             }
         }
-        if syntax_errors.len() > 0 {
+        if !syntax_errors.is_empty() {
             for error in syntax_errors {
-                println!("{}", error.to_string());
+                self.fail(error);
             }
-            return ControlFlow::Break(());
+            module.failed = true;
+            return;
         }
-        println!(" -> Parsing {}..", file);
+        self.logger.trace(format!(" -> Parsing {}..", file));
+        let now: Instant = Instant::now();
         let mut parser: Parser = Parser::new(tokens);
         let statements: Result<Vec<Statement>, HarmonyError> = parser.parse();
-        match statements.clone() {
-            Ok(_) => {}
+        module.timing.parse = now.elapsed();
+        for warning in &parser.warnings {
+            self.logger.normal(warning.to_string());
+        }
+        module.warnings = parser.warnings.clone();
+        self.warnings.extend(parser.warnings);
+        match statements {
+            Ok(statements) => module.statements = Some(statements),
             Err(error) => {
-                println!("{}", error.to_string());
-                return ControlFlow::Break(());
-            }
-        }
-        println!(" -> Checking {}..", file);
-        let mut checker: Checker = Checker::new(&self, &statements.clone().unwrap(), file);
-        let imports: Result<Vec<Import>, HarmonyError> = checker.lookup_imports();
-        match imports {
-            Ok(imports) => {
-                let mut scopes: Vec<Scope> = vec![];
-                for import in imports.clone() {
-                    checker.global_scope.imports.push(import.clone());
-                    let full_path: Result<String, HarmonyError> =
-                        checker.get_path_of_import(&import.clone());
-                    if let Err(error) = full_path {
-                        println!("{}", error.to_string());
-                        return ControlFlow::Break(());
-                    }
-                    let full_path: String = full_path.unwrap();
-                    if !self.compiled_files.contains_key(&full_path) {
-                        if let ControlFlow::Break(_) = self.compile_file(&full_path, true) {
-                            continue;
-                        }
-                    }
-                    let scope: Scope = self.compiled_files.get(&full_path).unwrap().clone();
-                    scopes.push(scope);
+                self.fail(error);
+                module.failed = true;
+            }
+        }
+    }
+
+    /// The modules of a layer only import earlier layers, so each layer is
+    /// compiled in parallel.
+    fn compile_layers(&mut self, mut modules: Vec<Module>) {
+        let mut layers: Vec<usize> = vec![];
+        for module in &modules {
+            let layer: usize = module
+                .imports
+                .iter()
+                .filter_map(|(_, _, path)| modules.iter().position(|module| module.path == *path))
+                .map(|index| layers[index] + 1)
+                .max()
+                .unwrap_or(0);
+            layers.push(layer);
+        }
+
+        let count: usize = layers.iter().max().map_or(0, |layer| layer + 1);
+        for layer in 0..count {
+            let mut jobs: Vec<Module> = vec![];
+            for (index, module) in modules.iter_mut().enumerate() {
+                if layers[index] != layer || module.failed {
+                    continue;
+                }
+                if let Some(error) = module.error.take() {
+                    self.fail(error);
+                    continue;
                 }
-                checker = Checker::new(&self, &statements.clone().unwrap(), file);
-                for import in imports {
-                    checker.global_scope.imports.push(import);
+                if self.use_cached(module) {
+                    continue;
                 }
-                for scope in scopes {
-                    checker.global_scope.merge(&scope);
+                if module.statements.is_none() {
+                    let source: String = std::mem::take(&mut module.source);
+                    self.parse(module, &source);
+                    module.source = source;
+                    if module.failed {
+                        continue;
+                    }
                 }
+                let path: String = module.path.clone();
+                jobs.push(std::mem::replace(module, Module::new(&path)));
             }
-            Err(error) => {
-                println!("{}", error.to_string());
-                return ControlFlow::Break(());
+
+            let test: bool = self.options.test;
+            let compiled_files: &HashMap<String, Scope> = &self.compiled_files;
+            let results: Vec<Checked> = parallel(&jobs, |module| {
+                check_and_generate(module, compiled_files, test)
+            });
+            for (mut module, checked) in jobs.into_iter().zip(results) {
+                self.logger.trace(format!(" -> Checking {}..", module.path));
+                self.compiled_files
+                    .insert(module.path.clone(), checked.scope.clone());
+                module.timing.check = checked.check;
+                module.timing.codegen = checked.codegen;
+                if let Some(error) = checked.error {
+                    self.fail(error);
+                    self.timings.push(module.timing);
+                    continue;
+                }
+                self.logger.trace(format!(" -> Codegen {}..", module.path));
+                let js: String = checked.js.unwrap();
+                self.save_cached(&module, &checked.scope, &js);
+                self.modules.push(CompiledModule {
+                    name: checked.scope.module.clone().unwrap().0,
+                    path: module.path.clone(),
+                    js,
+                });
+                self.logger.verbose(format!(
+                    "Compiled {} in {:?}!",
+                    module.path,
+                    module.timing.total()
+                ));
+                self.timings.push(module.timing);
             }
         }
+    }
 
-        let check_result: Result<(), HarmonyError> = checker.analyze();
-        self.compiled_files
-            .insert(file.clone(), checker.global_scope.clone());
+    fn load_cached(&self, file: &String, source: &str) -> Option<CacheEntry> {
+        let entry: Option<CacheEntry> =
+            self.cache
+                .as_ref()?
+                .load(file, cache::hash(source), self.options.test);
+        if entry.is_none() {
+            self.logger.verbose(format!("Cache miss {}", file));
+        }
+        entry
+    }
 
-        match check_result {
-            Ok(_) => {}
-            Err(error) => {
-                println!("{}", error.to_string());
-                return ControlFlow::Break(());
+    /// Only if its imports kept the interfaces they had when it was cached.
+    fn use_cached(&mut self, module: &mut Module) -> bool {
+        let Some(entry) = module.entry.take() else {
+            return false;
+        };
+        let mut imports: Vec<&Scope> = vec![];
+        for dependency in &entry.dependencies {
+            match self.compiled_files.get(&dependency.path) {
+                Some(scope) if cache::interface(scope) == dependency.interface => {
+                    imports.push(scope)
+                }
+                _ => {
+                    self.logger.verbose(format!("Cache miss {}", module.path));
+                    return false;
+                }
             }
         }
+        let scope: Scope = cache::restore(&entry.scope, &imports);
 
-        println!(" -> Codegen {}..", file);
-
-        let mut codegen: Codegen = Codegen::new(&statements.unwrap(), &checker);
-        let code: String = codegen.generate();
+        self.logger.verbose(format!("Cache hit {}", module.path));
+        for warning in &entry.warnings {
+            self.logger.normal(warning.to_string());
+        }
+        self.warnings.extend(entry.warnings);
+        self.modules.push(CompiledModule {
+            name: entry.scope.module.clone().unwrap().0,
+            path: module.path.clone(),
+            js: entry.js,
+        });
+        self.compiled_files.insert(module.path.clone(), scope);
+        module.timing.cached = true;
+        self.timings.push(module.timing.clone());
+        true
+    }
 
-        std::fs::write(file.clone().replace(".harm", ".mjs"), code).unwrap();
-        println!("Compiled {} in {:?}!", file, now.elapsed());
+    /// Nothing is saved after an error, which could leave a stale import.
+    fn save_cached(&self, module: &Module, scope: &Scope, js: &str) {
+        let Some(cache) = &self.cache else {
+            return;
+        };
+        if !self.errors.is_empty() {
+            return;
+        }
+        let mut dependencies: Vec<Dependency> = vec![];
+        for (name, location, path) in &module.imports {
+            let Some(scope) = self.compiled_files.get(path) else {
+                return;
+            };
+            dependencies.push(Dependency {
+                name: name.clone(),
+                location: location.clone(),
+                path: path.clone(),
+                interface: cache::interface(scope),
+            });
+        }
+        cache.save(
+            &module.path,
+            &CacheEntry {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                source: cache::hash(&module.source),
+                test: self.options.test,
+                dependencies,
+                scope: cache::declared(scope, &module.path),
+                js: js.to_string(),
+                warnings: module.warnings.clone(),
+            },
+        );
+    }
 
-        if is_import {
-            return ControlFlow::Continue(());
+    fn print_timings(&self) {
+        if !self.options.timings {
+            return;
         }
+        // Timings are asked for explicitly, so `--quiet` keeps them.
+        let print = |line: String| self.logger.log(Level::Quiet, line);
+        let width: usize = self
+            .timings
+            .iter()
+            .map(|timing| timing.path.len())
+            .max()
+            .unwrap_or(0)
+            .max("module".len());
+        print(format!(
+            "{:<width$}  {:>10}  {:>10}  {:>10}  {:>10}",
+            "module", "tokenize", "parse", "check", "codegen"
+        ));
+        for timing in &self.timings {
+            if timing.cached {
+                print(format!("{:<width$}  {:>10}", timing.path, "cached"));
+                continue;
+            }
+            print(format!(
+                "{:<width$}  {:>10}  {:>10}  {:>10}  {:>10}",
+                timing.path,
+                format!("{:.2?}", timing.tokenize),
+                format!("{:.2?}", timing.parse),
+                format!("{:.2?}", timing.check),
+                format!("{:.2?}", timing.codegen)
+            ));
+        }
+    }
 
-        let mut command: Command = Command::new("node");
-        command.arg("--harmony");
-        command.arg("--use-strict");
-        command.arg(file.clone().replace(".harm", ".mjs"));
+    pub fn command(&self, file: &str) -> Command {
+        let runtime: &str = &self.options.runtime;
+        let mut command: Command = Command::new(runtime);
+        match Path::new(runtime)
+            .file_stem()
+            .and_then(|name| name.to_str())
+        {
+            Some("node") => {
+                command.arg("--harmony");
+                command.arg("--use-strict");
+            }
+            Some("deno") => {
+                command.arg("run");
+                command.arg("--allow-all");
+            }
+            Some("bun") => {
+                command.arg("run");
+            }
+            _ => {}
+        }
+        command.arg(file.replace(".harm", ".mjs"));
         for arg in self.args.clone() {
             command.arg(arg);
         }
-        if command.output().unwrap().status.success() {
-            println!(
-                " => {}",
-                String::from_utf8(command.output().unwrap().stdout).unwrap()
-            );
-            return ControlFlow::Continue(());
-        } else {
-            println!(
-                " => {}",
-                String::from_utf8(command.output().unwrap().stderr).unwrap()
-            );
-        }
+        command
+    }
 
-        ControlFlow::Continue(())
+    pub fn spawn(&self, file: &str) -> Result<Child, String> {
+        self.command(file)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|error| match error.kind() {
+                std::io::ErrorKind::NotFound => format!(
+                    "Could not find the JavaScript runtime '{}'. Install it or choose another one with --runtime node|deno|bun|<path>",
+                    self.options.runtime
+                ),
+                _ => format!("Could not run {}: {}", self.options.runtime, error),
+            })
+    }
+
+    fn run(&self, file: &str) -> i32 {
+        let mut child: Child = match self.spawn(file) {
+            Ok(child) => child,
+            Err(message) => {
+                self.logger.error(message);
+                return 1;
+            }
+        };
+        match child.wait() {
+            Ok(status) => status.code().unwrap_or(1),
+            Err(error) => {
+                self.logger
+                    .error(format!("Could not run {}: {}", self.options.runtime, error));
+                1
+            }
+        }
     }
 }
 
@@ -171,26 +590,67 @@ impl Compiler {
 pub struct CompilerOptions {
     pub keep: bool,
     pub output: Option<String>,
-    pub verbose: bool,
+    pub level: Level,
+    pub no_cache: bool,
+    /// `node`, `deno`, `bun` or the path of an executable.
+    pub runtime: String,
+    pub dump: Option<Dump>,
+    pub timings: bool,
+    /// Compile each module's tests instead of `main`.
+    pub test: bool,
+    /// The directory relative paths and the `runtime` directory are found
+    /// in, the working directory unless it can't be read.
+    pub root: PathBuf,
 }
 
 impl CompilerOptions {
     pub fn new(options: Vec<String>) -> CompilerOptions {
         let mut keep = false;
         let mut output = None;
-        let mut verbose = false;
+        let mut level = Level::Normal;
+        let mut no_cache = false;
+        let mut timings = false;
+        let mut runtime = "node".to_string();
+        let mut dump = None;
 
-        for option in options {
+        let mut options = options.into_iter();
+        while let Some(option) = options.next() {
             match option.as_str() {
                 "-k" | "--keep" => {
                     keep = true;
                 }
                 "-o" | "--output" => {
-                    output = Some(option);
+                    output = options.next();
+                }
+                "--runtime" => match options.next() {
+                    Some(name) => runtime = name,
+                    None => {
+                        println!("Expected a runtime after --runtime");
+                        std::process::exit(1);
+                    }
+                },
+                "-q" | "--quiet" => {
+                    level = Level::Quiet;
                 }
                 "-v" | "--verbose" => {
-                    verbose = true;
+                    level = Level::Verbose;
+                }
+                "-vv" | "--trace" => {
+                    level = Level::Trace;
                 }
+                "--no-cache" => {
+                    no_cache = true;
+                }
+                "--timings" => {
+                    timings = true;
+                }
+                _ if option.starts_with("--dump=") => match Dump::parse(&option[7..]) {
+                    Some(phase) => dump = Some(phase),
+                    None => {
+                        println!("Expected --dump=tokens|ast|ast-json|scope|js");
+                        std::process::exit(1);
+                    }
+                },
                 _ => {
                     println!("Unknown option: {}", option);
                     std::process::exit(1);
@@ -201,7 +661,174 @@ impl CompilerOptions {
         CompilerOptions {
             keep: keep,
             output: output,
-            verbose: verbose,
+            level,
+            no_cache,
+            runtime,
+            dump,
+            timings,
+            test: false,
+            root: std::env::current_dir().unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Timing {
+    pub path: String,
+    pub tokenize: Duration,
+    pub parse: Duration,
+    pub check: Duration,
+    pub codegen: Duration,
+    pub cached: bool,
+}
+
+impl Timing {
+    pub fn total(&self) -> Duration {
+        self.tokenize + self.parse + self.check + self.codegen
+    }
+}
+
+struct Module {
+    path: String,
+    source: String,
+    /// Never parsed if it comes from the cache.
+    statements: Option<Vec<Statement>>,
+    imports: Vec<(String, SourceLocation, String)>,
+    error: Option<HarmonyError>,
+    /// Already reported.
+    failed: bool,
+    warnings: Vec<HarmonyError>,
+    entry: Option<CacheEntry>,
+    timing: Timing,
+}
+
+impl Module {
+    fn new(path: &str) -> Module {
+        Module {
+            path: path.to_string(),
+            source: String::new(),
+            statements: None,
+            imports: vec![],
+            error: None,
+            failed: false,
+            warnings: vec![],
+            entry: None,
+            timing: Timing {
+                path: path.to_string(),
+                ..Timing::default()
+            },
+        }
+    }
+}
+
+struct Checked {
+    /// The module's scope, even if it failed to check.
+    scope: Scope,
+    error: Option<HarmonyError>,
+    js: Option<String>,
+    check: Duration,
+    codegen: Duration,
+}
+
+fn import_names(statements: &[Statement]) -> Vec<(String, SourceLocation)> {
+    statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Import { name, .. } => Some((
+                name.iter()
+                    .map(|(name, _)| name.to_string())
+                    .collect::<Vec<String>>()
+                    .join("."),
+                name[0].1.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+fn importing_checker(
+    file: &String,
+    statements: &Vec<Statement>,
+    imports: &[(String, SourceLocation, String)],
+    compiled_files: &HashMap<String, Scope>,
+) -> Checker {
+    let mut checker: Checker = Checker::new(statements, file);
+    let names: Vec<Import> = checker.lookup_imports().unwrap_or_default();
+    for (import, (_, _, path)) in names.into_iter().zip(imports) {
+        checker.global_scope.imports.push(import.clone());
+        checker
+            .import_paths
+            .insert(import.name.clone(), path.clone());
+        if let Some(scope) = compiled_files.get(path) {
+            checker.global_scope.merge(scope);
         }
     }
+    checker
+}
+
+fn check_and_generate(
+    module: &Module,
+    compiled_files: &HashMap<String, Scope>,
+    test: bool,
+) -> Checked {
+    let statements: &Vec<Statement> = module.statements.as_ref().unwrap();
+    let now: Instant = Instant::now();
+    let mut checker: Checker =
+        importing_checker(&module.path, statements, &module.imports, compiled_files);
+    let result: Result<(), HarmonyError> = checker.analyze();
+    let check: Duration = now.elapsed();
+    if let Err(error) = result {
+        return Checked {
+            scope: checker.global_scope,
+            error: Some(error),
+            js: None,
+            check,
+            codegen: Duration::ZERO,
+        };
+    }
+
+    let now: Instant = Instant::now();
+    let mut codegen: Codegen = Codegen::new(statements, &checker);
+    codegen.test = test;
+    let js: String = codegen.generate();
+    Checked {
+        scope: checker.global_scope.clone(),
+        error: None,
+        js: Some(js),
+        check,
+        codegen: now.elapsed(),
+    }
+}
+
+/// Returns the results in the order of `items`.
+fn parallel<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let workers: usize = std::thread::available_parallelism()
+        .map_or(1, |workers| workers.get())
+        .min(items.len());
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+    let next: AtomicUsize = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            std::thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, || loop {
+                    let index: usize = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result: R = f(item);
+                    results.lock().unwrap()[index] = Some(result);
+                })
+                .unwrap();
+        }
+    });
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
 }
@@ -0,0 +1,522 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::{
+    ast::{EnumVariant, Type},
+    checker::{Class, Enum, Function, Instance, Newtype, TypeAlias},
+    compiler::Compiler,
+};
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; }
+pre { background: #f4f4f4; padding: 0.5em; }
+a { color: #3465a4; text-decoration: none; }
+h3 { margin-bottom: 0; }";
+
+/// The API of every module the compiler has checked, rendered by `harmony doc`
+/// as one HTML page per module plus an `index.html` and an `index.json`.
+#[derive(Debug, Clone)]
+pub struct Documentation {
+    pub modules: Vec<ModuleDocumentation>,
+    /// The page and anchor of each documented type and class, used for
+    /// cross-links.
+    types: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModuleDocumentation {
+    pub name: String,
+    pub file: String,
+    pub enums: Vec<Enum>,
+    pub type_aliases: Vec<TypeAlias>,
+    pub newtypes: Vec<Newtype>,
+    pub classes: Vec<Class>,
+    pub instances: Vec<Instance>,
+    pub functions: Vec<Function>,
+}
+
+impl Documentation {
+    /// Collects the declarations of each compiled module from its scope. The
+    /// scopes also hold everything merged in from imports, so only what is
+    /// declared in the module's own file is kept.
+    pub fn new(compiler: &Compiler) -> Documentation {
+        let mut modules: Vec<ModuleDocumentation> = vec![];
+        for (file, scope) in &compiler.compiled_files {
+            let name: String = match &scope.module {
+                Some((name, _)) => name.clone(),
+                None => continue,
+            };
+            let mut enums: Vec<Enum> = scope
+                .enums
+                .values()
+                .filter(|enum_| enum_.location.file == *file)
+                .cloned()
+                .collect();
+            enums.sort_by_key(|enum_| enum_.location.line);
+            let mut type_aliases: Vec<TypeAlias> = scope
+                .type_aliases
+                .values()
+                .filter(|alias| alias.location.file == *file)
+                .cloned()
+                .collect();
+            type_aliases.sort_by_key(|alias| alias.location.line);
+            let mut newtypes: Vec<Newtype> = scope
+                .newtypes
+                .values()
+                .filter(|newtype| newtype.location.file == *file)
+                .cloned()
+                .collect();
+            newtypes.sort_by_key(|newtype| newtype.location.line);
+            let mut classes: Vec<Class> = scope
+                .classes
+                .values()
+                .filter(|class| class.location.file == *file)
+                .cloned()
+                .collect();
+            classes.sort_by_key(|class| class.location.line);
+            let mut instances: Vec<Instance> = scope
+                .instances
+                .iter()
+                .filter(|instance| instance.location.file == *file)
+                .cloned()
+                .collect();
+            instances.sort_by_key(|instance| instance.location.line);
+            let mut functions: Vec<Function> = scope
+                .functions
+                .values()
+                .filter(|function| function.location.file == *file)
+                .cloned()
+                .collect();
+            functions.sort_by_key(|function| function.location.line);
+            modules.push(ModuleDocumentation {
+                name,
+                file: file.clone(),
+                enums,
+                type_aliases,
+                newtypes,
+                classes,
+                instances,
+                functions,
+            });
+        }
+        modules.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut types: HashMap<String, String> = HashMap::new();
+        for module in &modules {
+            let names = module
+                .enums
+                .iter()
+                .map(|enum_| &enum_.name)
+                .chain(module.type_aliases.iter().map(|alias| &alias.name))
+                .chain(module.newtypes.iter().map(|newtype| &newtype.name));
+            for name in names {
+                types
+                    .entry(name.clone())
+                    .or_insert(format!("{}.html#type-{}", module.name, name));
+            }
+            for class in &module.classes {
+                types
+                    .entry(class.name.clone())
+                    .or_insert(format!("{}.html#class-{}", module.name, class.name));
+            }
+        }
+
+        Documentation { modules, types }
+    }
+
+    pub fn write(&self, directory: &str) -> std::io::Result<()> {
+        std::fs::create_dir_all(directory)?;
+        std::fs::write(format!("{}/index.html", directory), self.render_index())?;
+        for module in &self.modules {
+            std::fs::write(
+                format!("{}/{}.html", directory, module.name),
+                self.render_module(module),
+            )?;
+        }
+        std::fs::write(
+            format!("{}/index.json", directory),
+            serde_json::to_string_pretty(&self.to_json()).unwrap(),
+        )?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        let modules: Vec<Value> = self
+            .modules
+            .iter()
+            .map(|module| {
+                json!({
+                    "name": module.name,
+                    "file": module.file,
+                    "url": format!("{}.html", module.name),
+                    "enums": module.enums.iter().map(|enum_| json!({
+                        "name": enum_.name,
+                        "generic_parameters": enum_
+                            .generic_parameters
+                            .iter()
+                            .map(|parameter| parameter.to_string())
+                            .collect::<Vec<String>>(),
+                        "variants": enum_.variants.iter().map(|variant| match variant {
+                            EnumVariant::Unit(name, _) => json!({ "name": name, "fields": [] }),
+                            EnumVariant::Tuple(name, _, types) => json!({
+                                "name": name,
+                                "fields": types
+                                    .iter()
+                                    .map(|type_| type_.to_string())
+                                    .collect::<Vec<String>>(),
+                            }),
+                        }).collect::<Vec<Value>>(),
+                        "signature": self.enum_signature(enum_, false),
+                        "doc": enum_.doc,
+                        "url": format!("{}.html#type-{}", module.name, enum_.name),
+                    })).collect::<Vec<Value>>(),
+                    "type_aliases": module.type_aliases.iter().map(|alias| json!({
+                        "name": alias.name,
+                        "generic_parameters": alias.generic_parameters,
+                        "type": alias.type_.to_string(),
+                        "signature": self.type_alias_signature(alias, false),
+                        "url": format!("{}.html#type-{}", module.name, alias.name),
+                    })).collect::<Vec<Value>>(),
+                    "newtypes": module.newtypes.iter().map(|newtype| json!({
+                        "name": newtype.name,
+                        "type": newtype.type_.to_string(),
+                        "signature": self.newtype_signature(newtype, false),
+                        "url": format!("{}.html#type-{}", module.name, newtype.name),
+                    })).collect::<Vec<Value>>(),
+                    "classes": module.classes.iter().map(|class| json!({
+                        "name": class.name,
+                        "parameter": class.parameter,
+                        "methods": class.methods.iter().map(|(name, type_)| json!({
+                            "name": name,
+                            "type": type_.to_string(),
+                        })).collect::<Vec<Value>>(),
+                        "signature": self.class_signature(class, false),
+                        "url": format!("{}.html#class-{}", module.name, class.name),
+                    })).collect::<Vec<Value>>(),
+                    "instances": module.instances.iter().map(|instance| json!({
+                        "class": instance.class,
+                        "type": instance.type_.to_string(),
+                        "signature": self.instance_signature(instance, false),
+                    })).collect::<Vec<Value>>(),
+                    "functions": module.functions.iter().map(|function| json!({
+                        "name": function.name,
+                        "parameters": function.parameters.iter().map(|parameter| json!({
+                            "name": parameter.name.0,
+                            "type": parameter.type_.to_string(),
+                            "default": parameter
+                                .default
+                                .as_ref()
+                                .map(|default| default.to_string()),
+                        })).collect::<Vec<Value>>(),
+                        "signature": self.function_signature(function, false),
+                        "foreign": function.is_external,
+                        "doc": function.doc,
+                        "url": format!("{}.html#fun-{}", module.name, function.name),
+                    })).collect::<Vec<Value>>(),
+                })
+            })
+            .collect();
+        json!({ "modules": modules })
+    }
+
+    fn render_index(&self) -> String {
+        let mut body: String = String::from("<h1>Modules</h1>\n<ul>\n");
+        for module in &self.modules {
+            body.push_str(&format!(
+                "<li><a href=\"{0}.html\">{0}</a></li>\n",
+                escape(&module.name)
+            ));
+        }
+        body.push_str("</ul>\n");
+        page("Modules", &body)
+    }
+
+    fn render_module(&self, module: &ModuleDocumentation) -> String {
+        let mut body: String = format!(
+            "<p><a href=\"index.html\">Modules</a></p>\n<h1>{}</h1>\n",
+            escape(&module.name)
+        );
+        let mut types: Vec<(usize, &str, String, String)> = vec![];
+        for enum_ in &module.enums {
+            types.push((
+                enum_.location.line,
+                &enum_.name,
+                self.enum_signature(enum_, true),
+                render_doc(&enum_.doc),
+            ));
+        }
+        for alias in &module.type_aliases {
+            types.push((
+                alias.location.line,
+                &alias.name,
+                self.type_alias_signature(alias, true),
+                String::new(),
+            ));
+        }
+        for newtype in &module.newtypes {
+            types.push((
+                newtype.location.line,
+                &newtype.name,
+                self.newtype_signature(newtype, true),
+                String::new(),
+            ));
+        }
+        types.sort_by_key(|(line, _, _, _)| *line);
+        if !types.is_empty() {
+            body.push_str("<h2>Types</h2>\n");
+            for (_, name, signature, doc) in types {
+                body.push_str(&format!(
+                    "<h3 id=\"type-{0}\">{0}</h3>\n<pre>{1}</pre>\n{2}",
+                    escape(name),
+                    signature,
+                    doc
+                ));
+            }
+        }
+        if !module.classes.is_empty() {
+            body.push_str("<h2>Classes</h2>\n");
+            for class in &module.classes {
+                body.push_str(&format!(
+                    "<h3 id=\"class-{0}\">{0}</h3>\n<pre>{1}</pre>\n",
+                    escape(&class.name),
+                    self.class_signature(class, true)
+                ));
+            }
+        }
+        if !module.instances.is_empty() {
+            body.push_str("<h2>Instances</h2>\n");
+            for instance in &module.instances {
+                body.push_str(&format!(
+                    "<pre>{}</pre>\n",
+                    self.instance_signature(instance, true)
+                ));
+            }
+        }
+        if !module.functions.is_empty() {
+            body.push_str("<h2>Functions</h2>\n");
+            for function in &module.functions {
+                body.push_str(&format!(
+                    "<h3 id=\"fun-{0}\">{0}</h3>\n<pre>{1}</pre>\n{2}",
+                    escape(&function.name),
+                    self.function_signature(function, true),
+                    render_doc(&function.doc)
+                ));
+            }
+        }
+        page(&module.name, &body)
+    }
+
+    pub fn enum_signature(&self, enum_: &Enum, html: bool) -> String {
+        let variants: Vec<String> = enum_
+            .variants
+            .iter()
+            .map(|variant| match variant {
+                EnumVariant::Unit(name, _) => name.clone(),
+                EnumVariant::Tuple(name, _, types) => format!(
+                    "{}({})",
+                    name,
+                    types
+                        .iter()
+                        .map(|type_| self.render_type(type_, html))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+            })
+            .collect();
+        let generic_parameters: String = if enum_.generic_parameters.is_empty() {
+            String::new()
+        } else {
+            let parameters: Vec<String> = enum_
+                .generic_parameters
+                .iter()
+                .map(|parameter| parameter.to_string())
+                .collect();
+            angle_brackets(&parameters.join(", "), html)
+        };
+        format!(
+            "enum {}{} = {}",
+            enum_.name,
+            generic_parameters,
+            variants.join(" | ")
+        )
+    }
+
+    pub fn function_signature(&self, function: &Function, html: bool) -> String {
+        let generic_parameters: String = if function.generic_parameters.is_empty() {
+            String::new()
+        } else {
+            let parameters: Vec<String> = function
+                .generic_parameters
+                .iter()
+                .map(|parameter| {
+                    let parameter: String = parameter.to_string();
+                    let classes: Vec<String> = function
+                        .constraints
+                        .iter()
+                        .filter(|constraint| constraint.parameter == parameter)
+                        .map(|constraint| constraint.class.0.clone())
+                        .collect();
+                    if classes.is_empty() {
+                        parameter
+                    } else {
+                        format!("{}: {}", parameter, classes.join(" + "))
+                    }
+                })
+                .collect();
+            angle_brackets(&parameters.join(", "), html)
+        };
+        let parameters: Vec<String> = function
+            .parameters
+            .iter()
+            .map(|parameter| {
+                let mut code: String = format!(
+                    "{}: {}",
+                    parameter.name.0,
+                    self.render_type(&parameter.type_, html)
+                );
+                if let Some(default) = &parameter.default {
+                    let default: String = default.to_string();
+                    if html {
+                        code.push_str(&format!(" = {}", escape(&default)));
+                    } else {
+                        code.push_str(&format!(" = {}", default));
+                    }
+                }
+                code
+            })
+            .collect();
+        format!(
+            "{}fun {}{}({}) {} {}",
+            if function.is_external { "foreign " } else { "" },
+            function.name,
+            generic_parameters,
+            parameters.join(", "),
+            if html { "-&gt;" } else { "->" },
+            self.render_type(&function.return_type, html)
+        )
+    }
+
+    pub fn type_alias_signature(&self, alias: &TypeAlias, html: bool) -> String {
+        let generic_parameters: String = if alias.generic_parameters.is_empty() {
+            String::new()
+        } else {
+            angle_brackets(&alias.generic_parameters.join(", "), html)
+        };
+        format!(
+            "type {}{} = {}",
+            alias.name,
+            generic_parameters,
+            self.render_type(&alias.type_, html)
+        )
+    }
+
+    pub fn newtype_signature(&self, newtype: &Newtype, html: bool) -> String {
+        format!(
+            "newtype {} = {}",
+            newtype.name,
+            self.render_type(&newtype.type_, html)
+        )
+    }
+
+    pub fn class_signature(&self, class: &Class, html: bool) -> String {
+        let methods: String = class
+            .methods
+            .iter()
+            .map(|(name, type_)| format!("    {}: {}\n", name, self.render_type(type_, html)))
+            .collect();
+        format!(
+            "class {}{} where\n{}end",
+            class.name,
+            angle_brackets(&class.parameter, html),
+            methods
+        )
+    }
+
+    pub fn instance_signature(&self, instance: &Instance, html: bool) -> String {
+        let class: String = if html {
+            self.link(&instance.class)
+        } else {
+            instance.class.clone()
+        };
+        format!(
+            "instance {}{}",
+            class,
+            angle_brackets(&self.render_type(&instance.type_, html), html)
+        )
+    }
+
+    /// Renders a type as it is written in source, linking the documented
+    /// types it mentions when rendering HTML.
+    fn render_type(&self, type_: &Type, html: bool) -> String {
+        if !html {
+            return type_.to_string();
+        }
+        match type_ {
+            Type::Generic(name, _, types) | Type::GenericEnum(name, _, types) => format!(
+                "{}{}",
+                self.link(name),
+                angle_brackets(
+                    &types
+                        .iter()
+                        .map(|type_| self.render_type(type_, html))
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                    html
+                )
+            ),
+            Type::Enum(name, _) | Type::Identifier(name, _) => self.link(name),
+            Type::List(Some(type_)) => format!("[{}]", self.render_type(type_, html)),
+            Type::Function(parameters, return_type) => format!(
+                "fun({}) -&gt; {}",
+                parameters
+                    .iter()
+                    .map(|type_| self.render_type(type_, html))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                self.render_type(return_type, html)
+            ),
+            _ => escape(&type_.to_string()),
+        }
+    }
+
+    fn link(&self, name: &str) -> String {
+        match self.types.get(name) {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape(url), escape(name)),
+            None => escape(name),
+        }
+    }
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape(title),
+        STYLE,
+        body
+    )
+}
+
+fn render_doc(doc: &Option<String>) -> String {
+    match doc {
+        Some(doc) => doc
+            .split("\n\n")
+            .map(|paragraph| format!("<p>{}</p>\n", escape(paragraph.trim())))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+fn angle_brackets(inner: &str, html: bool) -> String {
+    if html {
+        format!("&lt;{}&gt;", inner)
+    } else {
+        format!("<{}>", inner)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
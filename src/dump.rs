@@ -0,0 +1,192 @@
+use std::io::Write;
+
+use crate::{
+    ast::Statement,
+    checker::Scope,
+    compiler::Compiler,
+    doc::Documentation,
+    error::{HarmonyError, HarmonyErrorKind},
+    parser::Parser,
+    token::{Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+/// The output of a compiler phase printed by `--dump`, after which the
+/// compiler stops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dump {
+    /// The tokens, one per line.
+    Tokens,
+    /// The parsed statements, pretty-printed.
+    Ast,
+    /// The parsed statements as JSON.
+    AstJson,
+    /// What the module declares, as the checker sees it.
+    Scope,
+    /// The generated JavaScript.
+    Js,
+}
+
+impl Dump {
+    /// Parses the value of `--dump=<phase>`.
+    pub fn parse(phase: &str) -> Option<Dump> {
+        match phase {
+            "tokens" => Some(Dump::Tokens),
+            "ast" => Some(Dump::Ast),
+            "ast-json" => Some(Dump::AstJson),
+            "scope" => Some(Dump::Scope),
+            "js" => Some(Dump::Js),
+            _ => None,
+        }
+    }
+
+    /// Prints this phase's output for the file `compiler` compiles, running
+    /// only the phases before it. Returns whether they succeeded, after
+    /// printing their errors if they didn't. Output that can't be written,
+    /// like to a closed pipe, is dropped.
+    pub fn print(&self, compiler: &mut Compiler) -> bool {
+        match self.output(compiler) {
+            Some(output) => {
+                let _ = std::io::stdout().write_all(output.as_bytes());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn output(&self, compiler: &mut Compiler) -> Option<String> {
+        let file: String = compiler.files[0].clone();
+        match self {
+            Dump::Tokens | Dump::Ast | Dump::AstJson => {
+                let source: String = match compiler.resolver.read(&file) {
+                    Ok(source) => source,
+                    Err(error) => {
+                        compiler
+                            .logger
+                            .error(format!("Could not read {}: {}", file, error));
+                        return None;
+                    }
+                };
+                let mut tokenizer: Tokenizer = Tokenizer::new(&file, &source);
+                let tokens: Vec<Token> = tokenizer.tokenize();
+                if *self == Dump::Tokens {
+                    return Some(tokens.iter().map(|token| format!("{}\n", token)).collect());
+                }
+                let mut errors: Vec<HarmonyError> = tokenizer.errors.clone();
+                for token in &tokens {
+                    if token.kind == TokenKind::Unknown {
+                        errors.push(HarmonyError::new(
+                            HarmonyErrorKind::Syntax,
+                            format!("Unknown token: {}", token.lexeme),
+                            None,
+                            token.location.clone(),
+                        ));
+                    }
+                }
+                if !errors.is_empty() {
+                    for error in errors {
+                        compiler.logger.error(error.to_string());
+                    }
+                    return None;
+                }
+                let statements: Vec<Statement> = match Parser::new(tokens).parse() {
+                    Ok(statements) => statements,
+                    Err(error) => {
+                        compiler.logger.error(error.to_string());
+                        return None;
+                    }
+                };
+                if *self == Dump::Ast {
+                    Some(format!("{:#?}\n", statements))
+                } else {
+                    Some(format!(
+                        "{}\n",
+                        serde_json::to_string_pretty(&statements).unwrap()
+                    ))
+                }
+            }
+            Dump::Scope | Dump::Js => {
+                compiler.check();
+                if !compiler.errors.is_empty() {
+                    return None;
+                }
+                if *self == Dump::Js {
+                    let module = compiler.modules.iter().find(|module| module.path == file);
+                    Some(module.unwrap().js.clone())
+                } else {
+                    Some(scope(
+                        &compiler.compiled_files[&file],
+                        &file,
+                        &Documentation::new(compiler),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// Lists the signature of everything `scope` declares in `file`, as
+/// `harmony doc` renders it.
+fn scope(scope: &Scope, file: &str, documentation: &Documentation) -> String {
+    let mut code: String = String::new();
+    if let Some((name, _)) = &scope.module {
+        code.push_str(&format!("module {}\n", name));
+    }
+
+    let mut declarations: Vec<(usize, String)> = vec![];
+    for enum_ in scope.enums.values() {
+        if enum_.location.file != file {
+            continue;
+        }
+        declarations.push((
+            enum_.location.line,
+            documentation.enum_signature(enum_, false),
+        ));
+    }
+    for function in scope.functions.values() {
+        if function.location.file != file {
+            continue;
+        }
+        declarations.push((
+            function.location.line,
+            documentation.function_signature(function, false),
+        ));
+    }
+    for class in scope.classes.values() {
+        if class.location.file == file {
+            declarations.push((
+                class.location.line,
+                documentation.class_signature(class, false),
+            ));
+        }
+    }
+    for instance in &scope.instances {
+        if instance.location.file == file {
+            declarations.push((
+                instance.location.line,
+                documentation.instance_signature(instance, false),
+            ));
+        }
+    }
+    for alias in scope.type_aliases.values() {
+        if alias.location.file == file {
+            declarations.push((
+                alias.location.line,
+                documentation.type_alias_signature(alias, false),
+            ));
+        }
+    }
+    for newtype in scope.newtypes.values() {
+        if newtype.location.file == file {
+            declarations.push((
+                newtype.location.line,
+                documentation.newtype_signature(newtype, false),
+            ));
+        }
+    }
+    declarations.sort();
+    for (_, declaration) in declarations {
+        code.push_str(&format!("\n{}\n", declaration));
+    }
+    code
+}
@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
 use crate::token::SourceLocation;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HarmonyError {
     pub kind: HarmonyErrorKind,
     pub message: String,
@@ -8,13 +10,14 @@ pub struct HarmonyError {
     pub location: SourceLocation,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum HarmonyErrorKind {
     Syntax,
     Semantic,
     Type,
     CompileTime,
     Runtime,
+    Warning,
 }
 
 impl HarmonyErrorKind {
@@ -25,6 +28,7 @@ impl HarmonyErrorKind {
             HarmonyErrorKind::Type => "Type Error".to_string(),
             HarmonyErrorKind::CompileTime => "Compile Time Error".to_string(),
             HarmonyErrorKind::Runtime => "Runtime Error".to_string(),
+            HarmonyErrorKind::Warning => "Warning".to_string(),
         }
     }
 }
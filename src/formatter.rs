@@ -0,0 +1,1177 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{
+        dotted, exposing_list, generic_parameter_list, DoStatement, EnumVariant, Expression,
+        Parameter, PatternMatchDirective, Position, Statement, Type,
+    },
+    error::{HarmonyError, HarmonyErrorKind},
+    parser::Parser,
+    token::{LineIndex, SourceLocation, Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+pub const DEFAULT_WIDTH: usize = 80;
+
+const INDENT: usize = 4;
+
+/// Starts a line holding a comment that was written at the end of a line of
+/// code, which `attach_trailing_comments` moves back onto the line before it.
+const TRAILING: char = '\u{E000}';
+
+/// Reprints a module in the canonical layout used by `harmony fmt`.
+///
+/// Every construct is printed on one line when it fits in `width` and broken
+/// over several lines otherwise. Literals are copied from the source as
+/// written, and comments, which the parser never sees, are put back before
+/// the statement, case arm, do statement, argument or element that followed
+/// them, or at the end of the line they ended. A construct with a comment
+/// inside is never joined onto one line.
+pub struct Formatter {
+    filename: String,
+    source: String,
+    lines: LineIndex,
+    width: usize,
+    tokens: Vec<Token>,
+    /// The index in `tokens` of the token starting at each offset, which is
+    /// where the AST's locations start.
+    token_indices: HashMap<usize, usize>,
+    /// Indices in `tokens` of the comments, in source order.
+    comments: Vec<usize>,
+    /// How many of `comments` have been printed.
+    printed_comments: usize,
+}
+
+impl Formatter {
+    pub fn new(filename: &str, source: &str, width: usize) -> Formatter {
+        Formatter {
+            filename: filename.to_string(),
+            source: source.to_string(),
+            lines: LineIndex::new(source),
+            width,
+            tokens: vec![],
+            token_indices: HashMap::new(),
+            comments: vec![],
+            printed_comments: 0,
+        }
+    }
+
+    pub fn format(&mut self) -> Result<String, HarmonyError> {
+        let mut tokenizer: Tokenizer = Tokenizer::new(&self.filename, &self.source);
+        self.tokens = tokenizer.tokenize();
+        if let Some(error) = tokenizer.errors.first() {
+            return Err(error.clone());
+        }
+        if let Some(token) = self
+            .tokens
+            .iter()
+            .find(|token| token.kind == TokenKind::Unknown)
+        {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Syntax,
+                format!("Unknown token: {}", token.lexeme),
+                None,
+                token.location.clone(),
+            ));
+        }
+        for (index, token) in self.tokens.iter().enumerate() {
+            self.token_indices.insert(token.location.start, index);
+            if is_comment(token) {
+                self.comments.push(index);
+            }
+        }
+        let statements: Vec<Statement> = Parser::new(self.tokens.clone()).parse()?;
+
+        let mut code: String = String::new();
+        let mut previous: Option<&Statement> = None;
+        for statement in &statements {
+            if let Some(previous) = previous {
+                code.push_str(if is_grouped(previous, statement) {
+                    "\n"
+                } else {
+                    "\n\n"
+                });
+            }
+            let start: usize = statement_location(statement).start;
+            code.push_str(&self.top_level_comments(start));
+            code.push_str(&self.statement(statement, 0));
+            previous = Some(statement);
+        }
+        let rest: String = self.top_level_comments(usize::MAX);
+        if !rest.is_empty() {
+            if !code.is_empty() {
+                code.push_str("\n\n");
+            }
+            code.push_str(rest.trim_end());
+        }
+        let mut code: String = attach_trailing_comments(&code).trim_end().to_string();
+        code.push('\n');
+        Ok(code)
+    }
+
+    fn statement(&mut self, statement: &Statement, indent: usize) -> String {
+        match statement {
+            Statement::Module { name, exposing } => {
+                format!("module {}{}", dotted(name), exposing_list(exposing))
+            }
+            Statement::Import {
+                name,
+                alias,
+                exposing,
+            } => format!(
+                "import {}{}{}",
+                dotted(name),
+                match alias {
+                    Some((alias, _)) => format!(" as {}", alias),
+                    None => String::new(),
+                },
+                exposing_list(exposing)
+            ),
+            Statement::ForeignImport { name, exposing } => format!(
+                "foreign import {}{}",
+                self.text(&name.1),
+                exposing_list(exposing)
+            ),
+            Statement::ForeignFunction {
+                name,
+                parameters,
+                return_type,
+                binding,
+                doc: _,
+            } => {
+                let header: String = self.signature(
+                    &format!("foreign fun {}", name.0),
+                    parameters,
+                    return_type,
+                    indent,
+                );
+                let binding: String = self.text(&binding.1);
+                if column_after(indent, &header) + 1 + width(&binding) <= self.width {
+                    format!("{} {}", header, binding)
+                } else {
+                    format!("{}\n{}{}", header, pad(indent + INDENT), binding)
+                }
+            }
+            Statement::Function {
+                name,
+                parameters,
+                return_type,
+                body,
+                doc: _,
+            } => {
+                let header: String =
+                    self.signature(&format!("fun {}", name.0), parameters, return_type, indent);
+                self.function_body(header, body, indent)
+            }
+            Statement::GenericFunction {
+                name,
+                generic_parameters,
+                constraints,
+                parameters,
+                return_type,
+                body,
+                doc: _,
+            } => {
+                let header: String = self.signature(
+                    &format!(
+                        "fun {}<{}>",
+                        name.0,
+                        generic_parameter_list(generic_parameters, constraints)
+                    ),
+                    parameters,
+                    return_type,
+                    indent,
+                );
+                self.function_body(header, body, indent)
+            }
+            Statement::Enum {
+                name,
+                variants,
+                doc: _,
+            } => self.enum_(&name.0, variants, indent),
+            Statement::GenericEnum {
+                name,
+                generic_parameters,
+                variants,
+                doc: _,
+            } => self.enum_(
+                &format!(
+                    "{}<{}>",
+                    name.0,
+                    generic_parameter_list(generic_parameters, &[])
+                ),
+                variants,
+                indent,
+            ),
+            Statement::Class {
+                name,
+                generic_parameter,
+                methods,
+            } => {
+                let mut code: String = format!("class {}<{}> where", name.0, generic_parameter.0);
+                for method in methods {
+                    code.push('\n');
+                    code.push_str(&self.comments_before(method.name.1.start, indent + INDENT));
+                    code.push_str(&format!(
+                        "{}{}: {}",
+                        pad(indent + INDENT),
+                        method.name.0,
+                        method.type_
+                    ));
+                }
+                code.push_str(&self.closing(&name.1, indent));
+                code
+            }
+            Statement::Instance {
+                class,
+                type_: instance_type,
+                methods,
+            } => {
+                let mut code: String = format!("instance {}<{}> where", class.0, instance_type);
+                for (i, method) in methods.iter().enumerate() {
+                    code.push_str(if i == 0 { "\n" } else { "\n\n" });
+                    let start: usize = statement_location(method).start;
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    code.push_str(&pad(indent + INDENT));
+                    code.push_str(&self.statement(method, indent + INDENT));
+                }
+                code.push_str(&self.closing(&class.1, indent));
+                code
+            }
+            Statement::TypeAlias {
+                name,
+                generic_parameters,
+                type_: aliased,
+            } => {
+                let generic_parameters: String = if generic_parameters.is_empty() {
+                    String::new()
+                } else {
+                    format!("<{}>", generic_parameter_list(generic_parameters, &[]))
+                };
+                format!("type {}{} = {}", name.0, generic_parameters, aliased)
+            }
+            Statement::Newtype {
+                name,
+                type_: wrapped,
+            } => format!("newtype {} = {}", name.0, wrapped),
+            Statement::Test { name, body } => {
+                let header: String = format!("test \"{}\" =", name.0);
+                self.function_body(header, body, indent)
+            }
+        }
+    }
+
+    /// Prints `prefix(parameters) -> return_type =`, with one parameter per
+    /// line when they don't fit.
+    fn signature(
+        &mut self,
+        prefix: &str,
+        parameters: &[Parameter],
+        return_type: &Option<Type>,
+        indent: usize,
+    ) -> String {
+        let return_type: String = match return_type {
+            Some(return_type) => return_type.to_string(),
+            None if parameters.is_empty() => return format!("{} =", prefix),
+            None => "unit".to_string(),
+        };
+        let flat: Option<Vec<String>> = parameters
+            .iter()
+            .map(|parameter| self.flat_parameter(parameter))
+            .collect();
+        if let Some(flat) = flat {
+            let header: String = format!("{}({}) -> {} =", prefix, flat.join(", "), return_type);
+            if indent + width(&header) <= self.width {
+                return header;
+            }
+        }
+        let mut code: String = format!("{}(", prefix);
+        for (i, parameter) in parameters.iter().enumerate() {
+            code.push('\n');
+            code.push_str(&pad(indent + INDENT));
+            let name: String = format!("{}: {}", parameter.name.0, parameter.type_);
+            code.push_str(&name);
+            if let Some(default) = &parameter.default {
+                code.push_str(" = ");
+                let column: usize = indent + INDENT + width(&name) + 3;
+                code.push_str(&self.expression(default, indent + INDENT, column));
+            }
+            if i + 1 < parameters.len() {
+                code.push(',');
+            }
+        }
+        code.push_str(&format!("\n{}) -> {} =", pad(indent), return_type));
+        code
+    }
+
+    fn flat_parameter(&self, parameter: &Parameter) -> Option<String> {
+        let name: String = format!("{}: {}", parameter.name.0, parameter.type_);
+        match &parameter.default {
+            Some(default) => Some(format!("{} = {}", name, self.flat(default)?)),
+            None => Some(name),
+        }
+    }
+
+    /// Places a function body after its header: on the same line when it
+    /// fits or is a `do` block, indented on the next line otherwise.
+    fn function_body(&mut self, header: String, body: &Expression, indent: usize) -> String {
+        let column: usize = column_after(indent, &header) + 1;
+        if self.is_block(body, column) {
+            return format!("{} {}", header, self.expression(body, indent, column));
+        }
+        if let Some(flat) = self.flat(body) {
+            if column + width(&flat) <= self.width {
+                return format!("{} {}", header, flat);
+            }
+        }
+        let comments: String = self.comments_before(body.location().start, indent + INDENT);
+        format!(
+            "{}\n{}{}{}",
+            header,
+            comments,
+            pad(indent + INDENT),
+            self.expression(body, indent + INDENT, indent + INDENT)
+        )
+    }
+
+    fn enum_(&mut self, header: &str, variants: &[EnumVariant], indent: usize) -> String {
+        let printed: Vec<String> = variants.iter().map(EnumVariant::to_string).collect();
+        let flat: String = format!("enum {} = {}", header, printed.join(" | "));
+        let start: Option<usize> = variants
+            .first()
+            .map(|variant| variant_location(variant).start);
+        let end: Option<usize> = variants.last().map(|variant| variant_location(variant).end);
+        if indent + width(&flat) <= self.width && !self.has_comments(start, end) {
+            return flat;
+        }
+        let mut code: String = format!("enum {}", header);
+        for (i, (variant, printed)) in variants.iter().zip(printed).enumerate() {
+            code.push('\n');
+            let start: usize = variant_location(variant).start;
+            code.push_str(&self.comments_before(start, indent + INDENT));
+            code.push_str(&pad(indent + INDENT));
+            code.push_str(if i == 0 { "= " } else { "| " });
+            code.push_str(&printed);
+        }
+        code
+    }
+
+    /// Prints `expression` starting at `column` on a line indented by `indent`.
+    fn expression(&mut self, expression: &Expression, indent: usize, column: usize) -> String {
+        if let Some(flat) = self.flat(expression) {
+            if column + width(&flat) <= self.width {
+                return flat;
+            }
+        }
+        match expression {
+            Expression::Binary { .. } => {
+                let (operands, operators) = self.binary_chain(expression);
+                let (first, position) = &operands[0];
+                let mut code: String = self.operand(first, position, indent, column);
+                for (operator, (operand, position)) in operators.iter().zip(operands.iter().skip(1))
+                {
+                    code.push('\n');
+                    let start: usize = operand.location().start;
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    let prefix: String = format!("{}{} ", pad(indent + INDENT), operator);
+                    code.push_str(&prefix);
+                    code.push_str(&self.operand(
+                        operand,
+                        position,
+                        indent + INDENT,
+                        width(&prefix),
+                    ));
+                }
+                code
+            }
+            Expression::Unary {
+                operator, right, ..
+            } => {
+                let operator: String = unary_operator(operator, right);
+                let right: String =
+                    self.operand(right, &Position::Unary, indent, column + width(&operator));
+                format!("{}{}", operator, right)
+            }
+            Expression::Call {
+                callee,
+                arguments,
+                argument_names,
+                ..
+            } => {
+                if let [argument] = arguments.as_slice() {
+                    if let (Some(text), [None]) =
+                        (self.expression_text(argument), argument_names.as_slice())
+                    {
+                        if text.contains('\n') {
+                            if self.is_dollar_call(&callee.1) {
+                                return format!("{} $ {}", callee.0, text);
+                            }
+                            return format!("{} ({})", callee.0, text);
+                        }
+                    }
+                }
+                if self.is_dollar_call(&callee.1) {
+                    let prefix: String = format!("{} $ ", callee.0);
+                    let argument: String =
+                        self.expression(&arguments[0], indent, column + width(&prefix));
+                    return format!("{}{}", prefix, argument);
+                }
+                let mut code: String = format!("{} (", callee.0);
+                for (i, (argument, name)) in arguments.iter().zip(argument_names).enumerate() {
+                    code.push('\n');
+                    let start: usize = match name {
+                        Some((_, location)) => location.start,
+                        None => argument.location().start,
+                    };
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    let prefix: String = match name {
+                        Some((name, _)) => format!("{}{} = ", pad(indent + INDENT), name),
+                        None => pad(indent + INDENT),
+                    };
+                    code.push_str(&prefix);
+                    code.push_str(&self.expression(argument, indent + INDENT, width(&prefix)));
+                    if i + 1 < arguments.len() {
+                        code.push(',');
+                    }
+                }
+                let end: Option<usize> = arguments.last().map(|a| a.location().end);
+                code.push_str(&self.closing_after(end, indent));
+                code.push(')');
+                code
+            }
+            Expression::PatternMatch {
+                expression: scrutinee,
+                cases,
+                default_case,
+                ..
+            } => {
+                let mut code: String =
+                    format!("case {} of", self.expression(scrutinee, indent, column + 5));
+                for case in cases {
+                    code.push('\n');
+                    let start: usize = case.pattern.location().start;
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    let mut arm: String = format!("{}| ", pad(indent + INDENT));
+                    let pattern: String =
+                        self.expression(&case.pattern, indent + INDENT, width(&arm));
+                    arm.push_str(&pattern);
+                    if let PatternMatchDirective::If(condition) = &case.directive {
+                        arm.push_str(" if ");
+                        let column: usize = column_after(0, &arm);
+                        arm.push_str(&self.expression(condition, indent + INDENT, column));
+                    }
+                    arm.push_str(" =>");
+                    code.push_str(&arm);
+                    code.push_str(&self.arm_body(&case.body, indent, column_after(0, &arm)));
+                }
+                if let Some(default_case) = default_case {
+                    code.push('\n');
+                    let start: usize = default_case.location().start;
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    let arm: String = format!("{}| else =>", pad(indent + INDENT));
+                    code.push_str(&arm);
+                    code.push_str(&self.arm_body(default_case, indent, width(&arm)));
+                }
+                let end: Option<usize> = match default_case {
+                    Some(default_case) => Some(default_case.location().end),
+                    None => cases.last().map(|case| case.body.location().end),
+                };
+                code.push_str(&self.closing_after(end, indent));
+                code.push_str("end");
+                code
+            }
+            Expression::List(elements, _) => {
+                if elements.is_empty() {
+                    return "[]".to_string();
+                }
+                let mut code: String = "[".to_string();
+                for (i, element) in elements.iter().enumerate() {
+                    code.push('\n');
+                    let start: usize = element.location().start;
+                    code.push_str(&self.comments_before(start, indent + INDENT));
+                    code.push_str(&pad(indent + INDENT));
+                    code.push_str(&self.expression(element, indent + INDENT, indent + INDENT));
+                    if i + 1 < elements.len() {
+                        code.push(',');
+                    }
+                }
+                let end: Option<usize> = elements.last().map(|e| e.location().end);
+                code.push_str(&self.closing_after(end, indent));
+                code.push(']');
+                code
+            }
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let mut code: String =
+                    format!("if {} then", self.expression(condition, indent, column + 3));
+                let then_column: usize = column_after(column, &code) + 1;
+                match self.flat(then_branch) {
+                    Some(flat) if then_column + width(&flat) <= self.width => {
+                        code.push(' ');
+                        code.push_str(&flat);
+                    }
+                    _ => {
+                        code.push('\n');
+                        let start: usize = then_branch.location().start;
+                        code.push_str(&self.comments_before(start, indent + INDENT));
+                        code.push_str(&pad(indent + INDENT));
+                        code.push_str(&self.expression(
+                            then_branch,
+                            indent + INDENT,
+                            indent + INDENT,
+                        ));
+                    }
+                }
+                code.push('\n');
+                let start: usize = else_branch.location().start;
+                code.push_str(&self.comments_before(start, indent));
+                code.push_str(&pad(indent));
+                code.push_str("else");
+                match (else_branch.as_ref(), self.flat(else_branch)) {
+                    (Expression::If { .. }, _) => {
+                        code.push(' ');
+                        code.push_str(&self.expression(else_branch, indent, indent + 5));
+                    }
+                    (_, Some(flat)) if indent + 5 + width(&flat) <= self.width => {
+                        code.push(' ');
+                        code.push_str(&flat);
+                    }
+                    _ => {
+                        code.push('\n');
+                        code.push_str(&pad(indent + INDENT));
+                        code.push_str(&self.expression(
+                            else_branch,
+                            indent + INDENT,
+                            indent + INDENT,
+                        ));
+                    }
+                }
+                code
+            }
+            Expression::Access { name, member } => {
+                let prefix: String = format!("{}.", name.0);
+                let member: String =
+                    self.operand(member, &Position::Member, indent, column + width(&prefix));
+                format!("{}{}", prefix, member)
+            }
+            Expression::Rest(expression, _) => {
+                format!("..{}", self.expression(expression, indent, column + 2))
+            }
+            Expression::Index {
+                expression, index, ..
+            } => {
+                let expression: String =
+                    self.operand(expression, &Position::Indexed, indent, column);
+                let column: usize = column_after(column, &expression) + 1;
+                format!("{}[{}]", expression, self.expression(index, indent, column))
+            }
+            Expression::Let {
+                name,
+                type_annotation,
+                value,
+                body,
+                ..
+            } => {
+                let mut code: String = format!("let {}", name.0);
+                if let Some(type_annotation) = type_annotation {
+                    code.push_str(&format!(": {}", type_annotation));
+                }
+                code.push_str(" = ");
+                let value_column: usize = column + width(&code);
+                code.push_str(&self.expression(value, indent, value_column));
+                code.push_str(" in\n");
+                let start: usize = body.location().start;
+                code.push_str(&self.comments_before(start, indent));
+                code.push_str(&pad(indent));
+                code.push_str(&self.expression(body, indent, indent));
+                code
+            }
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                ..
+            } => {
+                let header: String = match self.flat_lambda_header(parameters, return_type) {
+                    Some(header) => header,
+                    None => self.signature("fun", parameters, return_type, indent),
+                };
+                let header_column: usize = column_after(column, &header) + 1;
+                if self.is_block(body, header_column) {
+                    return format!(
+                        "{} {}",
+                        header,
+                        self.expression(body, indent, header_column)
+                    );
+                }
+                match self.flat(body) {
+                    Some(flat) if header_column + width(&flat) <= self.width => {
+                        format!("{} {}", header, flat)
+                    }
+                    _ => {
+                        let start: usize = body.location().start;
+                        let comments: String = self.comments_before(start, indent + INDENT);
+                        format!(
+                            "{}\n{}{}{}",
+                            header,
+                            comments,
+                            pad(indent + INDENT),
+                            self.expression(body, indent + INDENT, indent + INDENT)
+                        )
+                    }
+                }
+            }
+            Expression::Do {
+                statements,
+                location,
+            } => {
+                let mut comments: Vec<String> = vec![];
+                let mut printed: Vec<(String, String)> = vec![];
+                for statement in statements {
+                    let start: usize = match statement {
+                        DoStatement::Bind { name, .. } | DoStatement::Let { name, .. } => {
+                            name.1.start
+                        }
+                        DoStatement::Expression(expression) => expression.location().start,
+                    };
+                    comments.push(self.comments_before(start, indent + INDENT));
+                    printed.push(self.do_statement(statement, indent + INDENT));
+                }
+                let mut code: String = "do".to_string();
+                for (comments, (prefix, value)) in comments.iter().zip(printed) {
+                    code.push('\n');
+                    code.push_str(comments);
+                    code.push_str(&pad(indent + INDENT));
+                    code.push_str(&prefix);
+                    code.push_str(&value);
+                }
+                code.push_str(&self.closing(location, indent));
+                code
+            }
+            _ => self.flat(expression).unwrap_or_else(|| {
+                self.expression_text(expression)
+                    .unwrap_or_else(|| format!("{:?}", expression))
+            }),
+        }
+    }
+
+    fn arm_body(&mut self, body: &Expression, indent: usize, column: usize) -> String {
+        if self.is_block(body, column + 1) {
+            return format!(" {}", self.expression(body, indent + INDENT, column + 1));
+        }
+        match self.flat(body) {
+            Some(flat) if column + 1 + width(&flat) <= self.width => format!(" {}", flat),
+            _ => {
+                let start: usize = body.location().start;
+                format!(
+                    "\n{}{}{}",
+                    self.comments_before(start, indent + 2 * INDENT),
+                    pad(indent + 2 * INDENT),
+                    self.expression(body, indent + 2 * INDENT, indent + 2 * INDENT)
+                )
+            }
+        }
+    }
+
+    /// Prints a do statement as what comes before its value, and its value.
+    fn do_statement(&mut self, statement: &DoStatement, indent: usize) -> (String, String) {
+        match statement {
+            DoStatement::Bind { name, value } => {
+                let prefix: String = format!("{} <- ", name.0);
+                let value: String = self.expression(value, indent, indent + width(&prefix));
+                (prefix, value)
+            }
+            DoStatement::Let {
+                name,
+                type_annotation,
+                value,
+            } => {
+                let mut prefix: String = format!("let {}", name.0);
+                if let Some(type_annotation) = type_annotation {
+                    prefix.push_str(&format!(": {}", type_annotation));
+                }
+                prefix.push_str(" = ");
+                let value: String = self.expression(value, indent, indent + width(&prefix));
+                (prefix, value)
+            }
+            DoStatement::Expression(expression) => {
+                (String::new(), self.expression(expression, indent, indent))
+            }
+        }
+    }
+
+    /// Prints `expression` on one line, or returns `None` when it has to span
+    /// several: it contains a `case`, a `do`, a multi-line string or a comment.
+    fn flat(&self, expression: &Expression) -> Option<String> {
+        if self.has_comments(
+            Some(expression.location().start),
+            Some(expression.location().end),
+        ) {
+            return None;
+        }
+        let code: String = match expression {
+            Expression::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => format!(
+                "{} {} {}",
+                self.flat_operand(left, &Position::Left(operator.clone()))?,
+                operator,
+                self.flat_operand(right, &Position::Right(operator.clone()))?
+            ),
+            Expression::Unary {
+                operator, right, ..
+            } => format!(
+                "{}{}",
+                unary_operator(operator, right),
+                self.flat_operand(right, &Position::Unary)?
+            ),
+            Expression::Call {
+                callee,
+                arguments,
+                argument_names,
+                ..
+            } => {
+                if self.is_dollar_call(&callee.1) {
+                    format!("{} $ {}", callee.0, self.flat(&arguments[0])?)
+                } else {
+                    let mut flat: Vec<String> = vec![];
+                    for (argument, name) in arguments.iter().zip(argument_names) {
+                        let argument: String = self.flat(argument)?;
+                        flat.push(match name {
+                            Some((name, _)) => format!("{} = {}", name, argument),
+                            None => argument,
+                        });
+                    }
+                    format!("{} ({})", callee.0, flat.join(", "))
+                }
+            }
+            Expression::Identifier(name, _) => name.clone(),
+            Expression::PatternMatch { .. } | Expression::Do { .. } => return None,
+            Expression::List(elements, _) => {
+                let elements: Option<Vec<String>> =
+                    elements.iter().map(|element| self.flat(element)).collect();
+                format!("[{}]", elements?.join(", "))
+            }
+            Expression::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => format!(
+                "if {} then {} else {}",
+                self.flat(condition)?,
+                self.flat(then_branch)?,
+                self.flat(else_branch)?
+            ),
+            Expression::Access { name, member } => format!(
+                "{}.{}",
+                name.0,
+                self.flat_operand(member, &Position::Member)?
+            ),
+            Expression::Rest(expression, _) => format!("..{}", self.flat(expression)?),
+            Expression::Index {
+                expression, index, ..
+            } => format!(
+                "{}[{}]",
+                self.flat_operand(expression, &Position::Indexed)?,
+                self.flat(index)?
+            ),
+            Expression::Let {
+                name,
+                type_annotation,
+                value,
+                body,
+                ..
+            } => format!(
+                "let {}{} = {} in {}",
+                name.0,
+                match type_annotation {
+                    Some(type_annotation) => format!(": {}", type_annotation),
+                    None => String::new(),
+                },
+                self.flat(value)?,
+                self.flat(body)?
+            ),
+            Expression::Function {
+                parameters,
+                return_type,
+                body,
+                ..
+            } => format!(
+                "{} {}",
+                self.flat_lambda_header(parameters, return_type)?,
+                self.flat(body)?
+            ),
+            _ => self.expression_text(expression)?,
+        };
+        if code.contains('\n') {
+            return None;
+        }
+        Some(code)
+    }
+
+    /// Prints `expression` inside another one at `position`, in parentheses
+    /// if it needs them there.
+    fn operand(
+        &mut self,
+        expression: &Expression,
+        position: &Position,
+        indent: usize,
+        column: usize,
+    ) -> String {
+        if self.needs_parentheses(expression, position) {
+            return format!("({})", self.expression(expression, indent, column + 1));
+        }
+        self.expression(expression, indent, column)
+    }
+
+    fn flat_operand(&self, expression: &Expression, position: &Position) -> Option<String> {
+        let flat: String = self.flat(expression)?;
+        if self.needs_parentheses(expression, position) {
+            return Some(format!("({})", flat));
+        }
+        Some(flat)
+    }
+
+    /// Whether `expression` needs parentheses at `position`. Calls written
+    /// with `$` are kept that way, and their argument takes in everything
+    /// after it, like the else branch of an `if` does.
+    fn needs_parentheses(&self, expression: &Expression, position: &Position) -> bool {
+        expression.needs_parentheses(position)
+            || (matches!(position, Position::Left(_) | Position::Indexed)
+                && self.ends_with_dollar_call(expression))
+    }
+
+    fn ends_with_dollar_call(&self, expression: &Expression) -> bool {
+        let (last, position) = match expression {
+            Expression::Call { callee, .. } => return self.is_dollar_call(&callee.1),
+            Expression::Binary {
+                operator, right, ..
+            } => (right, Position::Right(operator.clone())),
+            Expression::Unary { right, .. } => (right, Position::Unary),
+            Expression::Access { member, .. } => (member, Position::Member),
+            _ => return false,
+        };
+        !self.needs_parentheses(last, &position) && self.ends_with_dollar_call(last)
+    }
+
+    /// Splits `a + b - c` into its operands, with where each is written, and
+    /// its operators, as long as they share a precedence and so can be broken
+    /// one per line.
+    fn binary_chain<'a>(
+        &self,
+        expression: &'a Expression,
+    ) -> (Vec<(&'a Expression, Position)>, Vec<&'a TokenKind>) {
+        let Expression::Binary {
+            left,
+            operator,
+            right,
+            ..
+        } = expression
+        else {
+            unreachable!()
+        };
+        let chained = |expression: &Expression, position: &Position| match expression {
+            Expression::Binary {
+                operator: inner, ..
+            } => {
+                inner.precedence() == operator.precedence()
+                    && !self.needs_parentheses(expression, position)
+            }
+            _ => false,
+        };
+        let left_position: Position = Position::Left(operator.clone());
+        let right_position: Position = Position::Right(operator.clone());
+        if operator.is_right_associative() {
+            let mut operands: Vec<(&Expression, Position)> = vec![(left, left_position)];
+            let mut operators: Vec<&TokenKind> = vec![operator];
+            if chained(right, &right_position) {
+                let (rest, rest_operators) = self.binary_chain(right);
+                operands.extend(rest);
+                operators.extend(rest_operators);
+            } else {
+                operands.push((right, right_position));
+            }
+            return (operands, operators);
+        }
+        let (mut operands, mut operators) = if chained(left, &left_position) {
+            self.binary_chain(left)
+        } else {
+            (vec![(left.as_ref(), left_position)], vec![])
+        };
+        operands.push((right, right_position));
+        operators.push(operator);
+        (operands, operators)
+    }
+
+    fn flat_lambda_header(
+        &self,
+        parameters: &[Parameter],
+        return_type: &Option<Type>,
+    ) -> Option<String> {
+        match return_type {
+            None if parameters.is_empty() => Some("fun =".to_string()),
+            _ => {
+                let parameters: Option<Vec<String>> = parameters
+                    .iter()
+                    .map(|parameter| self.flat_parameter(parameter))
+                    .collect();
+                Some(format!(
+                    "fun({}) -> {} =",
+                    parameters?.join(", "),
+                    match return_type {
+                        Some(return_type) => return_type.to_string(),
+                        None => "unit".to_string(),
+                    }
+                ))
+            }
+        }
+    }
+
+    /// The source text of a literal, exactly as written.
+    fn expression_text(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Integer(_, location)
+            | Expression::Float(_, location)
+            | Expression::String(_, location)
+            | Expression::InterpolatedString(_, location)
+            | Expression::Bool(_, location)
+            | Expression::Char(_, location) => {
+                Some(self.source[location.start..location.end].to_string())
+            }
+            _ => None,
+        }
+    }
+
+    /// Prints the comments that start before `offset` and haven't been printed
+    /// yet, one per line at `indent`.
+    fn comments_before(&mut self, offset: usize, indent: usize) -> String {
+        let mut code: String = String::new();
+        while let Some(index) = self.comments.get(self.printed_comments) {
+            let token: &Token = &self.tokens[*index];
+            if token.location.start >= offset {
+                break;
+            }
+            if self.is_trailing(*index) {
+                code.push_str(&format!("{}{}\n", TRAILING, token.lexeme));
+            } else {
+                code.push_str(&format!("{}{}\n", pad(indent), token.lexeme));
+            }
+            self.printed_comments += 1;
+        }
+        code
+    }
+
+    /// Like `comments_before`, but keeps a blank line after a comment that was
+    /// followed by one.
+    fn top_level_comments(&mut self, offset: usize) -> String {
+        let mut code: String = String::new();
+        while let Some(index) = self.comments.get(self.printed_comments).copied() {
+            let token: &Token = &self.tokens[index];
+            if token.location.start >= offset {
+                break;
+            }
+            self.printed_comments += 1;
+            if self.is_trailing(index) {
+                code.push_str(&format!("{}{}\n", TRAILING, token.lexeme));
+                continue;
+            }
+            code.push_str(&token.lexeme);
+            code.push('\n');
+            if let Some(next) = self.tokens.get(index + 1) {
+                if self.line(next.location.start) > self.line(token.location.end) + 1 {
+                    code.push('\n');
+                }
+            }
+        }
+        code
+    }
+
+    /// Whether the comment at `index` in `tokens` ends a line of code.
+    fn is_trailing(&self, index: usize) -> bool {
+        let comment: &Token = &self.tokens[index];
+        comment.lexeme.starts_with("--")
+            && index > 0
+            && self.line(self.tokens[index - 1].location.end) == self.line(comment.location.start)
+    }
+
+    /// Prints the comments left before the token that closes a block opened
+    /// at `location`, then starts the line of that closing token.
+    fn closing(&mut self, location: &SourceLocation, indent: usize) -> String {
+        let end: Option<usize> = self.token_index(location).and_then(|index| {
+            self.tokens[index..]
+                .iter()
+                .find(|token| token.kind == TokenKind::End)
+                .map(|token| token.location.start)
+        });
+        let mut code: String = "\n".to_string();
+        code.push_str(&self.comments_before(end.unwrap_or(0), indent + INDENT));
+        code.push_str(&pad(indent));
+        code.push_str("end");
+        code
+    }
+
+    /// Prints the comments between `end` and the next token, then starts the
+    /// line of that closing token.
+    fn closing_after(&mut self, end: Option<usize>, indent: usize) -> String {
+        let closing: Option<usize> = end.and_then(|end| {
+            self.tokens
+                .iter()
+                .find(|token| token.location.start >= end && !is_comment(token))
+                .map(|token| token.location.start)
+        });
+        let mut code: String = "\n".to_string();
+        code.push_str(&self.comments_before(closing.unwrap_or(0), indent + INDENT));
+        code.push_str(&pad(indent));
+        code
+    }
+
+    fn has_comments(&self, start: Option<usize>, end: Option<usize>) -> bool {
+        let (Some(start), Some(end)) = (start, end) else {
+            return false;
+        };
+        self.comments[self.printed_comments..].iter().any(|index| {
+            let offset: usize = self.tokens[*index].location.start;
+            offset > start && offset < end
+        })
+    }
+
+    /// Whether `expression` is a `do` block, or a `case` whose `case ... of`
+    /// line fits at `column`, which start on the line of the `=` or `=>`
+    /// before them.
+    fn is_block(&self, expression: &Expression, column: usize) -> bool {
+        match expression {
+            Expression::Do { .. } => true,
+            Expression::PatternMatch { expression, .. } => match self.flat(expression) {
+                Some(flat) => column + width(&flat) + 8 <= self.width,
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn is_dollar_call(&self, callee: &SourceLocation) -> bool {
+        match self.token_index(callee) {
+            Some(index) => self.tokens[index + 1..]
+                .iter()
+                .find(|token| !is_comment(token))
+                .is_some_and(|token| token.kind == TokenKind::Dollar),
+            None => false,
+        }
+    }
+
+    fn token_index(&self, location: &SourceLocation) -> Option<usize> {
+        self.token_indices.get(&location.start).copied()
+    }
+
+    fn text(&self, location: &SourceLocation) -> String {
+        self.source[location.start..location.end].to_string()
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.lines.position(offset).0
+    }
+}
+
+fn statement_location(statement: &Statement) -> &SourceLocation {
+    match statement {
+        Statement::Module { name, .. } | Statement::Import { name, .. } => &name[0].1,
+        Statement::ForeignImport { name, .. }
+        | Statement::ForeignFunction { name, .. }
+        | Statement::Function { name, .. }
+        | Statement::GenericFunction { name, .. }
+        | Statement::Enum { name, .. }
+        | Statement::GenericEnum { name, .. }
+        | Statement::Class { name, .. }
+        | Statement::TypeAlias { name, .. }
+        | Statement::Newtype { name, .. }
+        | Statement::Test { name, .. } => &name.1,
+        Statement::Instance { class, .. } => &class.1,
+    }
+}
+
+fn variant_location(variant: &EnumVariant) -> &SourceLocation {
+    match variant {
+        EnumVariant::Unit(_, location) | EnumVariant::Tuple(_, location, _) => location,
+    }
+}
+
+/// Whether two consecutive statements are written without a blank line
+/// between them, as imports and type declarations are.
+fn is_grouped(previous: &Statement, statement: &Statement) -> bool {
+    let is_import = |statement: &Statement| {
+        matches!(
+            statement,
+            Statement::Import { .. } | Statement::ForeignImport { .. }
+        )
+    };
+    let is_type = |statement: &Statement| {
+        matches!(
+            statement,
+            Statement::TypeAlias { .. } | Statement::Newtype { .. }
+        )
+    };
+    (is_import(previous) && is_import(statement)) || (is_type(previous) && is_type(statement))
+}
+
+/// A unary operator, with a space after a `-` before another one, as `--`
+/// would start a comment.
+fn unary_operator(operator: &TokenKind, right: &Expression) -> String {
+    match (operator, right) {
+        (
+            TokenKind::Minus,
+            Expression::Unary {
+                operator: TokenKind::Minus,
+                ..
+            },
+        ) => "- ".to_string(),
+        _ => operator.to_string(),
+    }
+}
+
+/// Moves each comment marked as `TRAILING` to the end of the last line of
+/// code printed before it.
+fn attach_trailing_comments(code: &str) -> String {
+    let mut lines: Vec<String> = vec![];
+    for line in code.split('\n') {
+        let Some(comment) = line.strip_prefix(TRAILING) else {
+            lines.push(line.to_string());
+            continue;
+        };
+        match lines.iter_mut().rev().find(|line| !line.trim().is_empty()) {
+            Some(previous) => {
+                previous.push(' ');
+                previous.push_str(comment);
+            }
+            None => lines.push(comment.to_string()),
+        }
+    }
+    lines.join("\n")
+}
+
+fn is_comment(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Comment | TokenKind::DocComment)
+}
+
+fn pad(indent: usize) -> String {
+    " ".repeat(indent)
+}
+
+fn width(code: &str) -> usize {
+    code.chars().count()
+}
+
+/// The column reached after printing `code` from `column`.
+fn column_after(column: usize, code: &str) -> usize {
+    match code.rfind('\n') {
+        Some(newline) => width(&code[newline + 1..]),
+        None => column + width(code),
+    }
+}
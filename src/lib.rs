@@ -1,8 +1,18 @@
 pub mod ast;
+pub mod cache;
 pub mod checker;
 pub mod codegen;
 pub mod compiler;
+pub mod doc;
+pub mod dump;
 pub mod error;
+pub mod formatter;
+pub mod log;
+pub mod lsp;
 pub mod parser;
+pub mod repl;
+pub mod resolver;
+pub mod test;
 pub mod token;
 pub mod tokenizer;
+pub mod watch;
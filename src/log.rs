@@ -0,0 +1,55 @@
+use std::fmt::Display;
+
+/// How much the compiler says about what it's doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Nothing, for the language server which needs stdout for its own
+    /// messages, and for `compile_source`, which return the errors instead.
+    Silent,
+    /// Only errors, and the timings `--timings` asks for.
+    Quiet,
+    /// Also warnings, and what commands like `--watch` report.
+    Normal,
+    /// Also which modules are compiled or come from the cache, and how long
+    /// they took.
+    Verbose,
+    /// Also every phase of every module.
+    Trace,
+}
+
+/// Prints the messages up to its level to stderr, so that stdout only has
+/// what the compiled program prints.
+#[derive(Debug, Clone, Copy)]
+pub struct Logger {
+    pub level: Level,
+}
+
+impl Logger {
+    pub fn new(level: Level) -> Logger {
+        Logger { level }
+    }
+
+    /// Prints `message` if the logger's level is at least `level`.
+    pub fn log(&self, level: Level, message: impl Display) {
+        if level != Level::Silent && level <= self.level {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Prints `message` unless the logger is silent.
+    pub fn error(&self, message: impl Display) {
+        self.log(Level::Quiet, message);
+    }
+
+    pub fn normal(&self, message: impl Display) {
+        self.log(Level::Normal, message);
+    }
+
+    pub fn verbose(&self, message: impl Display) {
+        self.log(Level::Verbose, message);
+    }
+
+    pub fn trace(&self, message: impl Display) {
+        self.log(Level::Trace, message);
+    }
+}
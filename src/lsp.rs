@@ -0,0 +1,670 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    io::{BufRead, Write},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    ast::{EnumVariant, Statement},
+    checker::{Checker, Reference},
+    compiler::{Compiler, CompilerOptions},
+    doc::Documentation,
+    error::{HarmonyError, HarmonyErrorKind},
+    log::Level,
+    parser::Parser,
+    resolver::{DiskFileSystem, FileResolver, MemoryFileSystem},
+    token::{LineIndex, SourceLocation, Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const REQUEST_FAILED: i64 = -32803;
+
+/// A language server speaking LSP over stdin and stdout, started by
+/// `harmony lsp`.
+///
+/// Every change to a document runs it through the tokenizer, parser and
+/// checker again, and publishes the errors found as diagnostics. Hover,
+/// go-to-definition and rename are answered from the identifiers the checker
+/// resolved in the last version of a document that checked without errors.
+pub struct LanguageServer {
+    root: PathBuf,
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+}
+
+struct Document {
+    path: String,
+    text: String,
+    lines: LineIndex,
+    /// The statements of the last version of the text that parsed.
+    statements: Vec<Statement>,
+    /// The compiler of the last analysis that got as far as checking, which
+    /// holds the scopes of the modules the document imports.
+    compiler: Option<Compiler>,
+    /// The checker of the current text, if it checked without errors.
+    checker: Option<Checker>,
+}
+
+impl LanguageServer {
+    pub fn new() -> LanguageServer {
+        LanguageServer {
+            root: std::env::current_dir().unwrap(),
+            documents: HashMap::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Serves messages from stdin until the client sends `exit`.
+    pub fn run(&mut self) {
+        let stdin = std::io::stdin();
+        let mut input = stdin.lock();
+        let mut output = std::io::stdout();
+        while let Some(message) = read_message(&mut input) {
+            if message["method"] == "exit" {
+                break;
+            }
+            for reply in self.handle(&message) {
+                let body: String = reply.to_string();
+                write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+                output.flush().unwrap();
+            }
+        }
+        std::process::exit(if self.shutdown { 0 } else { 1 });
+    }
+
+    /// Handles a request or notification, returning the messages to send back.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id: &Value = &message["id"];
+        let params: &Value = &message["params"];
+        let uri: String = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        let position: (usize, usize) = (
+            params["position"]["line"].as_u64().unwrap_or(0) as usize,
+            params["position"]["character"].as_u64().unwrap_or(0) as usize,
+        );
+        let result: Result<Value, String> = match message["method"].as_str().unwrap_or("") {
+            "initialize" => {
+                if let Some(root) = params["rootUri"].as_str() {
+                    self.root = PathBuf::from(uri_to_path(root));
+                }
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": 1,
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                        "completionProvider": { "triggerCharacters": ["."] },
+                        "documentSymbolProvider": true,
+                        "renameProvider": true,
+                    },
+                    "serverInfo": { "name": "harmony", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/didOpen" => {
+                let text: &str = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(
+                    uri.clone(),
+                    Document {
+                        path: uri_to_path(&uri),
+                        text: text.to_string(),
+                        lines: LineIndex::new(text),
+                        statements: vec![],
+                        compiler: None,
+                        checker: None,
+                    },
+                );
+                return vec![self.analyze(&uri)];
+            }
+            "textDocument/didChange" => {
+                let changes: Option<&Vec<Value>> = params["contentChanges"].as_array();
+                let text: Option<&str> = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => {
+                        document.text = text.to_string();
+                        document.lines = LineIndex::new(text);
+                    }
+                    _ => return vec![],
+                }
+                return vec![self.analyze(&uri)];
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                })];
+            }
+            "textDocument/hover" => Ok(self.hover(&uri, position)),
+            "textDocument/definition" => Ok(self.definition(&uri, position)),
+            "textDocument/completion" => Ok(self.completion(&uri, position)),
+            "textDocument/documentSymbol" => Ok(self.document_symbols(&uri)),
+            "textDocument/rename" => {
+                self.rename(&uri, position, params["newName"].as_str().unwrap_or(""))
+            }
+            method => {
+                if id.is_null() {
+                    return vec![];
+                }
+                return vec![json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "error": {
+                        "code": METHOD_NOT_FOUND,
+                        "message": format!("Unknown method {}", method),
+                    },
+                })];
+            }
+        };
+        if id.is_null() {
+            return vec![];
+        }
+        match result {
+            Ok(result) => vec![json!({ "jsonrpc": "2.0", "id": id, "result": result })],
+            Err(message) => vec![json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": REQUEST_FAILED, "message": message },
+            })],
+        }
+    }
+
+    /// Checks a document, returning the notification publishing its errors.
+    fn analyze(&mut self, uri: &str) -> Value {
+        let root: PathBuf = self.root.clone();
+        // Imports of other open documents see their unsaved text.
+        let mut file_system: MemoryFileSystem = MemoryFileSystem::over(DiskFileSystem);
+        for document in self.documents.values() {
+            file_system.insert(&document.path, &document.text);
+        }
+        let document: &mut Document = self.documents.get_mut(uri).unwrap();
+        let path: String = document.path.clone();
+        document.checker = None;
+        // A bug in the compiler is reported on the document instead of taking
+        // the server down with it.
+        let errors: Vec<HarmonyError> =
+            match panic::catch_unwind(AssertUnwindSafe(|| document.check(&root, file_system))) {
+                Ok(errors) => errors,
+                Err(payload) => {
+                    vec![HarmonyError::new(
+                        HarmonyErrorKind::CompileTime,
+                        format!(
+                            "Internal compiler error: {}",
+                            panic_message(payload.as_ref())
+                        ),
+                        None,
+                        SourceLocation {
+                            file: path.clone(),
+                            ..SourceLocation::default()
+                        },
+                    )]
+                }
+            };
+
+        let diagnostics: Vec<Value> = errors
+            .iter()
+            .map(|error| {
+                let mut message: String = error.message.clone();
+                if error.location.file != path {
+                    message = format!("{}: {}", error.location.to_string(), message);
+                }
+                if let Some(hint) = &error.hint {
+                    message.push_str(&format!("\nHint: {}", hint));
+                }
+                json!({
+                    "range": if error.location.file == path {
+                        range(&document.lines, &error.location)
+                    } else {
+                        range(&document.lines, &SourceLocation::default())
+                    },
+                    "severity": if matches!(error.kind, HarmonyErrorKind::Warning) { 2 } else { 1 },
+                    "source": "harmony",
+                    "message": message,
+                })
+            })
+            .collect();
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        })
+    }
+
+    fn hover(&self, uri: &str, position: (usize, usize)) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+        let Some(reference) = document.reference_at(position) else {
+            return Value::Null;
+        };
+        let name: String = document.text_at(&reference.location);
+        let mut contents: String = format!("{} : {}", name, reference.type_);
+        let mut doc: Option<String> = None;
+        if let (Some(checker), Some(compiler)) = (&document.checker, &document.compiler) {
+            let documentation: Documentation = Documentation::new(compiler);
+            let scope = &checker.global_scope;
+            if let Some(function) = scope
+                .functions
+                .values()
+                .find(|function| function.location == reference.definition)
+            {
+                contents = documentation.function_signature(function, false);
+                doc = function.doc.clone();
+            } else if let Some(enum_) = scope.enums.values().find(|enum_| {
+                enum_.variants.iter().any(|variant| match variant {
+                    EnumVariant::Unit(_, location) | EnumVariant::Tuple(_, location, _) => {
+                        *location == reference.definition
+                    }
+                })
+            }) {
+                contents = documentation.enum_signature(enum_, false);
+                doc = enum_.doc.clone();
+            }
+        }
+        let mut value: String = format!("```harmony\n{}\n```", contents);
+        if let Some(doc) = doc {
+            value.push_str(&format!("\n\n{}", doc));
+        }
+        json!({
+            "contents": { "kind": "markdown", "value": value },
+            "range": range(&document.lines, &reference.location),
+        })
+    }
+
+    fn definition(&self, uri: &str, position: (usize, usize)) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return Value::Null;
+        };
+        match document.reference_at(position) {
+            Some(reference) => json!({
+                "uri": path_to_uri(&reference.definition.file),
+                "range": self.range(&reference.definition),
+            }),
+            None => Value::Null,
+        }
+    }
+
+    /// Completes the members of the module imported as `Alias` after `Alias.`.
+    fn completion(&self, uri: &str, position: (usize, usize)) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return json!([]);
+        };
+        let start: usize = document.lines.utf16_offset(position.0, 0);
+        let Some(prefix) = document.text.get(start..document.offset(position)) else {
+            return json!([]);
+        };
+        let Some(before) = prefix
+            .trim_end_matches(is_identifier_char)
+            .strip_suffix('.')
+        else {
+            return json!([]);
+        };
+        let alias: &str = &before[before.trim_end_matches(is_identifier_char).len()..];
+        let Some(compiler) = &document.compiler else {
+            return json!([]);
+        };
+        let Some(import) = compiler
+            .compiled_files
+            .get(&document.path)
+            .and_then(|scope| {
+                scope
+                    .imports
+                    .iter()
+                    .find(|import| import.alias.as_deref() == Some(alias))
+            })
+        else {
+            return json!([]);
+        };
+        let documentation: Documentation = Documentation::new(compiler);
+        let Some(module) = documentation
+            .modules
+            .iter()
+            .find(|module| module.name == import.name)
+        else {
+            return json!([]);
+        };
+        let mut items: Vec<Value> = vec![];
+        for function in &module.functions {
+            items.push(json!({
+                "label": function.name,
+                "kind": 3,
+                "detail": documentation.function_signature(function, false),
+                "documentation": function.doc,
+            }));
+        }
+        for enum_ in &module.enums {
+            for variant in &enum_.variants {
+                let (EnumVariant::Unit(name, _) | EnumVariant::Tuple(name, _, _)) = variant;
+                items.push(json!({
+                    "label": name,
+                    "kind": 20,
+                    "detail": documentation.enum_signature(enum_, false),
+                    "documentation": enum_.doc,
+                }));
+            }
+        }
+        json!(items)
+    }
+
+    fn document_symbols(&self, uri: &str) -> Value {
+        let Some(document) = self.documents.get(uri) else {
+            return json!([]);
+        };
+        let symbols: Vec<Value> = document
+            .statements
+            .iter()
+            .filter_map(|statement| match statement {
+                Statement::Function { name, .. }
+                | Statement::GenericFunction { name, .. }
+                | Statement::ForeignFunction { name, .. } => {
+                    Some(symbol(&document.lines, &name.0, 12, &name.1, vec![]))
+                }
+                Statement::Enum { name, variants, .. }
+                | Statement::GenericEnum { name, variants, .. } => {
+                    let children: Vec<Value> = variants
+                        .iter()
+                        .map(|variant| match variant {
+                            EnumVariant::Unit(name, location)
+                            | EnumVariant::Tuple(name, location, _) => {
+                                symbol(&document.lines, name, 22, location, vec![])
+                            }
+                        })
+                        .collect();
+                    Some(symbol(&document.lines, &name.0, 10, &name.1, children))
+                }
+                Statement::Class { name, methods, .. } => {
+                    let children: Vec<Value> = methods
+                        .iter()
+                        .map(|method| {
+                            symbol(&document.lines, &method.name.0, 6, &method.name.1, vec![])
+                        })
+                        .collect();
+                    Some(symbol(&document.lines, &name.0, 11, &name.1, children))
+                }
+                Statement::Instance {
+                    class,
+                    type_,
+                    methods,
+                } => {
+                    let children: Vec<Value> = methods
+                        .iter()
+                        .filter_map(|method| match method {
+                            Statement::Function { name, .. } => {
+                                Some(symbol(&document.lines, &name.0, 6, &name.1, vec![]))
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    let name: String = format!("{}<{}>", class.0, type_);
+                    Some(symbol(&document.lines, &name, 19, &class.1, children))
+                }
+                Statement::TypeAlias { name, .. } => {
+                    Some(symbol(&document.lines, &name.0, 26, &name.1, vec![]))
+                }
+                Statement::Newtype { name, .. } => {
+                    Some(symbol(&document.lines, &name.0, 23, &name.1, vec![]))
+                }
+                Statement::Test { name, .. } => Some(symbol(
+                    &document.lines,
+                    &format!("test \"{}\"", name.0),
+                    12,
+                    &name.1,
+                    vec![],
+                )),
+                Statement::Module { .. }
+                | Statement::Import { .. }
+                | Statement::ForeignImport { .. } => None,
+            })
+            .collect();
+        json!(symbols)
+    }
+
+    /// Renames a parameter or local binding, and every use of it.
+    fn rename(&self, uri: &str, position: (usize, usize), new_name: &str) -> Result<Value, String> {
+        let Some(document) = self.documents.get(uri) else {
+            return Ok(Value::Null);
+        };
+        let Some(checker) = &document.checker else {
+            return Err("Fix the errors in the file before renaming".to_string());
+        };
+        let definition: Option<&SourceLocation> = match document.reference_at(position) {
+            Some(reference) if reference.local => Some(&reference.definition),
+            Some(_) => None,
+            None => checker
+                .references
+                .iter()
+                .find(|reference| {
+                    reference.local && covers(&reference.definition, document.offset(position))
+                })
+                .map(|reference| &reference.definition),
+        };
+        let Some(definition) = definition else {
+            return Err("Only parameters and local bindings can be renamed".to_string());
+        };
+        let mut characters = new_name.chars();
+        let is_identifier: bool = characters
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && characters.all(is_identifier_char);
+        if !is_identifier {
+            return Err(format!("'{}' is not a valid name", new_name));
+        }
+        let mut edits: Vec<Value> =
+            vec![json!({ "range": range(&document.lines, definition), "newText": new_name })];
+        for reference in &checker.references {
+            if reference.definition == *definition {
+                edits.push(json!({
+                    "range": range(&document.lines, &reference.location),
+                    "newText": new_name,
+                }));
+            }
+        }
+        Ok(json!({ "changes": { uri: edits } }))
+    }
+
+    /// The LSP range of a location in any file, going by the text of its
+    /// open document or else of the file on disk.
+    fn range(&self, location: &SourceLocation) -> Value {
+        let on_disk: LineIndex;
+        let lines: &LineIndex = match self
+            .documents
+            .values()
+            .find(|document| document.path == location.file)
+        {
+            Some(document) => &document.lines,
+            None => {
+                on_disk =
+                    LineIndex::new(&std::fs::read_to_string(&location.file).unwrap_or_default());
+                &on_disk
+            }
+        };
+        range(lines, location)
+    }
+}
+
+impl Default for LanguageServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Document {
+    /// Runs the text through the tokenizer, parser and checker, keeping what
+    /// they produced and returning the errors they found.
+    fn check(&mut self, root: &Path, file_system: MemoryFileSystem) -> Vec<HarmonyError> {
+        let path: String = self.path.clone();
+        let mut errors: Vec<HarmonyError> = vec![];
+        let mut tokenizer: Tokenizer = Tokenizer::new(&path, &self.text);
+        let tokens: Vec<Token> = tokenizer.tokenize();
+        errors.extend(tokenizer.errors.clone());
+        for token in &tokens {
+            if token.kind == TokenKind::Unknown {
+                errors.push(HarmonyError::new(
+                    HarmonyErrorKind::Syntax,
+                    format!("Unknown token: {}", token.lexeme),
+                    None,
+                    token.location.clone(),
+                ));
+            }
+        }
+        if errors.is_empty() && tokens.iter().any(|token| !is_comment(token)) {
+            let mut parser: Parser = Parser::new(tokens);
+            let statements: Result<Vec<Statement>, HarmonyError> = parser.parse();
+            errors.extend(parser.warnings.clone());
+            match statements {
+                Ok(statements) => {
+                    self.statements = statements;
+                    let mut options: CompilerOptions = CompilerOptions::new(vec![]);
+                    options.level = Level::Silent;
+                    let mut compiler: Compiler =
+                        Compiler::new(&options, &vec![path.clone()], vec![]);
+                    compiler.resolver = Box::new(FileResolver::new(root, file_system));
+                    match compiler.check_module(&path, &self.statements) {
+                        Ok(checker) => self.checker = Some(checker),
+                        Err(error) => errors.push(error),
+                    }
+                    if compiler.compiled_files.contains_key(&path) {
+                        self.compiler = Some(compiler);
+                    }
+                }
+                Err(error) => errors.push(error),
+            }
+        }
+        errors
+    }
+
+    /// The identifier the checker resolved at a zero-based line and character.
+    fn reference_at(&self, position: (usize, usize)) -> Option<&Reference> {
+        let offset: usize = self.offset(position);
+        self.checker.as_ref()?.references.iter().find(|reference| {
+            reference.location.file == self.path && covers(&reference.location, offset)
+        })
+    }
+
+    /// The offset in the text of a zero-based line and UTF-16 character.
+    fn offset(&self, (line, character): (usize, usize)) -> usize {
+        self.lines.utf16_offset(line, character)
+    }
+
+    fn text_at(&self, location: &SourceLocation) -> String {
+        self.text
+            .get(location.start..location.end)
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Reads a message framed by a `Content-Length` header, or `None` once the
+/// input ends.
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    let mut length: Option<usize> = None;
+    loop {
+        let mut header: String = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header: &str = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+    let mut body: Vec<u8> = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+/// The LSP range of a location in the text `lines` indexes, in UTF-16 code
+/// units as the protocol counts them.
+fn range(lines: &LineIndex, location: &SourceLocation) -> Value {
+    let (start_line, start_character) = lines.utf16_position(location.start);
+    let (end_line, end_character) = lines.utf16_position(location.end);
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+/// Whether `offset` is in `location`, or just after it where a cursor at the
+/// end of a name is.
+fn covers(location: &SourceLocation, offset: usize) -> bool {
+    location.start <= offset && offset <= location.end
+}
+
+fn symbol(
+    lines: &LineIndex,
+    name: &str,
+    kind: u8,
+    location: &SourceLocation,
+    children: Vec<Value>,
+) -> Value {
+    json!({
+        "name": name,
+        "kind": kind,
+        "range": range(lines, location),
+        "selectionRange": range(lines, location),
+        "children": children,
+    })
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+fn is_comment(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Comment | TokenKind::DocComment)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn uri_to_path(uri: &str) -> String {
+    let path: &str = uri.strip_prefix("file://").unwrap_or(uri);
+    let mut decoded: Vec<u8> = vec![];
+    let bytes: &[u8] = path.as_bytes();
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        let escaped: Option<u8> = match bytes[i] {
+            b'%' if i + 2 < bytes.len() => std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn path_to_uri(path: &str) -> String {
+    format!("file://{}", path.replace('%', "%25").replace(' ', "%20"))
+}
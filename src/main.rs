@@ -1,25 +1,213 @@
-use harmony::compiler::{Compiler, CompilerOptions};
+use harmony::{
+    cache::{self, Cache},
+    compiler::{Compiler, CompilerOptions},
+    doc::Documentation,
+    formatter::{Formatter, DEFAULT_WIDTH},
+    lsp::LanguageServer,
+    repl::Repl,
+    test::TestRunner,
+    watch::Watcher,
+};
 
 fn usage() {
-    println!("Usage: harmony <file> [options]");
+    println!("Usage: harmony [run] <file> [options] [arguments]");
+    println!("       harmony build <file> [options]");
+    println!("       harmony doc <file>... [-o <directory>]");
+    println!("       harmony fmt [--check] [--width <columns>] <file>...");
+    println!("       harmony test [--filter <text>] [<file or directory>...]");
+    println!("       harmony repl [options]");
+    println!("       harmony lsp");
     println!("Options:");
     println!("  -h, --help     Print this help message");
     println!("  -V, --version  Print version information");
     println!("  -k, --keep     Keep the generated JavaScript file");
     println!("  -o <file>, --output <file>");
     println!("                 Output the generated JavaScript to a file");
-    println!("  -v, --verbose  Print verbose output");
+    println!("  -q, --quiet    Print only errors besides the program's output");
+    println!("  -v, --verbose  Print which modules are compiled and how long they take");
+    println!("  -vv, --trace   Print every phase of compiling every module");
+    println!("  --no-cache     Compile every module instead of reusing cached ones");
+    println!("  --watch        Build and run again whenever a source changes");
+    println!("  --runtime <node|deno|bun|path>");
+    println!("                 Run the program with another JavaScript runtime");
+    println!("  --dump=<tokens|ast|ast-json|scope|js>");
+    println!("                 Print the output of a phase of compiling and stop");
+    println!("  --timings      Print how long each phase took for each module");
+}
+
+/// Generates the documentation of `files` and the modules they import,
+/// written to `docs` unless another directory is given with `-o`.
+fn doc(args: &[&String]) {
+    let mut files: Vec<String> = vec![];
+    let mut directory: String = "docs".to_string();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => match args.next() {
+                Some(arg) => directory = arg.to_string(),
+                None => {
+                    usage();
+                    std::process::exit(1);
+                }
+            },
+            _ => files.push(arg.to_string()),
+        }
+    }
+    if files.is_empty() {
+        usage();
+        std::process::exit(1);
+    }
+
+    let mut compiler: Compiler = Compiler::new(&CompilerOptions::new(vec![]), &files, vec![]);
+    compiler.check();
+    let documentation: Documentation = Documentation::new(&compiler);
+    if let Err(error) = documentation.write(&directory) {
+        println!("Could not write documentation to {}: {}", directory, error);
+        std::process::exit(1);
+    }
+    println!(
+        "Documented {} modules in {}",
+        documentation.modules.len(),
+        directory
+    );
+}
+
+/// Formats `files` in place, or with `--check` only lists the files that
+/// would change. Exits with 1 if any would, or if a file can't be parsed.
+fn fmt(args: &[&String]) {
+    let mut files: Vec<String> = vec![];
+    let mut check: bool = false;
+    let mut width: usize = DEFAULT_WIDTH;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-w" | "--width" => match args.next().and_then(|arg| arg.parse().ok()) {
+                Some(columns) => width = columns,
+                None => {
+                    usage();
+                    std::process::exit(1);
+                }
+            },
+            _ => files.push(arg.to_string()),
+        }
+    }
+    if files.is_empty() {
+        usage();
+        std::process::exit(1);
+    }
+
+    let mut failed: bool = false;
+    for file in &files {
+        let source: String = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                println!("Could not read {}: {}", file, error);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted: String = match Formatter::new(file, &source, width).format() {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                println!("{}", error.to_string());
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("{}", file);
+            failed = true;
+        } else if let Err(error) = std::fs::write(file, formatted) {
+            println!("Could not write {}: {}", file, error);
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Runs the tests declared in `paths`, the working directory by default, and
+/// exits with 1 if any failed or didn't compile.
+fn test(args: &[&String]) {
+    let mut paths: Vec<String> = vec![];
+    let mut filter: String = String::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--filter" => match args.next() {
+                Some(arg) => filter = arg.to_string(),
+                None => {
+                    usage();
+                    std::process::exit(1);
+                }
+            },
+            _ => paths.push(arg.to_string()),
+        }
+    }
+    if paths.is_empty() {
+        paths.push(".".to_string());
+    }
+
+    if !TestRunner::new(paths, filter).run() {
+        std::process::exit(1);
+    }
 }
 
 fn main() {
     let args: Vec<String> = std::env::args().skip(1).collect();
-    let file = args.get(0).unwrap();
-    let args = args.iter().skip(1).collect::<Vec<&String>>();
-    let options: Vec<String> = args
-        .iter()
-        .filter(|&arg| arg.starts_with("-") || arg.starts_with("--"))
-        .map(|arg| arg.to_string())
-        .collect();
+    let mut file = args.get(0).unwrap();
+    let mut args = args.iter().skip(1).collect::<Vec<&String>>();
+    let mut build: bool = false;
+    if file == "build" || file == "run" {
+        build = file == "build";
+        if args.is_empty() {
+            usage();
+            std::process::exit(1);
+        }
+        file = args.remove(0);
+    }
+    if file == "doc" {
+        doc(&args);
+        return;
+    }
+    if file == "fmt" {
+        fmt(&args);
+        return;
+    }
+    if file == "test" {
+        test(&args);
+        return;
+    }
+    if file == "lsp" {
+        LanguageServer::new().run();
+        return;
+    }
+    if file == "repl" {
+        let options: CompilerOptions = CompilerOptions::new(args.into_iter().cloned().collect());
+        match Repl::new(&options) {
+            Ok(mut repl) => repl.run(),
+            Err(error) => println!("{}", error.to_string()),
+        }
+        return;
+    }
+    // the options come right after the file name, and the program's
+    // arguments after them
+    let mut options: Vec<String> = vec![];
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        options.push(arg.to_string());
+        if matches!(arg.as_str(), "-o" | "--output" | "--runtime") {
+            options.extend(args.next().cloned());
+        }
+    }
+    let args: Vec<String> = args.map(|arg| arg.to_string()).collect();
+    let watch: bool = options.contains(&"--watch".to_string());
+    options.retain(|option| option != "--watch");
 
     if options.contains(&"-h".to_string()) || options.contains(&"--help".to_string()) {
         usage();
@@ -30,15 +218,29 @@ fn main() {
         return;
     }
 
-    // get all arguments that are after the file name and options
-    let args: Vec<String> = args
-        .iter()
-        .skip_while(|&arg| arg.starts_with("-") || arg.starts_with("--"))
-        .map(|arg| arg.to_string())
-        .collect();
-
     let compiler_options: CompilerOptions = CompilerOptions::new(options);
+    if watch {
+        Watcher::new(compiler_options, file.to_string(), args, !build).watch();
+        return;
+    }
     let mut compiler: Compiler = Compiler::new(&compiler_options, &vec![file.to_string()], args);
+    if !compiler_options.no_cache {
+        compiler.cache = Some(Cache::new(cache::DIRECTORY));
+    }
 
-    compiler.compile();
+    if let Some(dump) = compiler_options.dump {
+        if !dump.print(&mut compiler) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if build {
+        compiler.build();
+        if !compiler.errors.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    std::process::exit(compiler.compile());
 }
@@ -1,21 +1,60 @@
+use std::collections::HashMap;
+
 use crate::{
     ast::{
-        EnumVariant, Expression, Parameter, PatternMatchCase, PatternMatchDirective, Statement,
-        Type,
+        ClassMethod, Constraint, DoStatement, EnumVariant, Expression, Parameter, PatternMatchCase,
+        PatternMatchDirective, Statement, StringPart, Type,
     },
     error::{HarmonyError, HarmonyErrorKind},
     token::{SourceLocation, Token, TokenKind},
+    tokenizer::{parse_float_literal, parse_integer_literal},
 };
 
 #[derive(Debug, Clone)]
 pub struct Parser {
     pub tokens: Vec<Token>,
     pub index: usize,
+    pub warnings: Vec<HarmonyError>,
+    /// Doc comments, keyed by the index of the token that follows them.
+    doc_comments: HashMap<usize, (String, SourceLocation)>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, index: 0 }
+        let mut code_tokens: Vec<Token> = vec![];
+        let mut doc_comments: HashMap<usize, (String, SourceLocation)> = HashMap::new();
+        let mut doc: Option<(String, SourceLocation)> = None;
+        for token in tokens {
+            if token.kind == TokenKind::Comment {
+                continue;
+            }
+            if token.kind == TokenKind::DocComment {
+                let line: &str = token.lexeme.trim_start_matches('-');
+                let line: &str = line.strip_prefix(' ').unwrap_or(line);
+                match &mut doc {
+                    Some((text, _)) => {
+                        text.push('\n');
+                        text.push_str(line);
+                    }
+                    None => doc = Some((line.to_string(), token.location)),
+                }
+                continue;
+            }
+            if let Some(doc) = doc.take() {
+                doc_comments.insert(code_tokens.len(), doc);
+            }
+            code_tokens.push(token);
+        }
+        if let Some(doc) = doc {
+            doc_comments.insert(code_tokens.len(), doc);
+        }
+
+        Parser {
+            tokens: code_tokens,
+            index: 0,
+            warnings: vec![],
+            doc_comments,
+        }
     }
 
     pub fn parse(&mut self) -> Result<Vec<Statement>, HarmonyError> {
@@ -25,9 +64,43 @@ impl Parser {
             statements.push(self.parse_statement()?);
         }
 
+        let mut unattached: Vec<(usize, (String, SourceLocation))> =
+            self.doc_comments.drain().collect();
+        unattached.sort_by_key(|(index, _)| *index);
+        for (_, (_, location)) in unattached {
+            self.warnings.push(HarmonyError::new(
+                HarmonyErrorKind::Warning,
+                "Doc comment is not attached to a declaration".to_string(),
+                Some(
+                    "Doc comments document the function, enum or foreign function below them"
+                        .to_string(),
+                ),
+                location,
+            ));
+        }
+
         Ok(statements)
     }
 
+    /// Parses tokens that hold a single expression and nothing else.
+    pub fn parse_expression_only(&mut self) -> Result<Expression, HarmonyError> {
+        let expression: Expression = self.parse_expression()?;
+        if !self.is_at_end() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Syntax,
+                format!("Unexpected {:?} after expression", self.current()?.kind),
+                None,
+                self.current()?.location,
+            ));
+        }
+        Ok(expression)
+    }
+
+    /// Takes the doc comment written right above the current token.
+    fn take_doc(&mut self) -> Option<String> {
+        self.doc_comments.remove(&self.index).map(|(doc, _)| doc)
+    }
+
     fn parse_statement(&mut self) -> Result<Statement, HarmonyError> {
         match self.current()?.kind {
             TokenKind::Module => self.parse_module(),
@@ -35,6 +108,11 @@ impl Parser {
             TokenKind::Fun => self.parse_function(),
             TokenKind::Enum => self.parse_enum(),
             TokenKind::Foreign => self.parse_foreign(),
+            TokenKind::Class => self.parse_class(),
+            TokenKind::Instance => self.parse_instance(),
+            TokenKind::Type => self.parse_type_alias(),
+            TokenKind::Newtype => self.parse_newtype(),
+            TokenKind::Test => self.parse_test(),
             _ => Err(HarmonyError::new(
                 HarmonyErrorKind::Syntax,
                 format!("Expected statement, found {:?}", self.current()?.kind),
@@ -115,24 +193,19 @@ impl Parser {
     }
 
     fn parse_function(&mut self) -> Result<Statement, HarmonyError> {
+        let doc: Option<String> = self.take_doc();
         self.expect(TokenKind::Fun)?;
         let location: SourceLocation = self.current()?.location;
         let name = self.expect(TokenKind::Identifier)?.lexeme;
         let mut generic_parameters: Vec<Type> = vec![];
+        let mut constraints: Vec<Constraint> = vec![];
         if !self.is_at_end() && self.current()?.kind == TokenKind::LessThan {
             self.expect(TokenKind::LessThan)?;
-            let location: SourceLocation = self.current()?.location;
-            generic_parameters.push(Type::GenericParameter(
-                self.expect(TokenKind::Identifier)?.lexeme,
-                location,
-            ));
+            generic_parameters.push(self.parse_constrained_generic_parameter(&mut constraints)?);
             while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
                 self.expect(TokenKind::Comma)?;
-                let location: SourceLocation = self.current()?.location;
-                generic_parameters.push(Type::GenericParameter(
-                    self.expect(TokenKind::Identifier)?.lexeme,
-                    location,
-                ));
+                generic_parameters
+                    .push(self.parse_constrained_generic_parameter(&mut constraints)?);
             }
             self.expect(TokenKind::GreaterThan)?;
         }
@@ -141,24 +214,10 @@ impl Parser {
         if !self.is_at_end() && self.current()?.kind == TokenKind::OpenParenthesis {
             self.expect(TokenKind::OpenParenthesis)?;
             if !self.is_at_end() && self.current()?.kind == TokenKind::Identifier {
-                let location: SourceLocation = self.current()?.location;
-                let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                self.expect(TokenKind::Colon)?;
-                let type_ = self.parse_type()?;
-                parameters.push(Parameter {
-                    name: (identifier, location),
-                    type_,
-                });
+                parameters.push(self.parse_parameter()?);
                 while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
                     self.expect(TokenKind::Comma)?;
-                    let location: SourceLocation = self.current()?.location;
-                    let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                    self.expect(TokenKind::Colon)?;
-                    let type_ = self.parse_type()?;
-                    parameters.push(Parameter {
-                        name: (identifier, location),
-                        type_,
-                    });
+                    parameters.push(self.parse_parameter()?);
                 }
             }
             self.expect(TokenKind::CloseParenthesis)?;
@@ -171,9 +230,11 @@ impl Parser {
             return Ok(Statement::GenericFunction {
                 name: (name, location),
                 generic_parameters,
+                constraints,
                 parameters,
                 return_type,
                 body,
+                doc,
             });
         }
         Ok(Statement::Function {
@@ -181,10 +242,139 @@ impl Parser {
             parameters,
             return_type,
             body,
+            doc,
+        })
+    }
+
+    fn parse_constrained_generic_parameter(
+        &mut self,
+        constraints: &mut Vec<Constraint>,
+    ) -> Result<Type, HarmonyError> {
+        let location: SourceLocation = self.current()?.location;
+        let name = self.expect(TokenKind::Identifier)?.lexeme;
+        if !self.is_at_end() && self.current()?.kind == TokenKind::Colon {
+            self.expect(TokenKind::Colon)?;
+            loop {
+                let class_location: SourceLocation = self.current()?.location;
+                let class = self.expect(TokenKind::Identifier)?.lexeme;
+                constraints.push(Constraint {
+                    parameter: name.clone(),
+                    class: (class, class_location),
+                });
+                if self.is_at_end() || self.current()?.kind != TokenKind::Plus {
+                    break;
+                }
+                self.expect(TokenKind::Plus)?;
+            }
+        }
+        Ok(Type::GenericParameter(name, location))
+    }
+
+    fn parse_class(&mut self) -> Result<Statement, HarmonyError> {
+        self.expect(TokenKind::Class)?;
+        let location: SourceLocation = self.current()?.location;
+        let name = self.expect(TokenKind::Identifier)?.lexeme;
+        self.expect(TokenKind::LessThan)?;
+        let parameter_location: SourceLocation = self.current()?.location;
+        let parameter = self.expect(TokenKind::Identifier)?.lexeme;
+        self.expect(TokenKind::GreaterThan)?;
+        self.expect(TokenKind::Where)?;
+        let mut methods: Vec<ClassMethod> = vec![];
+        while !self.is_at_end() && self.current()?.kind != TokenKind::End {
+            let method_location: SourceLocation = self.current()?.location;
+            let method = self.expect(TokenKind::Identifier)?.lexeme;
+            self.expect(TokenKind::Colon)?;
+            let type_: Type = self.parse_type()?;
+            methods.push(ClassMethod {
+                name: (method, method_location),
+                type_,
+            });
+        }
+        self.expect(TokenKind::End)?;
+        Ok(Statement::Class {
+            name: (name, location),
+            generic_parameter: (parameter, parameter_location),
+            methods,
+        })
+    }
+
+    fn parse_instance(&mut self) -> Result<Statement, HarmonyError> {
+        self.expect(TokenKind::Instance)?;
+        let location: SourceLocation = self.current()?.location;
+        let class = self.expect(TokenKind::Identifier)?.lexeme;
+        self.expect(TokenKind::LessThan)?;
+        let type_: Type = self.parse_type()?;
+        self.expect_generic_close()?;
+        self.expect(TokenKind::Where)?;
+        let mut methods: Vec<Statement> = vec![];
+        while !self.is_at_end() && self.current()?.kind != TokenKind::End {
+            methods.push(self.parse_function()?);
+        }
+        self.expect(TokenKind::End)?;
+        Ok(Statement::Instance {
+            class: (class, location),
+            type_,
+            methods,
+        })
+    }
+
+    fn parse_type_alias(&mut self) -> Result<Statement, HarmonyError> {
+        self.expect(TokenKind::Type)?;
+        let location: SourceLocation = self.current()?.location;
+        let name = self.expect(TokenKind::Identifier)?.lexeme;
+        let mut generic_parameters: Vec<Type> = vec![];
+        if !self.is_at_end() && self.current()?.kind == TokenKind::LessThan {
+            self.expect(TokenKind::LessThan)?;
+            let location: SourceLocation = self.current()?.location;
+            generic_parameters.push(Type::GenericParameter(
+                self.expect(TokenKind::Identifier)?.lexeme,
+                location,
+            ));
+            while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
+                self.expect(TokenKind::Comma)?;
+                let location: SourceLocation = self.current()?.location;
+                generic_parameters.push(Type::GenericParameter(
+                    self.expect(TokenKind::Identifier)?.lexeme,
+                    location,
+                ));
+            }
+            self.expect(TokenKind::GreaterThan)?;
+        }
+        self.expect(TokenKind::Equals)?;
+        let type_: Type = self.parse_type()?;
+        Ok(Statement::TypeAlias {
+            name: (name, location),
+            generic_parameters,
+            type_,
+        })
+    }
+
+    fn parse_newtype(&mut self) -> Result<Statement, HarmonyError> {
+        self.expect(TokenKind::Newtype)?;
+        let location: SourceLocation = self.current()?.location;
+        let name = self.expect(TokenKind::Identifier)?.lexeme;
+        self.expect(TokenKind::Equals)?;
+        let type_: Type = self.parse_type()?;
+        Ok(Statement::Newtype {
+            name: (name, location),
+            type_,
+        })
+    }
+
+    fn parse_test(&mut self) -> Result<Statement, HarmonyError> {
+        self.expect(TokenKind::Test)?;
+        let location: SourceLocation = self.current()?.location;
+        let name = self.expect(TokenKind::StringLiteral)?.lexeme;
+        self.expect(TokenKind::Equals)?;
+        let body: Expression = self.parse_expression()?;
+        Ok(Statement::Test {
+            name: (name, location),
+            body,
         })
     }
 
     fn parse_enum(&mut self) -> Result<Statement, HarmonyError> {
+        let doc: Option<String> = self.take_doc();
         self.expect(TokenKind::Enum)?;
         let location: SourceLocation = self.current()?.location;
         let name = self.expect(TokenKind::Identifier)?.lexeme;
@@ -217,12 +407,14 @@ impl Parser {
             Ok(Statement::Enum {
                 name: (name, location),
                 variants,
+                doc,
             })
         } else {
             Ok(Statement::GenericEnum {
                 name: (name, location),
                 generic_parameters,
                 variants,
+                doc,
             })
         }
     }
@@ -263,6 +455,12 @@ impl Parser {
     }
 
     fn parse_foreign(&mut self) -> Result<Statement, HarmonyError> {
+        // Only foreign functions take a doc comment, one above a foreign import
+        // is reported as unattached.
+        let doc: Option<String> = match self.tokens.get(self.index + 1) {
+            Some(token) if token.kind == TokenKind::Fun => self.take_doc(),
+            _ => None,
+        };
         self.expect(TokenKind::Foreign)?;
         let location: SourceLocation = self.current()?.location;
         if self.current()?.kind == TokenKind::Import {
@@ -296,24 +494,10 @@ impl Parser {
             if !self.is_at_end() && self.current()?.kind == TokenKind::OpenParenthesis {
                 self.expect(TokenKind::OpenParenthesis)?;
                 if !self.is_at_end() && self.current()?.kind == TokenKind::Identifier {
-                    let location: SourceLocation = self.current()?.location;
-                    let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                    self.expect(TokenKind::Colon)?;
-                    let type_ = self.parse_type()?;
-                    parameters.push(Parameter {
-                        name: (identifier, location),
-                        type_,
-                    });
+                    parameters.push(self.parse_parameter()?);
                     while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
                         self.expect(TokenKind::Comma)?;
-                        let location: SourceLocation = self.current()?.location;
-                        let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                        self.expect(TokenKind::Colon)?;
-                        let type_ = self.parse_type()?;
-                        parameters.push(Parameter {
-                            name: (identifier, location),
-                            type_,
-                        });
+                        parameters.push(self.parse_parameter()?);
                     }
                 }
                 self.expect(TokenKind::CloseParenthesis)?;
@@ -328,6 +512,7 @@ impl Parser {
                 parameters,
                 return_type,
                 binding: (binding, binding_location),
+                doc,
             });
         }
         Err(HarmonyError::new(
@@ -346,15 +531,21 @@ impl Parser {
         let mut left = self.parse_unary_expression()?;
         while !self.is_at_end() && self.current()?.kind.is_binary_operator() {
             let operator = self.current()?.kind.clone();
+            let operator_location: SourceLocation = self.current()?.location;
             let operator_precedence = operator.precedence();
             if operator_precedence < precedence {
                 break;
             }
             self.expect(operator.clone())?;
-            let right = self.parse_binary_expression(operator_precedence + 1)?;
+            let right = if operator.is_right_associative() {
+                self.parse_binary_expression(operator_precedence)?
+            } else {
+                self.parse_binary_expression(operator_precedence + 1)?
+            };
             left = Expression::Binary {
                 left: Box::new(left),
                 operator,
+                operator_location,
                 right: Box::new(right),
             };
         }
@@ -363,12 +554,14 @@ impl Parser {
 
     fn parse_unary_expression(&mut self) -> Result<Expression, HarmonyError> {
         if !self.is_at_end() && self.current()?.kind.is_unary_operator() {
+            let location: SourceLocation = self.current()?.location;
             let operator = self.current()?.kind.clone();
             self.expect(operator.clone())?;
             let right = self.parse_unary_expression()?;
             Ok(Expression::Unary {
                 operator,
                 right: Box::new(right),
+                location: self.since(&location),
             })
         } else {
             self.parse_index_expression()
@@ -377,13 +570,18 @@ impl Parser {
 
     fn parse_index_expression(&mut self) -> Result<Expression, HarmonyError> {
         let mut expression: Expression = self.parse_primary_expression()?;
-        while !self.is_at_end() && self.current()?.kind == TokenKind::OpenBracket {
+        while !self.is_at_end()
+            && self.current()?.kind == TokenKind::OpenBracket
+            && self.continues_line()
+        {
             self.expect(TokenKind::OpenBracket)?;
             let index = self.parse_expression()?;
             self.expect(TokenKind::CloseBracket)?;
+            let location: SourceLocation = self.since(&expression.location());
             expression = Expression::Index {
                 expression: Box::new(expression),
                 index: Box::new(index),
+                location,
             };
         }
         Ok(expression)
@@ -420,21 +618,29 @@ impl Parser {
                 //         generic_arguments,
                 //         arguments,
                 //     })
-                if !self.is_at_end() && self.current()?.kind == TokenKind::OpenParenthesis {
+                if !self.is_at_end()
+                    && self.current()?.kind == TokenKind::OpenParenthesis
+                    && self.continues_line()
+                {
                     self.expect(TokenKind::OpenParenthesis)?;
                     let mut arguments: Vec<Expression> = vec![];
+                    let mut argument_names: Vec<Option<(String, SourceLocation)>> = vec![];
                     if !self.is_at_end() && self.current()?.kind != TokenKind::CloseParenthesis {
+                        argument_names.push(self.parse_argument_name()?);
                         arguments.push(self.parse_expression()?);
                         while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
                             self.expect(TokenKind::Comma)?;
+                            argument_names.push(self.parse_argument_name()?);
                             arguments.push(self.parse_expression()?);
                         }
                     }
                     self.expect(TokenKind::CloseParenthesis)?;
                     Ok(Expression::Call {
+                        location: self.since(&location),
                         callee: (identifier, location),
                         generic_arguments: vec![],
                         arguments,
+                        argument_names,
                     })
                 } else if !self.is_at_end() && self.current()?.kind == TokenKind::Dot {
                     self.expect(TokenKind::Dot)?;
@@ -447,9 +653,11 @@ impl Parser {
                     self.expect(TokenKind::Dollar)?;
                     let argument: Expression = self.parse_expression()?;
                     Ok(Expression::Call {
+                        location: self.since(&location),
                         callee: (identifier, location),
                         generic_arguments: vec![],
                         arguments: vec![argument],
+                        argument_names: vec![None],
                     })
                 } else {
                     Ok(Expression::Identifier(identifier, location))
@@ -458,18 +666,60 @@ impl Parser {
             TokenKind::IntegerLiteral => {
                 let location: SourceLocation = self.current()?.location;
                 let value = self.expect(TokenKind::IntegerLiteral)?.lexeme;
-                Ok(Expression::Integer(value.parse::<i64>().unwrap(), location))
+                let value: i64 = parse_integer_literal(&value).map_err(|message| {
+                    HarmonyError::new(HarmonyErrorKind::Syntax, message, None, location.clone())
+                })?;
+                Ok(Expression::Integer(value, location))
             }
             TokenKind::FloatLiteral => {
                 let location: SourceLocation = self.current()?.location;
                 let value = self.expect(TokenKind::FloatLiteral)?.lexeme;
-                Ok(Expression::Float(value.parse::<f64>().unwrap(), location))
+                let value: f64 = parse_float_literal(&value).map_err(|message| {
+                    HarmonyError::new(HarmonyErrorKind::Syntax, message, None, location.clone())
+                })?;
+                Ok(Expression::Float(value, location))
             }
             TokenKind::StringLiteral => {
                 let location: SourceLocation = self.current()?.location;
                 let value = self.expect(TokenKind::StringLiteral)?.lexeme;
                 Ok(Expression::String(value, location))
             }
+            TokenKind::TemplateString => {
+                let location: SourceLocation = self.current()?.location;
+                let value = self.expect(TokenKind::TemplateString)?.lexeme;
+                Ok(Expression::InterpolatedString(
+                    vec![StringPart::Literal(value)],
+                    location,
+                ))
+            }
+            TokenKind::TemplateHead => {
+                let location: SourceLocation = self.current()?.location;
+                let mut parts: Vec<StringPart> = vec![StringPart::Literal(
+                    self.expect(TokenKind::TemplateHead)?.lexeme,
+                )];
+                let mut interpolation: SourceLocation = location.clone();
+                loop {
+                    parts.push(StringPart::Expression(
+                        Box::new(self.parse_expression()?),
+                        interpolation.clone(),
+                    ));
+                    interpolation = self.current()?.location;
+                    match self.current()?.kind {
+                        TokenKind::TemplateMiddle => {
+                            parts.push(StringPart::Literal(
+                                self.expect(TokenKind::TemplateMiddle)?.lexeme,
+                            ));
+                        }
+                        _ => {
+                            parts.push(StringPart::Literal(
+                                self.expect(TokenKind::TemplateTail)?.lexeme,
+                            ));
+                            break;
+                        }
+                    }
+                }
+                Ok(Expression::InterpolatedString(parts, self.since(&location)))
+            }
             TokenKind::CharacterLiteral => {
                 let location: SourceLocation = self.current()?.location;
                 let value = self.expect(TokenKind::CharacterLiteral)?.lexeme;
@@ -481,6 +731,7 @@ impl Parser {
                 Ok(Expression::Bool(value.parse::<bool>().unwrap(), location))
             }
             TokenKind::Case => {
+                let location: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::Case)?;
                 let expression: Expression = self.parse_expression()?;
                 self.expect(TokenKind::Of)?;
@@ -503,7 +754,7 @@ impl Parser {
                     let pattern: Expression = self.parse_expression()?;
                     if !self.is_at_end() && self.current()?.kind == TokenKind::If {
                         self.expect(TokenKind::If)?;
-                        directive = PatternMatchDirective::If(self.parse_expression()?);
+                        directive = PatternMatchDirective::If(Box::new(self.parse_expression()?));
                     }
                     self.expect(TokenKind::FatArrow)?;
                     let body: Expression = self.parse_expression()?;
@@ -518,9 +769,11 @@ impl Parser {
                     expression: Box::new(expression),
                     cases,
                     default_case,
+                    location: self.since(&location),
                 })
             }
             TokenKind::OpenBracket => {
+                let location: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::OpenBracket)?;
                 let mut elements: Vec<Expression> = vec![];
                 if !self.is_at_end() && self.current()?.kind != TokenKind::CloseBracket {
@@ -531,9 +784,10 @@ impl Parser {
                     }
                 }
                 self.expect(TokenKind::CloseBracket)?;
-                Ok(Expression::List(elements))
+                Ok(Expression::List(elements, self.since(&location)))
             }
             TokenKind::If => {
+                let location: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::If)?;
                 let condition: Expression = self.parse_expression()?;
                 self.expect(TokenKind::Then)?;
@@ -544,14 +798,20 @@ impl Parser {
                     condition: Box::new(condition),
                     then_branch: Box::new(then_branch),
                     else_branch: Box::new(else_branch),
+                    location: self.since(&location),
                 })
             }
             TokenKind::DoubleDot => {
+                let location: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::DoubleDot)?;
                 let expression: Expression = self.parse_expression()?;
-                Ok(Expression::Rest(Box::new(expression)))
+                Ok(Expression::Rest(
+                    Box::new(expression),
+                    self.since(&location),
+                ))
             }
             TokenKind::Let => {
+                let start: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::Let)?;
                 let location: SourceLocation = self.current()?.location;
                 let name = self.expect(TokenKind::Identifier)?.lexeme;
@@ -569,33 +829,55 @@ impl Parser {
                     type_annotation: ty,
                     value: Box::new(value),
                     body: Box::new(body),
+                    location: self.since(&start),
                 })
             }
+            TokenKind::Do => {
+                let location: SourceLocation = self.current()?.location;
+                self.expect(TokenKind::Do)?;
+                let mut statements: Vec<DoStatement> = vec![];
+                while !self.is_at_end() && self.current()?.kind != TokenKind::End {
+                    statements.push(self.parse_do_statement()?);
+                    if self.current()?.kind == TokenKind::Semicolon {
+                        self.expect(TokenKind::Semicolon)?;
+                    } else if self.current()?.kind != TokenKind::End && self.continues_line() {
+                        return Err(HarmonyError::new(
+                            HarmonyErrorKind::Syntax,
+                            format!(
+                                "Expected a new line or ';' after a do statement, found {:?}",
+                                self.current()?.kind
+                            ),
+                            Some("Put each statement of a do block on its own line".to_string()),
+                            self.current()?.location,
+                        ));
+                    }
+                }
+                self.expect(TokenKind::End)?;
+                match statements.last() {
+                    Some(DoStatement::Expression(_)) => Ok(Expression::Do {
+                        statements,
+                        location: self.since(&location),
+                    }),
+                    _ => Err(HarmonyError::new(
+                        HarmonyErrorKind::Syntax,
+                        "The last statement of a do block must be an expression".to_string(),
+                        None,
+                        location,
+                    )),
+                }
+            }
             TokenKind::Fun => {
+                let location: SourceLocation = self.current()?.location;
                 self.expect(TokenKind::Fun)?;
                 let mut parameters: Vec<Parameter> = vec![];
                 let mut return_type: Option<Type> = None;
                 if !self.is_at_end() && self.current()?.kind == TokenKind::OpenParenthesis {
                     self.expect(TokenKind::OpenParenthesis)?;
                     if !self.is_at_end() && self.current()?.kind == TokenKind::Identifier {
-                        let location: SourceLocation = self.current()?.location;
-                        let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                        self.expect(TokenKind::Colon)?;
-                        let type_ = self.parse_type()?;
-                        parameters.push(Parameter {
-                            name: (identifier, location),
-                            type_,
-                        });
+                        parameters.push(self.parse_parameter()?);
                         while !self.is_at_end() && self.current()?.kind == TokenKind::Comma {
                             self.expect(TokenKind::Comma)?;
-                            let location: SourceLocation = self.current()?.location;
-                            let identifier = self.expect(TokenKind::Identifier)?.lexeme;
-                            self.expect(TokenKind::Colon)?;
-                            let type_ = self.parse_type()?;
-                            parameters.push(Parameter {
-                                name: (identifier, location),
-                                type_,
-                            });
+                            parameters.push(self.parse_parameter()?);
                         }
                     }
                     self.expect(TokenKind::CloseParenthesis)?;
@@ -608,8 +890,16 @@ impl Parser {
                     parameters,
                     return_type,
                     body: Box::new(body),
+                    location: self.since(&location),
                 })
             }
+            // Parentheses only group, so they leave nothing in the AST.
+            TokenKind::OpenParenthesis => {
+                self.expect(TokenKind::OpenParenthesis)?;
+                let expression: Expression = self.parse_expression()?;
+                self.expect(TokenKind::CloseParenthesis)?;
+                Ok(expression)
+            }
             _ => Err(HarmonyError::new(
                 HarmonyErrorKind::Syntax,
                 format!("Expected expression, found {:?}", self.current()?.kind),
@@ -619,6 +909,81 @@ impl Parser {
         }
     }
 
+    fn parse_parameter(&mut self) -> Result<Parameter, HarmonyError> {
+        let location: SourceLocation = self.current()?.location;
+        let identifier = self.expect(TokenKind::Identifier)?.lexeme;
+        self.expect(TokenKind::Colon)?;
+        let type_ = self.parse_type()?;
+        let mut default: Option<Expression> = None;
+        if !self.is_at_end() && self.current()?.kind == TokenKind::Equals {
+            self.expect(TokenKind::Equals)?;
+            default = Some(self.parse_expression()?);
+        }
+        Ok(Parameter {
+            name: (identifier, location),
+            type_,
+            default,
+        })
+    }
+
+    fn parse_argument_name(&mut self) -> Result<Option<(String, SourceLocation)>, HarmonyError> {
+        if self.current()?.kind == TokenKind::Identifier
+            && self.peek_kind(1) == Some(TokenKind::Equals)
+        {
+            let location: SourceLocation = self.current()?.location;
+            let name = self.expect(TokenKind::Identifier)?.lexeme;
+            self.expect(TokenKind::Equals)?;
+            return Ok(Some((name, location)));
+        }
+        Ok(None)
+    }
+
+    fn parse_do_statement(&mut self) -> Result<DoStatement, HarmonyError> {
+        if self.current()?.kind == TokenKind::Identifier
+            && self.peek_kind(1) == Some(TokenKind::LeftArrow)
+        {
+            let location: SourceLocation = self.current()?.location;
+            let name = self.expect(TokenKind::Identifier)?.lexeme;
+            self.expect(TokenKind::LeftArrow)?;
+            let value: Expression = self.parse_expression()?;
+            return Ok(DoStatement::Bind {
+                name: (name, location),
+                value,
+            });
+        }
+        if self.current()?.kind == TokenKind::Let {
+            let start: SourceLocation = self.current()?.location;
+            self.expect(TokenKind::Let)?;
+            let location: SourceLocation = self.current()?.location;
+            let name = self.expect(TokenKind::Identifier)?.lexeme;
+            let mut ty: Option<Type> = None;
+            if self.current()?.kind == TokenKind::Colon {
+                self.expect(TokenKind::Colon)?;
+                ty = Some(self.parse_type()?);
+            }
+            self.expect(TokenKind::Equals)?;
+            let value: Expression = self.parse_expression()?;
+            // `let ... in ...` is still an ordinary expression inside a do block
+            if !self.is_at_end() && self.current()?.kind == TokenKind::In {
+                self.expect(TokenKind::In)?;
+                let body: Expression = self.parse_expression()?;
+                return Ok(DoStatement::Expression(Expression::Let {
+                    name: (name, location),
+                    type_annotation: ty,
+                    value: Box::new(value),
+                    body: Box::new(body),
+                    location: self.since(&start),
+                }));
+            }
+            return Ok(DoStatement::Let {
+                name: (name, location),
+                type_annotation: ty,
+                value,
+            });
+        }
+        Ok(DoStatement::Expression(self.parse_expression()?))
+    }
+
     fn parse_type(&mut self) -> Result<Type, HarmonyError> {
         match self.current()?.kind {
             TokenKind::Identifier => {
@@ -642,7 +1007,7 @@ impl Parser {
                             ty => ty,
                         });
                     }
-                    self.expect(TokenKind::GreaterThan)?;
+                    self.expect_generic_close()?;
                     Ok(Type::Generic(identifier, location, types))
                 } else {
                     Ok(Type::Identifier(identifier, location))
@@ -739,6 +1104,37 @@ impl Parser {
         }
     }
 
+    /// The location from `start` to the end of the last token parsed.
+    fn since(&self, start: &SourceLocation) -> SourceLocation {
+        start.merge(&self.tokens[self.index - 1].location)
+    }
+
+    /// Whether the current token is on the line the previous one ends on, as
+    /// the `(` of a call and the `[` of an index have to be.
+    fn continues_line(&self) -> bool {
+        let previous: &Token = &self.tokens[self.index - 1];
+        let end: usize = previous.location.line + previous.lexeme.matches('\n').count();
+        self.tokens[self.index].location.line == end
+    }
+
+    fn peek_kind(&self, offset: usize) -> Option<TokenKind> {
+        self.tokens
+            .get(self.index + offset)
+            .map(|token| token.kind.clone())
+    }
+
+    fn expect_generic_close(&mut self) -> Result<(), HarmonyError> {
+        // `>>` is tokenized as forward composition, so nested generic arguments
+        // like `Maybe<Maybe<T>>` need it split back into two `>` tokens.
+        if !self.is_at_end() && self.current()?.kind == TokenKind::ComposeForward {
+            self.tokens[self.index].kind = TokenKind::GreaterThan;
+            self.tokens[self.index].lexeme = ">".to_string();
+            return Ok(());
+        }
+        self.expect(TokenKind::GreaterThan)?;
+        Ok(())
+    }
+
     fn is_at_end(&self) -> bool {
         self.index >= self.tokens.len()
     }
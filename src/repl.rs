@@ -0,0 +1,422 @@
+use std::io::Write;
+
+use crate::{
+    ast::{Expression, Statement, Type},
+    checker::{Checker, LocalScope},
+    codegen::Codegen,
+    compiler::{Compiler, CompilerOptions},
+    doc::Documentation,
+    error::{HarmonyError, HarmonyErrorKind},
+    parser::Parser,
+    token::{SourceLocation, Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+/// The module everything entered at the prompt is declared in. It lives in
+/// the root directory of the compiler options, so modules there can be
+/// imported like from a file, and its JavaScript is written next to it.
+const FILE: &str = "Repl.harm";
+
+const HELP: &str = "Enter an expression to evaluate it, or a declaration to add it.
+Commands:
+  :type <expression>  Print the type of an expression
+  :load <file>        Load the declarations of a module
+  :reload             Load the last loaded module again
+  :browse <module>    List the functions and enums of an imported module
+  :help               Print this help message
+  :quit               Exit the REPL";
+
+/// An interactive session started by `harmony repl`.
+///
+/// Declarations accumulate in the `Repl` module, which is checked again as a
+/// whole whenever something is added to it. An expression is evaluated by
+/// compiling that module with the expression as its `main`, and running it.
+pub struct Repl {
+    compiler: Compiler,
+    /// Holds the scope of everything declared so far.
+    checker: Checker,
+    /// Imports and declarations entered at the prompt, in order.
+    entered: Vec<Statement>,
+    /// The file given to `:load` and its statements, which `:reload` reads
+    /// again.
+    loaded: Option<(String, Vec<Statement>)>,
+    /// The line the next input starts at. Each input gets its own lines so
+    /// that its tokens' locations don't collide with earlier ones.
+    line: usize,
+}
+
+impl Repl {
+    /// Starts a session whose expressions are compiled and run with `options`,
+    /// like a file given to `harmony run`.
+    pub fn new(options: &CompilerOptions) -> Result<Repl, HarmonyError> {
+        let mut compiler: Compiler = Compiler::new(options, &vec![], vec![]);
+        let checker: Checker = compiler.check_module(&FILE.to_string(), &vec![module()])?;
+        Ok(Repl {
+            compiler,
+            checker,
+            entered: vec![],
+            loaded: None,
+            line: 1,
+        })
+    }
+
+    pub fn run(&mut self) {
+        println!(
+            "Harmony {} REPL. Type :help for help.",
+            env!("CARGO_PKG_VERSION")
+        );
+        let mut input: String = String::new();
+        loop {
+            print!("{}", if input.is_empty() { "> " } else { "| " });
+            std::io::stdout().flush().unwrap();
+            let mut line: String = String::new();
+            if std::io::stdin().read_line(&mut line).unwrap_or(0) == 0 {
+                println!();
+                break;
+            }
+            input.push_str(&line);
+            if input.trim().is_empty() {
+                input.clear();
+                continue;
+            }
+            if input.trim() == ":quit" || input.trim() == ":q" {
+                break;
+            }
+            match self.eval(&input) {
+                Err(error) if error.message == "Unexpected end of file" => continue,
+                Err(error) => println!("{}", error.to_string()),
+                Ok(()) => {}
+            }
+            self.line += input.lines().count();
+            input.clear();
+        }
+        self.clean_up();
+    }
+
+    /// Handles one input: a command, a declaration or an expression.
+    pub fn eval(&mut self, input: &str) -> Result<(), HarmonyError> {
+        let input: &str = input.trim();
+        if let Some(command) = input.strip_prefix(':') {
+            let (command, argument) = match command.split_once(char::is_whitespace) {
+                Some((command, argument)) => (command, argument.trim()),
+                None => (command, ""),
+            };
+            return match command {
+                "t" | "type" => {
+                    let expression: Expression = self.parse_expression(argument)?;
+                    let type_: Type = self.type_of(&expression)?;
+                    println!("{} : {}", argument, type_);
+                    Ok(())
+                }
+                "l" | "load" => self.load(argument),
+                "r" | "reload" => match self.loaded.clone() {
+                    Some((file, _)) => self.load(&file),
+                    None => {
+                        println!("No module has been loaded yet");
+                        Ok(())
+                    }
+                },
+                "b" | "browse" => {
+                    self.browse(argument);
+                    Ok(())
+                }
+                "h" | "help" => {
+                    println!("{}", HELP);
+                    Ok(())
+                }
+                _ => {
+                    println!("Unknown command :{}, type :help for help", command);
+                    Ok(())
+                }
+            };
+        }
+
+        let tokens: Vec<Token> = self.tokenize(input)?;
+        if tokens.is_empty() {
+            return Ok(());
+        }
+        let is_declaration: bool = match tokens.first().map(|token| &token.kind) {
+            Some(TokenKind::Fun) => tokens
+                .get(1)
+                .is_some_and(|token| token.kind == TokenKind::Identifier),
+            Some(
+                TokenKind::Import
+                | TokenKind::Enum
+                | TokenKind::Foreign
+                | TokenKind::Class
+                | TokenKind::Instance
+                | TokenKind::Type
+                | TokenKind::Newtype,
+            ) => true,
+            _ => false,
+        };
+        if is_declaration {
+            let statements: Vec<Statement> = Parser::new(tokens).parse()?;
+            return self.declare(statements);
+        }
+        let expression: Expression = Parser::new(tokens).parse_expression_only()?;
+        self.evaluate(expression)
+    }
+
+    /// Adds declarations to the `Repl` module, replacing earlier ones with the
+    /// same name.
+    fn declare(&mut self, statements: Vec<Statement>) -> Result<(), HarmonyError> {
+        let mut entered: Vec<Statement> = self.entered.clone();
+        for statement in statements {
+            if let Statement::Function { name, .. } | Statement::GenericFunction { name, .. } =
+                &statement
+            {
+                if name.0 == "main" {
+                    return Err(HarmonyError::new(
+                        HarmonyErrorKind::CompileTime,
+                        "main can't be declared in the REPL".to_string(),
+                        Some("Enter its body as an expression to evaluate it".to_string()),
+                        name.1.clone(),
+                    ));
+                }
+            }
+            let name: Option<String> = declared_name(&statement);
+            if name.is_some() {
+                entered.retain(|entered| declared_name(entered) != name);
+            }
+            entered.push(statement);
+        }
+        let checker: Checker = self.check(&entered, None)?;
+        self.set_checker(checker);
+        self.entered = entered;
+        Ok(())
+    }
+
+    /// Compiles the `Repl` module with `expression` as its `main` and runs it,
+    /// printing the value and type of the expression unless it is `unit`.
+    fn evaluate(&mut self, expression: Expression) -> Result<(), HarmonyError> {
+        let type_: Type = self.type_of(&expression)?;
+        let main: Statement = Statement::Function {
+            name: (
+                "main".to_string(),
+                SourceLocation {
+                    file: FILE.to_string(),
+                    start: 0,
+                    end: 0,
+                    line: self.line,
+                    column: 0,
+                },
+            ),
+            parameters: vec![],
+            return_type: Some(type_.clone()),
+            body: expression,
+            doc: None,
+        };
+        let checker: Checker = self.check(&self.entered.clone(), Some(main))?;
+
+        let mut code: String = Codegen::new(&checker.statements, &checker).generate();
+        if !matches!(type_, Type::Unit(_)) {
+            code.push_str(&format!(
+                "import {{ inspect }} from \"node:util\";\nconsole.log(inspect(main) + \" : \" + {});\n",
+                serde_json::to_string(&type_.to_string()).unwrap()
+            ));
+        }
+        self.compiler.write();
+        let file: String = self.file();
+        std::fs::write(file.replace(".harm", ".mjs"), code).unwrap();
+        match self.compiler.spawn(&file) {
+            Ok(mut child) => {
+                let _ = child.wait();
+            }
+            Err(message) => println!("{}", message),
+        }
+        Ok(())
+    }
+
+    /// The path of the `Repl` module in the root directory.
+    fn file(&self) -> String {
+        self.compiler.options.root.join(FILE).display().to_string()
+    }
+
+    fn type_of(&self, expression: &Expression) -> Result<Type, HarmonyError> {
+        self.checker
+            .clone()
+            .check_expression(expression, &mut LocalScope::new())
+    }
+
+    /// Replaces the declarations of the loaded module with those of `file`.
+    fn load(&mut self, file: &str) -> Result<(), HarmonyError> {
+        let source: String = match std::fs::read_to_string(file) {
+            Ok(source) => source,
+            Err(error) => {
+                println!("Could not read {}: {}", file, error);
+                return Ok(());
+            }
+        };
+        let mut tokenizer: Tokenizer = Tokenizer::new(&file.to_string(), &source);
+        let tokens: Vec<Token> = tokenizer.tokenize();
+        if let Some(error) = tokenizer.errors.first() {
+            return Err(error.clone());
+        }
+        let statements: Vec<Statement> = Parser::new(tokens)
+            .parse()?
+            .into_iter()
+            .filter(|statement| {
+                !matches!(statement, Statement::Module { .. })
+                    && declared_name(statement).as_deref() != Some("main")
+            })
+            .collect();
+        let previous: Option<(String, Vec<Statement>)> = self.loaded.clone();
+        self.loaded = Some((file.to_string(), statements));
+        match self.check(&self.entered.clone(), None) {
+            Ok(checker) => {
+                self.set_checker(checker);
+                println!("Loaded {}", file);
+                Ok(())
+            }
+            Err(error) => {
+                self.loaded = previous;
+                Err(error)
+            }
+        }
+    }
+
+    fn browse(&self, name: &str) {
+        let documentation: Documentation = Documentation::new(&self.compiler);
+        let Some(module) = documentation
+            .modules
+            .iter()
+            .find(|module| module.name == name)
+        else {
+            println!("Module {} is not imported", name);
+            return;
+        };
+        for enum_ in &module.enums {
+            println!("{}", documentation.enum_signature(enum_, false));
+        }
+        for function in &module.functions {
+            println!("{}", documentation.function_signature(function, false));
+        }
+    }
+
+    /// Checks the `Repl` module made of the loaded module's declarations, then
+    /// `entered` and `main`.
+    fn check(
+        &mut self,
+        entered: &[Statement],
+        main: Option<Statement>,
+    ) -> Result<Checker, HarmonyError> {
+        let names: Vec<Option<String>> = entered.iter().map(declared_name).collect();
+        let mut statements: Vec<Statement> = vec![module()];
+        if let Some((_, loaded)) = &self.loaded {
+            statements.extend(
+                loaded
+                    .iter()
+                    .filter(|statement| {
+                        let name: Option<String> = declared_name(statement);
+                        name.is_none() || !names.contains(&name)
+                    })
+                    .cloned(),
+            );
+        }
+        statements.extend(entered.iter().cloned());
+        statements.extend(main);
+        let result: Result<Checker, HarmonyError> =
+            self.compiler.check_module(&FILE.to_string(), &statements);
+        // Until the caller keeps the new scope, `:browse Repl` shows the old one.
+        self.compiler
+            .compiled_files
+            .insert(FILE.to_string(), self.checker.global_scope.clone());
+        result
+    }
+
+    fn set_checker(&mut self, checker: Checker) {
+        self.compiler
+            .compiled_files
+            .insert(FILE.to_string(), checker.global_scope.clone());
+        self.checker = checker;
+    }
+
+    fn parse_expression(&self, input: &str) -> Result<Expression, HarmonyError> {
+        let tokens: Vec<Token> = self.tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Syntax,
+                "Expected an expression".to_string(),
+                None,
+                SourceLocation {
+                    file: FILE.to_string(),
+                    start: 0,
+                    end: 0,
+                    line: self.line,
+                    column: 0,
+                },
+            ));
+        }
+        Parser::new(tokens).parse_expression_only()
+    }
+
+    fn tokenize(&self, input: &str) -> Result<Vec<Token>, HarmonyError> {
+        let mut tokenizer: Tokenizer = Tokenizer::new(&FILE.to_string(), &input.to_string());
+        tokenizer.line = self.line;
+        let tokens: Vec<Token> = tokenizer.tokenize();
+        if let Some(error) = tokenizer.errors.first() {
+            return Err(error.clone());
+        }
+        if let Some(token) = tokens.iter().find(|token| token.kind == TokenKind::Unknown) {
+            return Err(HarmonyError::new(
+                HarmonyErrorKind::Syntax,
+                format!("Unknown token: {}", token.lexeme),
+                None,
+                token.location.clone(),
+            ));
+        }
+        Ok(tokens
+            .into_iter()
+            .filter(|token| !matches!(token.kind, TokenKind::Comment | TokenKind::DocComment))
+            .collect())
+    }
+
+    /// Removes the JavaScript generated for the session.
+    fn clean_up(&self) {
+        if !self.compiler.options.keep {
+            let _ = std::fs::remove_file(self.file().replace(".harm", ".mjs"));
+            self.compiler.clean();
+        }
+    }
+}
+
+fn module() -> Statement {
+    Statement::Module {
+        name: vec![(
+            "Repl".to_string(),
+            SourceLocation {
+                file: FILE.to_string(),
+                start: 0,
+                end: 0,
+                line: 0,
+                column: 0,
+            },
+        )],
+        exposing: vec![],
+    }
+}
+
+/// The name a statement declares, which a later declaration replaces.
+fn declared_name(statement: &Statement) -> Option<String> {
+    match statement {
+        Statement::Import { name, .. } => Some(
+            name.iter()
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<String>>()
+                .join("."),
+        ),
+        Statement::ForeignFunction { name, .. }
+        | Statement::Function { name, .. }
+        | Statement::GenericFunction { name, .. }
+        | Statement::Enum { name, .. }
+        | Statement::GenericEnum { name, .. }
+        | Statement::Class { name, .. }
+        | Statement::TypeAlias { name, .. }
+        | Statement::Newtype { name, .. } => Some(name.0.clone()),
+        Statement::Module { .. }
+        | Statement::ForeignImport { .. }
+        | Statement::Instance { .. }
+        | Statement::Test { .. } => None,
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Finds and reads the modules a program imports, so the compiler itself
+/// never looks at the filesystem.
+pub trait ModuleResolver {
+    /// Finds the module named `name`, like `Data.List`, imported by the
+    /// module at `importer`, and returns the path that identifies it.
+    fn resolve(&self, name: &str, importer: &str) -> Option<String>;
+
+    /// Reads the source of the module at `path`.
+    fn read(&self, path: &str) -> std::io::Result<String>;
+}
+
+/// Where a `FileResolver` reads sources from.
+pub trait FileSystem {
+    fn exists(&self, path: &Path) -> bool;
+
+    fn read(&self, path: &Path) -> std::io::Result<String>;
+}
+
+/// The files on disk.
+#[derive(Debug, Clone)]
+pub struct DiskFileSystem;
+
+impl FileSystem for DiskFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+}
+
+/// Files held in memory, like the language server's unsaved documents, in
+/// front of an optional `base` file system that has every other file.
+#[derive(Default)]
+pub struct MemoryFileSystem {
+    pub files: HashMap<PathBuf, String>,
+    pub base: Option<Box<dyn FileSystem>>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> MemoryFileSystem {
+        MemoryFileSystem::default()
+    }
+
+    /// Looks up the files that aren't in memory in `base`.
+    pub fn over(base: impl FileSystem + 'static) -> MemoryFileSystem {
+        MemoryFileSystem {
+            files: HashMap::new(),
+            base: Some(Box::new(base)),
+        }
+    }
+
+    pub fn insert(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        self.files.insert(path.into(), source.into());
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        self.files.contains_key(path) || self.base.as_ref().is_some_and(|base| base.exists(path))
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        if let Some(source) = self.files.get(path) {
+            return Ok(source.clone());
+        }
+        match &self.base {
+            Some(base) => base.read(path),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not in memory", path.display()),
+            )),
+        }
+    }
+}
+
+/// Resolves imports the way the `harmony` command does: first among the
+/// runtime modules in `root/runtime`, then next to the importing file.
+/// Relative paths are relative to `root`.
+pub struct FileResolver {
+    pub root: PathBuf,
+    pub file_system: Box<dyn FileSystem>,
+}
+
+impl FileResolver {
+    pub fn new(root: &Path, file_system: impl FileSystem + 'static) -> FileResolver {
+        FileResolver {
+            root: root.to_path_buf(),
+            file_system: Box::new(file_system),
+        }
+    }
+
+    /// Resolves modules among the files on disk.
+    pub fn on_disk(root: &Path) -> FileResolver {
+        FileResolver::new(root, DiskFileSystem)
+    }
+}
+
+impl ModuleResolver for FileResolver {
+    fn resolve(&self, name: &str, importer: &str) -> Option<String> {
+        let mut path: Vec<&str> = name.split('.').collect();
+        let module: String = format!("{}.harm", path.pop().unwrap());
+
+        let mut runtime: PathBuf = self.root.join("runtime");
+        for directory in path {
+            runtime.push(directory);
+        }
+        runtime.push(&module);
+        if self.file_system.exists(&runtime) {
+            return Some(runtime.to_str().unwrap().replace('\\', "/"));
+        }
+
+        let sibling: PathBuf = self.root.join(importer).parent()?.join(&module);
+        if self.file_system.exists(&sibling) {
+            return Some(sibling.to_str().unwrap().replace('\\', "/"));
+        }
+        None
+    }
+
+    fn read(&self, path: &str) -> std::io::Result<String> {
+        self.file_system.read(&self.root.join(path))
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    compiler::{Compiler, CompilerOptions},
+    log::Level,
+    token::{Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+/// Runs the `__tests` of the modules in `modules`, which the runner declares
+/// above it. Takes the filter as its only argument, prints a line per test
+/// and then the failures, and exits with 1 if any test failed.
+const RUNNER: &str = r#"import { inspect } from "node:util";
+
+const filter = process.argv[2] ?? "";
+const show = (value) => inspect(value, { depth: null });
+let passed = 0;
+let filtered = 0;
+const failures = [];
+for (const [module, tests] of modules) {
+    for (const test of tests) {
+        const name = `${module}: ${test.name}`;
+        if (!name.includes(filter)) {
+            filtered++;
+            continue;
+        }
+        let result;
+        try {
+            result = test.run();
+        } catch (error) {
+            result = { passed: false, error };
+        }
+        if (result.passed) {
+            passed++;
+            console.log(`test ${name} ... ok`);
+        } else {
+            failures.push([name, test.location, result]);
+            console.log(`test ${name} ... FAILED`);
+        }
+    }
+}
+if (failures.length > 0) {
+    console.log("\nfailures:");
+    for (const [name, location, result] of failures) {
+        console.log(`\n---- ${name} at ${location} ----`);
+        if ("error" in result) {
+            console.log(`  threw: ${result.error instanceof Error ? result.error.message : show(result.error)}`);
+        } else if ("left" in result) {
+            console.log(`   left: ${show(result.left)}`);
+            console.log(`  right: ${show(result.right)}`);
+        } else {
+            console.log("  expected true, found false");
+        }
+    }
+}
+console.log(
+    `\ntest result: ${failures.length > 0 ? "FAILED" : "ok"}. ${passed} passed; ${failures.length} failed; ${filtered} filtered out`
+);
+process.exitCode = failures.length > 0 ? 1 : 0;
+"#;
+
+/// Runs the `test` declarations of Harmony modules, for `harmony test`.
+///
+/// Directories are searched for files declaring tests, which are compiled
+/// with their tests instead of `main`, and run together by a generated
+/// JavaScript runner.
+pub struct TestRunner {
+    /// Files and directories to look for tests in.
+    pub paths: Vec<String>,
+    /// Only tests whose `Module: name` contains this are run.
+    pub filter: String,
+}
+
+impl TestRunner {
+    pub fn new(paths: Vec<String>, filter: String) -> TestRunner {
+        TestRunner { paths, filter }
+    }
+
+    /// Compiles and runs the tests, returning whether they all passed. Any
+    /// compile error fails the run before a test is run.
+    pub fn run(&self) -> bool {
+        let mut files: Vec<String> = vec![];
+        for path in &self.paths {
+            let path: &Path = Path::new(path);
+            if path.is_dir() {
+                discover(path, &mut files);
+            } else {
+                files.push(path.to_str().unwrap().to_string());
+            }
+        }
+        if files.is_empty() {
+            println!("No tests found");
+            return true;
+        }
+
+        let mut options: CompilerOptions = CompilerOptions::new(vec![]);
+        options.level = Level::Silent;
+        options.test = true;
+        let mut compiler: Compiler = Compiler::new(&options, &files, vec![]);
+        compiler.build();
+        if !compiler.errors.is_empty() {
+            for error in &compiler.errors {
+                println!("{}", error.to_string());
+            }
+            println!("Could not compile the tests");
+            compiler.clean();
+            return false;
+        }
+
+        let mut code: String = String::new();
+        let mut modules: Vec<String> = vec![];
+        for (i, file) in files.iter().enumerate() {
+            let path: PathBuf = std::env::current_dir()
+                .unwrap()
+                .join(file.replace(".harm", ".mjs"));
+            code.push_str(&format!(
+                "import {{ __tests as tests{} }} from \"file:///{}\";\n",
+                i,
+                path.to_str().unwrap().replace('\\', "/")
+            ));
+            let module: &String = &compiler.compiled_files[file].module.as_ref().unwrap().0;
+            modules.push(format!("[\"{}\", tests{}]", module, i));
+        }
+        code.push_str(&format!("const modules = [{}];\n", modules.join(", ")));
+        code.push_str(RUNNER);
+
+        let runner: PathBuf =
+            std::env::temp_dir().join(format!("harmony-test-{}.mjs", std::process::id()));
+        std::fs::write(&runner, code).unwrap();
+        let status = Command::new("node")
+            .arg("--harmony")
+            .arg("--use-strict")
+            .arg(&runner)
+            .arg(&self.filter)
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status();
+        let _ = std::fs::remove_file(&runner);
+        compiler.clean();
+        match status {
+            Ok(status) => status.success(),
+            Err(error) => {
+                println!("Could not run node: {}", error);
+                false
+            }
+        }
+    }
+}
+
+/// Adds the files under `directory` that declare tests to `files`, skipping
+/// hidden directories and `target`.
+fn discover(directory: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        let name: &str = path.file_name().unwrap().to_str().unwrap();
+        if path.is_dir() {
+            if !name.starts_with('.') && name != "target" {
+                discover(&path, files);
+            }
+            continue;
+        }
+        if !name.ends_with(".harm") {
+            continue;
+        }
+        let Ok(source) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let file: String = path
+            .strip_prefix(".")
+            .unwrap_or(&path)
+            .to_str()
+            .unwrap()
+            .to_string();
+        let tokens: Vec<Token> = Tokenizer::new(&file, &source).tokenize();
+        if tokens.iter().any(|token| token.kind == TokenKind::Test) {
+            files.push(file);
+        }
+    }
+}
@@ -1,22 +1,40 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, PartialEq)]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Where something is in a file: the byte offsets of its first byte and of
+/// the byte after it, and the line and column it starts at, counting from 1
+/// with columns in bytes, as the file's `LineIndex` converts them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct SourceLocation {
     pub file: String,
+    pub start: usize,
+    pub end: usize,
     pub line: usize,
     pub column: usize,
-    pub length: usize,
 }
 
 impl SourceLocation {
+    /// The location covering both `self` and `other`, which starts where the
+    /// first of them does. Locations in different files aren't merged.
     pub fn merge(&self, other: &SourceLocation) -> SourceLocation {
-        let mut location = self.clone();
-        let length: i64 = (self.line + self.column + self.length) as i64
-            - (other.line + other.column + other.length) as i64;
-        if length > 0 {
-            location.length = length as usize;
+        if self.file != other.file {
+            return self.clone();
+        }
+        let first: &SourceLocation = if other.start < self.start {
+            other
+        } else {
+            self
+        };
+        SourceLocation {
+            file: self.file.clone(),
+            start: first.start,
+            end: self.end.max(other.end),
+            line: first.line,
+            column: first.column,
         }
-        location
     }
 
     pub fn to_string(&self) -> String {
@@ -31,13 +49,108 @@ impl SourceLocation {
     pub fn default() -> SourceLocation {
         SourceLocation {
             file: String::new(),
+            start: 0,
+            end: 0,
             line: 0,
             column: 0,
-            length: 0,
+        }
+    }
+
+    /// `value` with every serialized location in it replaced by `null`, to
+    /// compare what was serialized regardless of where it was written.
+    pub fn remove_from(value: Value) -> Value {
+        match value {
+            Value::Object(_) if SourceLocation::deserialize(&value).is_ok() => Value::Null,
+            Value::Object(object) => object
+                .into_iter()
+                .map(|(key, value)| (key, SourceLocation::remove_from(value)))
+                .collect(),
+            Value::Array(values) => values
+                .into_iter()
+                .map(SourceLocation::remove_from)
+                .collect(),
+            value => value,
         }
     }
 }
 
+/// Where the lines of a source start, to turn the byte offsets of its
+/// locations into lines and columns: in bytes for messages, and in UTF-16
+/// code units for the language server.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    source: String,
+    /// The offset of the first byte of every line.
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(source: &str) -> LineIndex {
+        let mut starts: Vec<usize> = vec![0];
+        starts.extend(source.match_indices('\n').map(|(offset, _)| offset + 1));
+        LineIndex {
+            source: source.to_string(),
+            starts,
+        }
+    }
+
+    /// The line and column of `offset`, counting from 1 with the column in
+    /// bytes.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let line: usize = self.line(offset);
+        (
+            line + 1,
+            offset.min(self.source.len()) - self.starts[line] + 1,
+        )
+    }
+
+    /// The line and character of `offset` the way the language server
+    /// protocol counts them: from 0, with the character in UTF-16 code units.
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let line: usize = self.line(offset);
+        let offset: usize = self.boundary(offset.min(self.source.len()));
+        let character: usize = self.source[self.starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        (line, character)
+    }
+
+    /// The offset of a line and UTF-16 character from the language server.
+    /// Characters past the end of the line stop at its end, and lines past
+    /// the end of the source at the end of the source.
+    pub fn utf16_offset(&self, line: usize, character: usize) -> usize {
+        let Some(start) = self.starts.get(line).copied() else {
+            return self.source.len();
+        };
+        let end: usize = self
+            .starts
+            .get(line + 1)
+            .map_or(self.source.len(), |next| next - 1);
+        let mut units: usize = 0;
+        for (index, c) in self.source[start..end].char_indices() {
+            if units >= character {
+                return start + index;
+            }
+            units += c.len_utf16();
+        }
+        end
+    }
+
+    /// The line, from 0, that `offset` is on.
+    fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|start| *start <= offset) - 1
+    }
+
+    /// `offset`, moved back to the start of the character it's in.
+    fn boundary(&self, mut offset: usize) -> usize {
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub kind: TokenKind,
@@ -45,15 +158,25 @@ pub struct Token {
     pub location: SourceLocation,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TokenKind {
     // Literals
     Identifier,
     IntegerLiteral,
     FloatLiteral,
     StringLiteral,
+    // Strings emitted as template literals: complete ones, and the pieces of
+    // interpolated ones around each `${...}`.
+    TemplateString,
+    TemplateHead,
+    TemplateMiddle,
+    TemplateTail,
     CharacterLiteral,
     BooleanLiteral,
+    // A `---` line, attached by the parser to the declaration that follows it.
+    DocComment,
+    // `--` and `{- -}` comments, which only the formatter looks at.
+    Comment,
 
     // Keywords
     Module,
@@ -71,6 +194,13 @@ pub enum TokenKind {
     Else,
     Let,
     In,
+    Do,
+    Class,
+    Instance,
+    Where,
+    Type,
+    Newtype,
+    Test,
 
     // Types
     Int,
@@ -90,10 +220,12 @@ pub enum TokenKind {
     DoubleDot,        // ..   (range)
     Comma,            // ,
     Colon,            // :
+    Semicolon,        // ;    (between do statements)
     DoubleColon,      // ::   (list constructor (cons))
     Pipe,             // |
     Dollar,           // $
     Arrow,            // ->
+    LeftArrow,        // <-   (do binding)
     FatArrow,         // =>
 
     // Operators
@@ -105,7 +237,7 @@ pub enum TokenKind {
     Percent,           // %     (modulo)
     Equals,            // =     (assignment)
     DoubleEquals,      // ==    (equality)
-    NotEquals,         // /=    (inequality)
+    NotEquals,         // !=    (inequality)
     LessThan,          // <     (less than)
     LessThanEquals,    // <=    (less than or equal to)
     GreaterThan,       // >     (greater than)
@@ -113,6 +245,10 @@ pub enum TokenKind {
     And,               // &&    (and)
     Or,                // ||    (or)
     Not,               // !     (not)
+    PipeForward,       // |>    (pipe forward)
+    PipeBackward,      // <|    (pipe backward)
+    ComposeForward,    // >>    (forward composition)
+    ComposeBackward,   // <<    (backward composition)
 
     // Special
     Newline,
@@ -121,6 +257,17 @@ pub enum TokenKind {
     Unknown,
 }
 
+/// A token as `line:column Kind "lexeme"`, for `--dump=tokens`.
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{} {:?} {:?}",
+            self.location.line, self.location.column, self.kind, self.lexeme
+        )
+    }
+}
+
 impl Display for TokenKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -128,7 +275,13 @@ impl Display for TokenKind {
             TokenKind::IntegerLiteral => write!(f, "<integer literal>"),
             TokenKind::FloatLiteral => write!(f, "<float literal>"),
             TokenKind::StringLiteral => write!(f, "<string literal>"),
+            TokenKind::TemplateString => write!(f, "<string literal>"),
+            TokenKind::TemplateHead => write!(f, "<string literal>"),
+            TokenKind::TemplateMiddle => write!(f, "<string literal>"),
+            TokenKind::TemplateTail => write!(f, "<string literal>"),
             TokenKind::CharacterLiteral => write!(f, "<character literal>"),
+            TokenKind::DocComment => write!(f, "<doc comment>"),
+            TokenKind::Comment => write!(f, "<comment>"),
             TokenKind::BooleanLiteral => write!(f, "<boolean literal>"),
             TokenKind::Module => write!(f, "module"),
             TokenKind::Import => write!(f, "import"),
@@ -145,6 +298,13 @@ impl Display for TokenKind {
             TokenKind::Else => write!(f, "else"),
             TokenKind::Let => write!(f, "let"),
             TokenKind::In => write!(f, "in"),
+            TokenKind::Do => write!(f, "do"),
+            TokenKind::Class => write!(f, "class"),
+            TokenKind::Instance => write!(f, "instance"),
+            TokenKind::Where => write!(f, "where"),
+            TokenKind::Type => write!(f, "type"),
+            TokenKind::Newtype => write!(f, "newtype"),
+            TokenKind::Test => write!(f, "test"),
             TokenKind::Int => write!(f, "int"),
             TokenKind::Float => write!(f, "float"),
             TokenKind::String => write!(f, "string"),
@@ -159,11 +319,13 @@ impl Display for TokenKind {
             TokenKind::Dot => write!(f, "."),
             TokenKind::DoubleDot => write!(f, ".."),
             TokenKind::Comma => write!(f, ","),
+            TokenKind::Semicolon => write!(f, ";"),
             TokenKind::Colon => write!(f, ":"),
             TokenKind::DoubleColon => write!(f, "::"),
             TokenKind::Pipe => write!(f, "|"),
             TokenKind::Dollar => write!(f, "$"),
             TokenKind::Arrow => write!(f, "->"),
+            TokenKind::LeftArrow => write!(f, "<-"),
             TokenKind::FatArrow => write!(f, "=>"),
             TokenKind::Plus => write!(f, "+"),
             TokenKind::PlusPlus => write!(f, "++"),
@@ -173,7 +335,7 @@ impl Display for TokenKind {
             TokenKind::Percent => write!(f, "%"),
             TokenKind::Equals => write!(f, "="),
             TokenKind::DoubleEquals => write!(f, "=="),
-            TokenKind::NotEquals => write!(f, "/="),
+            TokenKind::NotEquals => write!(f, "!="),
             TokenKind::LessThan => write!(f, "<"),
             TokenKind::LessThanEquals => write!(f, "<="),
             TokenKind::GreaterThan => write!(f, ">"),
@@ -181,6 +343,10 @@ impl Display for TokenKind {
             TokenKind::And => write!(f, "&&"),
             TokenKind::Or => write!(f, "||"),
             TokenKind::Not => write!(f, "!"),
+            TokenKind::PipeForward => write!(f, "|>"),
+            TokenKind::PipeBackward => write!(f, "<|"),
+            TokenKind::ComposeForward => write!(f, ">>"),
+            TokenKind::ComposeBackward => write!(f, "<<"),
             TokenKind::Newline => write!(f, "<newline>"),
             TokenKind::Whitespace => write!(f, "<whitespace>"),
             TokenKind::EndOfFile => write!(f, "<end of file>"),
@@ -205,7 +371,11 @@ impl TokenKind {
             | TokenKind::GreaterThan
             | TokenKind::GreaterThanEquals
             | TokenKind::And
-            | TokenKind::Or => true,
+            | TokenKind::Or
+            | TokenKind::PipeForward
+            | TokenKind::PipeBackward
+            | TokenKind::ComposeForward
+            | TokenKind::ComposeBackward => true,
             _ => false,
         }
     }
@@ -217,18 +387,24 @@ impl TokenKind {
         }
     }
 
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, TokenKind::PipeBackward)
+    }
+
     pub fn precedence(&self) -> u8 {
         match self {
-            TokenKind::Or => 1,
-            TokenKind::And => 2,
-            TokenKind::DoubleEquals | TokenKind::NotEquals => 3,
+            TokenKind::PipeForward | TokenKind::PipeBackward => 1,
+            TokenKind::Or => 2,
+            TokenKind::And => 3,
+            TokenKind::DoubleEquals | TokenKind::NotEquals => 4,
             TokenKind::LessThan
             | TokenKind::LessThanEquals
             | TokenKind::GreaterThan
-            | TokenKind::GreaterThanEquals => 4,
-            TokenKind::Plus | TokenKind::Minus => 5,
-            TokenKind::Asterisk | TokenKind::Slash | TokenKind::Percent => 6,
-            TokenKind::PlusPlus => 7,
+            | TokenKind::GreaterThanEquals => 5,
+            TokenKind::Plus | TokenKind::Minus => 6,
+            TokenKind::Asterisk | TokenKind::Slash | TokenKind::Percent => 7,
+            TokenKind::PlusPlus => 8,
+            TokenKind::ComposeForward | TokenKind::ComposeBackward => 9,
             _ => 0,
         }
     }
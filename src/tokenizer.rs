@@ -1,12 +1,34 @@
-use crate::token::{SourceLocation, Token, TokenKind};
+use crate::{
+    error::{HarmonyError, HarmonyErrorKind},
+    token::{LineIndex, SourceLocation, Token, TokenKind},
+};
 
 pub struct Tokenizer {
     pub source: String,
     pub tokens: Vec<Token>,
+    pub errors: Vec<HarmonyError>,
     filename: String,
+    /// Byte offset of the next character.
     index: usize,
-    line: usize,
-    column: usize,
+    /// Byte offset of the first character of the token being scanned.
+    start: usize,
+    /// The line the source starts at. Starts at 1, and can be moved on by
+    /// callers tokenizing source that continues earlier input, like the REPL
+    /// does.
+    pub line: usize,
+    lines: LineIndex,
+    strings: Vec<StringLiteral>,
+}
+
+/// A string literal being scanned. Interpolated strings stay on the stack
+/// while the tokens of their `${...}` expressions are produced.
+struct StringLiteral {
+    triple: bool,
+    raw: bool,
+    /// Byte offset of the opening quote.
+    start: usize,
+    /// Indices of the template tokens emitted for this literal so far.
+    pieces: Vec<usize>,
 }
 
 impl Tokenizer {
@@ -14,20 +36,22 @@ impl Tokenizer {
         Tokenizer {
             source: source.clone(),
             tokens: vec![],
+            errors: vec![],
             filename: filename.clone(),
             index: 0,
+            start: 0,
             line: 1,
-            column: 1,
+            lines: LineIndex::new(source),
+            strings: vec![],
         }
     }
 
     pub fn tokenize(&mut self) -> Vec<Token> {
         while !self.is_at_end() {
+            self.start = self.index;
             let c: char = self.next().unwrap();
             match c {
-                ' ' | '\r' | '\t' => {
-                    // self.column += 1;
-                }
+                ' ' | '\r' | '\t' => {}
                 '\n' => {
                     // self.add_token(TokenKind::Newline, c.to_string());
                     // let mut spaces: usize = 0;
@@ -53,6 +77,7 @@ impl Tokenizer {
                     }
                 }
                 ',' => self.add_token(TokenKind::Comma, c.to_string()),
+                ';' => self.add_token(TokenKind::Semicolon, c.to_string()),
                 ':' => {
                     if self.peek() == Some(':') {
                         self.next();
@@ -65,6 +90,9 @@ impl Tokenizer {
                     if self.peek() == Some('|') {
                         self.next();
                         self.add_token(TokenKind::Or, "||".to_string());
+                    } else if self.peek() == Some('>') {
+                        self.next();
+                        self.add_token(TokenKind::PipeForward, "|>".to_string());
                     } else {
                         self.add_token(TokenKind::Pipe, c.to_string());
                     }
@@ -91,8 +119,16 @@ impl Tokenizer {
                         self.add_token(TokenKind::Arrow, "->".to_string());
                     } else if self.peek() == Some('-') {
                         self.next();
+                        let mut comment: String = "--".to_string();
                         while self.peek() != Some('\n') && !self.is_at_end() {
-                            self.next();
+                            comment.push(self.next().unwrap());
+                        }
+                        // `---` starts a doc comment, longer runs of dashes are
+                        // just separators.
+                        if comment.starts_with("---") && !comment.starts_with("----") {
+                            self.add_token(TokenKind::DocComment, comment.trim_end().to_string());
+                        } else {
+                            self.add_token(TokenKind::Comment, comment.trim_end().to_string());
                         }
                     } else {
                         self.add_token(TokenKind::Minus, c.to_string());
@@ -116,6 +152,15 @@ impl Tokenizer {
                     if self.peek() == Some('=') {
                         self.next();
                         self.add_token(TokenKind::LessThanEquals, "<=".to_string());
+                    } else if self.peek() == Some('|') {
+                        self.next();
+                        self.add_token(TokenKind::PipeBackward, "<|".to_string());
+                    } else if self.peek() == Some('<') {
+                        self.next();
+                        self.add_token(TokenKind::ComposeBackward, "<<".to_string());
+                    } else if self.peek() == Some('-') {
+                        self.next();
+                        self.add_token(TokenKind::LeftArrow, "<-".to_string());
                     } else {
                         self.add_token(TokenKind::LessThan, c.to_string());
                     }
@@ -124,6 +169,9 @@ impl Tokenizer {
                     if self.peek() == Some('=') {
                         self.next();
                         self.add_token(TokenKind::GreaterThanEquals, ">=".to_string());
+                    } else if self.peek() == Some('>') {
+                        self.next();
+                        self.add_token(TokenKind::ComposeForward, ">>".to_string());
                     } else {
                         self.add_token(TokenKind::GreaterThan, c.to_string());
                     }
@@ -137,116 +185,35 @@ impl Tokenizer {
                     }
                 }
                 '"' => {
-                    let mut string = String::new();
-                    while self.peek() != Some('"') && !self.is_at_end() {
-                        if self.peek() == Some('\\') {
-                            self.next();
-                            match self.peek() {
-                                Some('n') => {
-                                    self.next();
-                                    string.push_str("\\n");
-                                }
-                                Some('t') => {
-                                    self.next();
-                                    string.push_str("\\t");
-                                }
-                                Some('r') => {
-                                    self.next();
-                                    string.push_str("\\r");
-                                }
-                                Some('\\') => {
-                                    self.next();
-                                    string.push_str("\\\\");
-                                }
-                                Some('"') => {
-                                    self.next();
-                                    string.push_str("\"");
-                                }
-                                Some('\'') => {
-                                    self.next();
-                                    string.push_str("'");
-                                }
-                                Some('0') => {
-                                    self.next();
-                                    string.push_str("\\0");
-                                }
-                                _ => {
-                                    panic!("Invalid escape sequence");
-                                }
-                            }
-                        } else {
-                            string.push(self.next().unwrap());
-                        }
-                    }
-                    if self.is_at_end() {
-                        panic!("Unterminated string");
+                    let triple: bool = self.peek() == Some('"') && self.peek_next() == Some('"');
+                    if triple {
+                        self.next();
+                        self.next();
                     }
-                    self.next();
-                    self.add_token(TokenKind::StringLiteral, string);
+                    self.strings.push(StringLiteral {
+                        triple,
+                        raw: false,
+                        start: self.start,
+                        pieces: vec![],
+                    });
+                    self.scan_string();
                 }
-                '\'' => {
-                    let mut character = String::new();
-                    while self.peek() != Some('\'') && !self.is_at_end() {
-                        if self.peek() == Some('\\') {
-                            self.next();
-                            match self.peek() {
-                                Some('n') => {
-                                    self.next();
-                                    character.push_str("\\n");
-                                }
-                                Some('t') => {
-                                    self.next();
-                                    character.push_str("\\t");
-                                }
-                                Some('r') => {
-                                    self.next();
-                                    character.push_str("\\r");
-                                }
-                                Some('\\') => {
-                                    self.next();
-                                    character.push_str("\\\\");
-                                }
-                                Some('"') => {
-                                    self.next();
-                                    character.push_str("\"");
-                                }
-                                Some('\'') => {
-                                    self.next();
-                                    character.push_str("'");
-                                }
-                                Some('0') => {
-                                    self.next();
-                                    character.push_str("\\0");
-                                }
-                                _ => {
-                                    panic!("Invalid escape sequence");
-                                }
-                            }
-                        } else {
-                            character.push(self.next().unwrap());
-                        }
-                    }
-                    if self.is_at_end() {
-                        panic!("Unterminated character");
-                    }
+                'r' if self.peek() == Some('"') => {
                     self.next();
-                    self.add_token(TokenKind::CharacterLiteral, character);
+                    self.strings.push(StringLiteral {
+                        triple: false,
+                        raw: true,
+                        start: self.start,
+                        pieces: vec![],
+                    });
+                    self.scan_string();
                 }
+                '}' if !self.strings.is_empty() => self.scan_string(),
+                '{' if self.peek() == Some('-') => self.scan_block_comment(),
+                '\'' => self.scan_character(),
                 _ => {
                     if c.is_digit(10) {
-                        let mut number = c.to_string();
-                        while self.peek().is_some() && self.peek().unwrap().is_digit(10) {
-                            number.push(self.next().unwrap());
-                        }
-                        if self.peek() == Some('.') {
-                            number.push(self.next().unwrap());
-                            while self.peek().is_some() && self.peek().unwrap().is_digit(10) {
-                                number.push(self.next().unwrap());
-                            }
-                            self.add_token(TokenKind::FloatLiteral, number);
-                        } else {
-                            self.add_token(TokenKind::IntegerLiteral, number);
-                        }
+                        self.scan_number(c);
                     } else if c.is_alphabetic() || c == '_' {
                         let mut identifier = c.to_string();
                         while self.peek().is_some()
@@ -271,6 +238,13 @@ impl Tokenizer {
                             "else" => self.add_token(TokenKind::Else, identifier),
                             "let" => self.add_token(TokenKind::Let, identifier),
                             "in" => self.add_token(TokenKind::In, identifier),
+                            "do" => self.add_token(TokenKind::Do, identifier),
+                            "class" => self.add_token(TokenKind::Class, identifier),
+                            "instance" => self.add_token(TokenKind::Instance, identifier),
+                            "where" => self.add_token(TokenKind::Where, identifier),
+                            "type" => self.add_token(TokenKind::Type, identifier),
+                            "newtype" => self.add_token(TokenKind::Newtype, identifier),
+                            "test" => self.add_token(TokenKind::Test, identifier),
                             "int" => self.add_token(TokenKind::Int, identifier),
                             "float" => self.add_token(TokenKind::Float, identifier),
                             "string" => self.add_token(TokenKind::String, identifier),
@@ -288,23 +262,255 @@ impl Tokenizer {
                 }
             }
         }
+        while let Some(literal) = self.strings.pop() {
+            self.error(
+                "Unterminated string".to_string(),
+                Some("Close it with '\"'".to_string()),
+                self.location(literal.start, literal.start + 1),
+            );
+        }
         self.tokens.clone()
     }
 
-    fn next(&mut self) -> Option<char> {
-        let c = self.source.chars().nth(self.index);
-        self.index += 1;
-        if c == Some('\n') {
-            self.line += 1;
-            self.column = 1;
+    /// Scans a numeric literal starting with `first`: a decimal, `0x`, `0o` or
+    /// `0b` integer, or a float with a fraction and/or exponent. Digits may be
+    /// separated by `_`. Malformed literals are still emitted as tokens, with
+    /// an error recorded for them.
+    fn scan_number(&mut self, first: char) {
+        let mut number: String = first.to_string();
+        let mut kind: TokenKind = TokenKind::IntegerLiteral;
+        if first == '0' && matches!(self.peek(), Some('x' | 'X' | 'o' | 'O' | 'b' | 'B')) {
+            number.push(self.next().unwrap());
+            while matches!(self.peek(), Some(c) if c.is_alphanumeric() || c == '_') {
+                number.push(self.next().unwrap());
+            }
+        } else {
+            self.scan_digits(&mut number);
+            if self.peek() == Some('.') && matches!(self.peek_next(), Some(c) if c.is_ascii_digit())
+            {
+                kind = TokenKind::FloatLiteral;
+                number.push(self.next().unwrap());
+                self.scan_digits(&mut number);
+            }
+            if matches!(self.peek(), Some('e' | 'E')) {
+                kind = TokenKind::FloatLiteral;
+                number.push(self.next().unwrap());
+                if matches!(self.peek(), Some('+' | '-')) {
+                    number.push(self.next().unwrap());
+                }
+                self.scan_digits(&mut number);
+            }
+        }
+
+        let result: Result<(), String> = match kind {
+            TokenKind::IntegerLiteral => parse_integer_literal(&number).map(|_| ()),
+            _ => parse_float_literal(&number).map(|_| ()),
+        };
+        self.add_token(kind, number);
+        if let Err(message) = result {
+            self.error(message, None, self.tokens.last().unwrap().location.clone());
+        }
+    }
+
+    /// Scans a character literal after its opening quote. A literal that is
+    /// empty, holds more than one character or isn't closed on its line is
+    /// still emitted, with an error recorded for it.
+    fn scan_character(&mut self) {
+        let mut character = String::new();
+        let mut length: usize = 0;
+        while !matches!(self.peek(), Some('\'' | '\n') | None) {
+            length += 1;
+            let c: char = self.next().unwrap();
+            if c != '\\' {
+                character.push(c);
+                continue;
+            }
+            let escape: usize = self.index - 1;
+            match self.next() {
+                Some('n') => character.push_str("\\n"),
+                Some('t') => character.push_str("\\t"),
+                Some('r') => character.push_str("\\r"),
+                Some('\\') => character.push_str("\\\\"),
+                Some('"') => character.push_str("\""),
+                Some('\'') => character.push_str("'"),
+                Some('0') => character.push_str("\\0"),
+                _ => self.invalid_escape(escape),
+            }
+        }
+        if self.peek() != Some('\'') {
+            self.error(
+                "Unterminated character literal".to_string(),
+                Some("Close it with \"'\"".to_string()),
+                self.location(self.start, self.start + 1),
+            );
+        } else {
+            self.next();
+            if length != 1 {
+                self.error(
+                    "Character literal must contain exactly one character".to_string(),
+                    Some("Use a string literal for text".to_string()),
+                    self.location(self.start, self.index),
+                );
+            }
+        }
+        self.add_token(TokenKind::CharacterLiteral, character);
+    }
+
+    fn invalid_escape(&mut self, start: usize) {
+        let sequence: String = self.source[start..].chars().take(2).collect();
+        self.error(
+            format!("Invalid escape sequence '{}'", sequence),
+            None,
+            self.location(start, start + sequence.len()),
+        );
+    }
+
+    /// Scans a `{- ... -}` block comment, including any comments nested in it.
+    fn scan_block_comment(&mut self) {
+        self.next();
+        let mut comment: String = "{-".to_string();
+        let location: SourceLocation = self.location(self.start, self.index);
+        let mut depth: usize = 1;
+        while depth > 0 {
+            match self.next() {
+                Some('{') if self.peek() == Some('-') => {
+                    self.next();
+                    comment.push_str("{-");
+                    depth += 1;
+                }
+                Some('-') if self.peek() == Some('}') => {
+                    self.next();
+                    comment.push_str("-}");
+                    depth -= 1;
+                }
+                Some(c) => comment.push(c),
+                None => {
+                    self.error(
+                        "Unterminated block comment".to_string(),
+                        Some("Close it with '-}'".to_string()),
+                        location,
+                    );
+                    return;
+                }
+            }
+        }
+        self.add_token(TokenKind::Comment, comment);
+    }
+
+    fn scan_digits(&mut self, number: &mut String) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit() || c == '_') {
+            number.push(self.next().unwrap());
+        }
+    }
+
+    /// Scans the rest of the innermost string literal, up to its closing quote
+    /// or the next `${`. String contents are kept as JavaScript source text, so
+    /// escape sequences are passed through and characters that are special in
+    /// template literals are escaped.
+    fn scan_string(&mut self) {
+        let (triple, raw) = {
+            let literal: &StringLiteral = self.strings.last().unwrap();
+            (literal.triple, literal.raw)
+        };
+        let mut string = String::new();
+        loop {
+            if self.is_at_end() {
+                let start: usize = self.strings.last().unwrap().start;
+                let quote: &str = if triple { "\"\"\"" } else { "\"" };
+                self.error(
+                    "Unterminated string".to_string(),
+                    Some(format!("Close it with '{}'", quote)),
+                    self.location(start, start + 1),
+                );
+                break;
+            }
+            if triple
+                && self.peek() == Some('"')
+                && self.peek_next() == Some('"')
+                && self.source[self.index..].chars().nth(2) == Some('"')
+            {
+                self.next();
+                self.next();
+                self.next();
+                break;
+            }
+            if !triple && self.peek() == Some('"') {
+                self.next();
+                break;
+            }
+            if !raw && self.peek() == Some('$') && self.peek_next() == Some('{') {
+                self.next();
+                self.next();
+                let literal: &mut StringLiteral = self.strings.last_mut().unwrap();
+                let kind: TokenKind = if literal.pieces.is_empty() {
+                    TokenKind::TemplateHead
+                } else {
+                    TokenKind::TemplateMiddle
+                };
+                literal.pieces.push(self.tokens.len());
+                self.add_token(kind, string);
+                return;
+            }
+            let c: char = self.next().unwrap();
+            match c {
+                '\\' if raw => string.push_str("\\\\"),
+                '$' if raw => string.push_str("\\$"),
+                '\\' => {
+                    let escape: usize = self.index - 1;
+                    match self.next() {
+                        Some('n') => string.push_str("\\n"),
+                        Some('t') => string.push_str("\\t"),
+                        Some('r') => string.push_str("\\r"),
+                        Some('\\') => string.push_str("\\\\"),
+                        Some('"') => string.push_str("\\\""),
+                        Some('\'') => string.push_str("'"),
+                        Some('0') => string.push_str("\\0"),
+                        Some('$') => string.push_str("\\$"),
+                        _ => self.invalid_escape(escape),
+                    }
+                }
+                '`' => string.push_str("\\`"),
+                _ => string.push(c),
+            }
+        }
+
+        let literal: StringLiteral = self.strings.pop().unwrap();
+        if literal.pieces.is_empty() && !triple && !raw {
+            self.add_token(TokenKind::StringLiteral, string);
+            return;
+        }
+        if triple {
+            let mut pieces: Vec<String> = literal
+                .pieces
+                .iter()
+                .map(|index| self.tokens[*index].lexeme.clone())
+                .collect();
+            pieces.push(string);
+            let mut pieces: Vec<String> = strip_indentation(&pieces);
+            string = pieces.pop().unwrap();
+            for (index, piece) in literal.pieces.iter().zip(pieces) {
+                self.tokens[*index].lexeme = piece;
+            }
+        }
+        if literal.pieces.is_empty() {
+            self.add_token(TokenKind::TemplateString, string);
         } else {
-            self.column += 1;
+            self.add_token(TokenKind::TemplateTail, string);
         }
-        c
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c: char = self.source[self.index..].chars().next()?;
+        self.index += c.len_utf8();
+        Some(c)
     }
 
     fn peek(&mut self) -> Option<char> {
-        self.source.chars().nth(self.index)
+        self.source[self.index..].chars().next()
+    }
+
+    fn peek_next(&mut self) -> Option<char> {
+        self.source[self.index..].chars().nth(1)
     }
 
     fn is_at_end(&self) -> bool {
@@ -315,12 +521,125 @@ impl Tokenizer {
         self.tokens.push(Token {
             kind: kind,
             lexeme: lexeme.clone(),
-            location: SourceLocation {
-                file: self.filename.clone(),
-                line: self.line,
-                column: self.column,
-                length: lexeme.len(),
-            },
+            location: self.location(self.start, self.index),
         });
     }
+
+    fn error(&mut self, message: String, hint: Option<String>, location: SourceLocation) {
+        self.errors.push(HarmonyError::new(
+            HarmonyErrorKind::Syntax,
+            message,
+            hint,
+            location,
+        ));
+    }
+
+    /// The location of the bytes from `start` up to `end`.
+    fn location(&self, start: usize, end: usize) -> SourceLocation {
+        let (line, column) = self.lines.position(start);
+        SourceLocation {
+            file: self.filename.clone(),
+            start,
+            end,
+            line: line + self.line - 1,
+            column,
+        }
+    }
+}
+
+/// Removes the blank first and last lines of a triple-quoted string and the
+/// indentation shared by its remaining lines. `pieces` are the literal parts
+/// around its interpolations, and a line continuing into an interpolation is
+/// never blank.
+fn strip_indentation(pieces: &[String]) -> Vec<String> {
+    const INTERPOLATION: char = '\u{E000}';
+    let joined: String = pieces.join(&INTERPOLATION.to_string());
+    let mut lines: Vec<&str> = joined.split('\n').collect();
+    let is_blank = |line: &str| line.chars().all(|c| c == ' ' || c == '\t' || c == '\r');
+    if lines.len() > 1 && is_blank(lines[0]) {
+        lines.remove(0);
+    }
+    if lines.len() > 1 && is_blank(lines[lines.len() - 1]) {
+        lines.pop();
+    }
+    let indentation: usize = lines
+        .iter()
+        .filter(|line| !is_blank(line))
+        .map(|line| line.chars().take_while(|c| *c == ' ' || *c == '\t').count())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| {
+            let blank: usize = line.chars().take_while(|c| *c == ' ' || *c == '\t').count();
+            line.chars()
+                .skip(blank.min(indentation))
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+        .split(INTERPOLATION)
+        .map(|piece| piece.to_string())
+        .collect()
+}
+
+/// The largest integer literal, which is the largest integer that JavaScript
+/// numbers, and so Harmony's `int`, hold exactly.
+pub const MAX_INTEGER: i64 = (1 << 53) - 1;
+
+/// Parses the lexeme of an `IntegerLiteral` token, returning the error message
+/// to report if it is malformed or larger than `MAX_INTEGER`.
+pub fn parse_integer_literal(lexeme: &str) -> Result<i64, String> {
+    let (radix, name, digits) = match lexeme.get(..2) {
+        Some("0x" | "0X") => (16, "hexadecimal", &lexeme[2..]),
+        Some("0o" | "0O") => (8, "octal", &lexeme[2..]),
+        Some("0b" | "0B") => (2, "binary", &lexeme[2..]),
+        _ => (10, "decimal", lexeme),
+    };
+    if digits.is_empty() {
+        return Err(format!("Expected {} digits after '{}'", name, &lexeme[..2]));
+    }
+    if let Some(digit) = digits.chars().find(|c| *c != '_' && !c.is_digit(radix)) {
+        return Err(format!(
+            "Invalid digit '{}' in {} literal '{}'",
+            digit, name, lexeme
+        ));
+    }
+    let digits: String = remove_separators(digits, lexeme)?;
+    match i64::from_str_radix(&digits, radix) {
+        Ok(value) if value <= MAX_INTEGER => Ok(value),
+        _ => Err(format!(
+            "Integer literal '{}' is larger than {}, the largest integer JavaScript represents exactly",
+            lexeme, MAX_INTEGER
+        )),
+    }
+}
+
+/// Parses the lexeme of a `FloatLiteral` token, returning the error message to
+/// report if it is malformed or not finite.
+pub fn parse_float_literal(lexeme: &str) -> Result<f64, String> {
+    if lexeme.ends_with(['e', 'E', '+', '-']) {
+        return Err(format!("Expected exponent digits after '{}'", lexeme));
+    }
+    // Separators are checked per digit run, so `1_.5` and `1e_5` are rejected.
+    for (i, run) in lexeme.split(['.', 'e', 'E', '+', '-']).enumerate() {
+        if i > 0 || !run.is_empty() {
+            remove_separators(run, lexeme)?;
+        }
+    }
+    match lexeme.replace('_', "").parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("Float literal '{}' is out of range", lexeme)),
+        Err(_) => Err(format!("Invalid float literal '{}'", lexeme)),
+    }
+}
+
+fn remove_separators(digits: &str, lexeme: &str) -> Result<String, String> {
+    if digits.starts_with('_') || digits.ends_with('_') || digits.contains("__") {
+        return Err(format!(
+            "Digit separators in '{}' must be placed between digits",
+            lexeme
+        ));
+    }
+    Ok(digits.replace('_', ""))
 }
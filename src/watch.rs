@@ -0,0 +1,95 @@
+use std::{
+    process::Child,
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    cache::{self, Cache},
+    compiler::{Compiler, CompilerOptions},
+};
+
+/// How often the watched files are checked for changes.
+const INTERVAL: Duration = Duration::from_millis(250);
+
+/// Builds a file again whenever it or a module it imports changes, for
+/// `harmony build --watch` and `harmony run --watch`.
+///
+/// Files are polled for changes to their modification time. Each build goes
+/// through the cache, so only the changed modules and the modules importing
+/// them are compiled again.
+pub struct Watcher {
+    pub options: CompilerOptions,
+    pub file: String,
+    /// The program's arguments.
+    pub args: Vec<String>,
+    /// Whether to run the program after every successful build, stopping the
+    /// previous run first.
+    pub run: bool,
+}
+
+impl Watcher {
+    pub fn new(options: CompilerOptions, file: String, args: Vec<String>, run: bool) -> Watcher {
+        Watcher {
+            options,
+            file,
+            args,
+            run,
+        }
+    }
+
+    /// Builds until the process is interrupted.
+    pub fn watch(&self) {
+        let mut program: Option<Child> = None;
+        loop {
+            eprint!("\x1b[2J\x1b[H");
+            let compiler: Compiler = self.build();
+            if !compiler.errors.is_empty() {
+                compiler.logger.normal("Build failed");
+            } else if self.run {
+                match compiler.spawn(&self.file) {
+                    Ok(child) => program = Some(child),
+                    Err(message) => compiler.logger.normal(message),
+                }
+            } else {
+                compiler.logger.normal(format!("Built {}", self.file));
+            }
+
+            compiler.logger.normal(format!(
+                "Watching {} files for changes..",
+                compiler.sources.len()
+            ));
+            let files: Vec<String> = compiler.sources;
+            let before: Vec<Option<SystemTime>> = modified(&files);
+            while modified(&files) == before {
+                std::thread::sleep(INTERVAL);
+            }
+            if let Some(mut child) = program.take() {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+        }
+    }
+
+    fn build(&self) -> Compiler {
+        let mut compiler: Compiler =
+            Compiler::new(&self.options, &vec![self.file.clone()], self.args.clone());
+        if !self.options.no_cache {
+            compiler.cache = Some(Cache::new(cache::DIRECTORY));
+        }
+        compiler.build();
+        compiler
+    }
+}
+
+/// When each of `files` was last modified, `None` for the ones that can't be
+/// read.
+fn modified(files: &[String]) -> Vec<Option<SystemTime>> {
+    files
+        .iter()
+        .map(|file| {
+            std::fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
@@ -0,0 +1,124 @@
+//! Tests for which modules the compiler takes from its cache after their
+//! sources or their imports change.
+
+use std::path::{Path, PathBuf};
+
+use harmony::{
+    cache::Cache,
+    checker::Scope,
+    compiler::{Compiler, CompilerOptions},
+    log::Level,
+    resolver::{FileResolver, MemoryFileSystem},
+};
+
+const ROOT: &str = "/project";
+
+const MAIN: &str = "module Main
+
+import Helpers as Helpers
+
+fun two() -> int = Helpers.helper (1)
+";
+
+const HELPERS: &str = "module Helpers
+
+fun helper(x: int) -> int = x + 1
+";
+
+/// A cache directory of its own for each test, removed when dropped.
+struct Directory(PathBuf);
+
+impl Directory {
+    fn new(test: &str) -> Directory {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("harmony-cache-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        Directory(path)
+    }
+}
+
+impl Drop for Directory {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Checks `Main.harm` against `helpers` with the cache in `directory`, and
+/// returns the compiler with the modules that came from the cache.
+fn check(directory: &Directory, helpers: &str) -> (Compiler, Vec<String>) {
+    let mut file_system: MemoryFileSystem = MemoryFileSystem::new();
+    file_system.insert(Path::new(ROOT).join("Main.harm"), MAIN);
+    file_system.insert(Path::new(ROOT).join("Helpers.harm"), helpers);
+    let mut options: CompilerOptions = CompilerOptions::new(vec![]);
+    options.level = Level::Silent;
+    let mut compiler: Compiler =
+        Compiler::new(&options, &vec![format!("{}/Main.harm", ROOT)], vec![]);
+    compiler.resolver = Box::new(FileResolver::new(Path::new(ROOT), file_system));
+    compiler.cache = Some(Cache::new(&directory.0));
+    compiler.check();
+    assert!(compiler.errors.is_empty(), "{:?}", compiler.errors);
+    let cached: Vec<String> = compiler
+        .timings
+        .iter()
+        .filter(|timing| timing.cached)
+        .map(|timing| timing.path.clone())
+        .collect();
+    (compiler, cached)
+}
+
+fn helper_line(compiler: &Compiler, file: &str) -> usize {
+    let scope: &Scope = &compiler.compiled_files[&format!("{}/{}", ROOT, file)];
+    scope.functions[&scope.function_names["helper"]]
+        .location
+        .line
+}
+
+#[test]
+fn unchanged_modules_are_cached() {
+    let directory: Directory = Directory::new("unchanged");
+    assert!(check(&directory, HELPERS).1.is_empty());
+    assert_eq!(
+        check(&directory, HELPERS).1,
+        ["/project/Helpers.harm", "/project/Main.harm"]
+    );
+}
+
+#[test]
+fn body_edits_keep_importers_cached() {
+    let directory: Directory = Directory::new("body");
+    check(&directory, HELPERS);
+    let edited: &str = "module Helpers
+
+--- Adds ten.
+fun helper(x: int) -> int = x + 10
+";
+    let (compiler, cached) = check(&directory, edited);
+    assert_eq!(cached, ["/project/Main.harm"]);
+    assert_eq!(helper_line(&compiler, "Helpers.harm"), 4);
+    assert_eq!(helper_line(&compiler, "Main.harm"), 4);
+}
+
+#[test]
+fn interface_edits_recompile_importers() {
+    let directory: Directory = Directory::new("interface");
+    check(&directory, HELPERS);
+    let edited: &str = "module Helpers
+
+fun helper(x: int, y: int = 1) -> int = x + y
+";
+    assert!(check(&directory, edited).1.is_empty());
+}
+
+#[test]
+fn other_versions_are_not_cached() {
+    let directory: Directory = Directory::new("version");
+    check(&directory, HELPERS);
+    for entry in std::fs::read_dir(&directory.0).unwrap() {
+        let path: PathBuf = entry.unwrap().path();
+        let contents: String = std::fs::read_to_string(&path).unwrap();
+        let version: String = format!("\"version\":\"{}\"", env!("CARGO_PKG_VERSION"));
+        assert!(contents.contains(&version));
+        std::fs::write(&path, contents.replace(&version, "\"version\":\"0.0.0\"")).unwrap();
+    }
+    assert!(check(&directory, HELPERS).1.is_empty());
+}
@@ -0,0 +1,77 @@
+//! Golden tests for `--dump`, which run `harmony` on each file in `tests/dump`
+//! and compare what every phase prints with the file next to it named after
+//! the phase.
+//!
+//! Run with `HARMONY_BLESS=1` to write the current outputs as the expected
+//! ones.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+const DIRECTORY: &str = "tests/dump";
+const BLESS: &str = "HARMONY_BLESS";
+const PHASES: [&str; 5] = ["tokens", "ast", "ast-json", "scope", "js"];
+
+fn dump(file: &Path, phase: &str) -> String {
+    let root: &str = env!("CARGO_MANIFEST_DIR");
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_harmony"))
+        .current_dir(root)
+        .arg(file)
+        .arg("--no-cache")
+        .arg(format!("--dump={}", phase))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "--dump={} {} failed:\n{}",
+        phase,
+        file.display(),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .replace(&root.replace('\\', "/"), "<root>")
+}
+
+fn dump_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(DIRECTORY)
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "harm")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn dumps() {
+    let bless: bool = std::env::var(BLESS).is_ok_and(|value| !value.is_empty() && value != "0");
+    let mut failures: Vec<String> = vec![];
+    for path in dump_files() {
+        for phase in PHASES {
+            let expected_path: PathBuf = path.with_extension(phase);
+            let actual: String = dump(&path, phase);
+            if bless {
+                std::fs::write(&expected_path, actual).unwrap();
+                continue;
+            }
+            match std::fs::read_to_string(&expected_path) {
+                Ok(expected) if expected == actual => {}
+                Ok(_) => failures.push(format!("{} differs", expected_path.display())),
+                Err(_) => failures.push(format!("{} is missing", expected_path.display())),
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun with {}=1 to accept the new outputs.",
+        failures.join("\n"),
+        BLESS
+    );
+}
@@ -0,0 +1,46 @@
+const Show$int = { show: (x) => String(x) };
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+export const Shape = {
+    Square: (value0) => ({
+        "Square0": value0,
+    }),
+    Rectangle: (value0, value1) => ({
+        "Rectangle0": value0,
+        "Rectangle1": value1,
+    }),
+};
+export const Square = Shape.Square;
+export const Rectangle = Shape.Rectangle;
+export const Show$Shape = {
+    show: (shape) => (() => {
+    const __condition = shape;
+    if (__condition.Square0 !== undefined) {
+        const side = __condition.Square0;
+        return `square ${side}`;
+    }
+    if (__condition.Rectangle0 !== undefined && __condition.Rectangle1 !== undefined) {
+        const width = __condition.Rectangle0;
+        const height = __condition.Rectangle1;
+        return `rectangle ${width}x${height}`;
+    }
+    throw new Error("Pattern match failed");
+})(),
+};
+export var area = (shape) => {
+    return (() => {
+    const __condition = shape;
+    if (__condition.Square0 !== undefined) {
+        const side = __condition.Square0;
+        return side * side;
+    }
+    if (__condition.Rectangle0 !== undefined && __condition.Rectangle1 !== undefined) {
+        const width = __condition.Rectangle0;
+        const height = __condition.Rectangle1;
+        return width * height;
+    }
+    throw new Error("Pattern match failed");
+})();
+}
+var main = (() => {
+    return Console.println(Show$Shape.show(Square(3)) + ": " + Show$int.show(area(Square(3))));
+})();
@@ -0,0 +1,250 @@
+//! Snapshot tests for the compiler. Every `.harm` file in `tests/golden` is
+//! tokenized, parsed, checked and compiled, and each stage's output is
+//! compared with the file next to it of the same name:
+//!
+//! - `.tokens`, one token per line
+//! - `.ast`, the parsed statements
+//! - `.diagnostics`, the errors and warnings
+//! - `.js`, the JavaScript `compile_source` generated, when there were no
+//!   errors
+//! - `.fmt`, what `harmony fmt` prints, when that isn't the file as written
+//!
+//! The parsed statements are also printed back to source, which has to parse
+//! to the same statements apart from their locations.
+//!
+//! Run with `HARMONY_BLESS=1` to write the current outputs as the expected
+//! ones. A `-- ERROR: <text>` comment expects an error containing `<text>` on
+//! its line, and every error needs such a comment.
+
+use std::path::{Path, PathBuf};
+
+use harmony::{
+    ast::Statement,
+    compiler::compile_source,
+    error::{HarmonyError, HarmonyErrorKind},
+    formatter::{Formatter, DEFAULT_WIDTH},
+    parser::Parser,
+    resolver::FileResolver,
+    token::{SourceLocation, Token, TokenKind},
+    tokenizer::Tokenizer,
+};
+
+const DIRECTORY: &str = "tests/golden";
+const BLESS: &str = "HARMONY_BLESS";
+
+/// What each stage produced for a file, keyed by the extension of the file
+/// it is compared with.
+struct Outputs {
+    outputs: Vec<(&'static str, String)>,
+    diagnostics: Vec<HarmonyError>,
+    statements: Option<Vec<Statement>>,
+}
+
+fn compile(file: &String, source: &String) -> Outputs {
+    let mut outputs: Vec<(&'static str, String)> = vec![];
+
+    let tokens: Vec<Token> = Tokenizer::new(file, source).tokenize();
+    outputs.push(("tokens", tokens.iter().map(token).collect()));
+    let statements: Option<Vec<Statement>> = parse(tokens);
+    if let Some(statements) = &statements {
+        outputs.push(("ast", format!("{:#?}\n", statements)));
+    }
+
+    let root: PathBuf = std::env::current_dir().unwrap();
+    let diagnostics: Vec<HarmonyError> =
+        match compile_source(file, source, FileResolver::on_disk(&root)) {
+            Ok(output) => {
+                let code: &String = &output.modules.last().unwrap().js;
+                let root: String = root.to_str().unwrap().replace('\\', "/");
+                outputs.push(("js", code.replace(&root, "<root>")));
+                output.warnings
+            }
+            Err(diagnostics) => diagnostics,
+        };
+    if let Ok(formatted) = Formatter::new(file, source, DEFAULT_WIDTH).format() {
+        if formatted != *source {
+            outputs.push(("fmt", formatted));
+        }
+    }
+    outputs.push((
+        "diagnostics",
+        diagnostics
+            .iter()
+            .map(|error| format!("{}\n", error.to_string()))
+            .collect(),
+    ));
+    Outputs {
+        outputs,
+        diagnostics,
+        statements,
+    }
+}
+
+fn parse(tokens: Vec<Token>) -> Option<Vec<Statement>> {
+    if tokens.iter().any(|token| token.kind == TokenKind::Unknown) {
+        return None;
+    }
+    Parser::new(tokens).parse().ok()
+}
+
+/// Prints `statements` back to source and parses it again, returning why
+/// that didn't give the same statements.
+fn check_round_trip(file: &String, statements: &[Statement]) -> Option<String> {
+    let printed: String = statements
+        .iter()
+        .map(|statement| statement.to_string())
+        .collect::<Vec<String>>()
+        .join("\n\n");
+    let Some(reparsed) = parse(Tokenizer::new(file, &printed).tokenize()) else {
+        return Some(format!(
+            "{} does not parse once printed:\n{}",
+            file, printed
+        ));
+    };
+    let expected: serde_json::Value =
+        SourceLocation::remove_from(serde_json::to_value(statements).unwrap());
+    let actual: serde_json::Value =
+        SourceLocation::remove_from(serde_json::to_value(&reparsed).unwrap());
+    if expected == actual {
+        return None;
+    }
+    Some(format!(
+        "{} parses differently once printed:\n{}",
+        file, printed
+    ))
+}
+
+fn token(token: &Token) -> String {
+    format!("{}\n", token)
+}
+
+/// Compares the `-- ERROR:` comments of `source` with the errors found in
+/// `file`, returning a line for each that has no counterpart.
+fn check_annotations(file: &str, source: &str, diagnostics: &[HarmonyError]) -> Vec<String> {
+    let mut mismatches: Vec<String> = vec![];
+    let errors: Vec<&HarmonyError> = diagnostics
+        .iter()
+        .filter(|error| !matches!(error.kind, HarmonyErrorKind::Warning))
+        .collect();
+    let mut annotated: Vec<usize> = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let Some((_, expected)) = line.split_once("-- ERROR:") else {
+            continue;
+        };
+        let expected: &str = expected.trim();
+        let line: usize = index + 1;
+        annotated.push(line);
+        let found: bool = errors.iter().any(|error| {
+            error.location.file == file
+                && error.location.line == line
+                && error.message.contains(expected)
+        });
+        if !found {
+            mismatches.push(format!(
+                "{}:{}: expected an error containing {:?}",
+                file, line, expected
+            ));
+        }
+    }
+    for error in errors {
+        if error.location.file != file || !annotated.contains(&error.location.line) {
+            mismatches.push(format!(
+                "unexpected error without an `-- ERROR:` comment: {}",
+                error.to_string()
+            ));
+        }
+    }
+    mismatches
+}
+
+/// The first line where `expected` and `actual` differ, for the failure
+/// message.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line: usize = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(expected), Some(actual)) if expected == actual => line += 1,
+            (expected, actual) => {
+                return format!(
+                    "line {}:\n    expected: {}\n      actual: {}",
+                    line,
+                    expected.unwrap_or("<end of file>"),
+                    actual.unwrap_or("<end of file>")
+                );
+            }
+        }
+    }
+}
+
+fn golden_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(DIRECTORY)
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "harm")
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn golden() {
+    let bless: bool = std::env::var(BLESS).is_ok_and(|value| !value.is_empty() && value != "0");
+    let mut failures: Vec<String> = vec![];
+    for path in golden_files() {
+        let file: String = path.to_str().unwrap().replace('\\', "/");
+        let source: String = std::fs::read_to_string(&path).unwrap();
+        let outputs: Outputs = compile(&file, &source);
+        failures.extend(check_annotations(&file, &source, &outputs.diagnostics));
+        if let Some(statements) = &outputs.statements {
+            failures.extend(check_round_trip(&file, statements));
+        }
+
+        for extension in ["tokens", "ast", "diagnostics", "js", "fmt"] {
+            let expected_path: PathBuf = path.with_extension(extension);
+            let actual: Option<&String> = outputs
+                .outputs
+                .iter()
+                .find(|(name, _)| *name == extension)
+                .map(|(_, output)| output);
+            if bless {
+                match actual {
+                    Some(actual) => std::fs::write(&expected_path, actual).unwrap(),
+                    None => {
+                        let _ = std::fs::remove_file(&expected_path);
+                    }
+                }
+                continue;
+            }
+            let expected: Option<String> = std::fs::read_to_string(&expected_path).ok();
+            match (expected, actual) {
+                (Some(expected), Some(actual)) if expected == *actual => {}
+                (Some(expected), Some(actual)) => failures.push(format!(
+                    "{} differs at {}",
+                    display(&expected_path),
+                    first_difference(&expected, actual)
+                )),
+                (None, Some(_)) => failures.push(format!("{} is missing", display(&expected_path))),
+                (Some(_), None) => {
+                    failures.push(format!("{} is no longer produced", display(&expected_path)))
+                }
+                (None, None) => {}
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{}\n\nRun with {}=1 to accept the new outputs.",
+        failures.join("\n"),
+        BLESS
+    );
+}
+
+fn display(path: &Path) -> String {
+    path.to_str().unwrap().replace('\\', "/")
+}
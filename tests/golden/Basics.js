@@ -0,0 +1,14 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+import * as List from "file:///<root>/runtime/Data/List.mjs";
+export var double = (x) => {
+    return x * 2;
+}
+export var factorial = (x) => {
+    return x === 0 ? 1 : x * factorial(x - 1);
+}
+export var sumSquares = (xs) => {
+    return List.foldl((x, acc) => x * x + acc, 0, xs);
+}
+var main = (() => {
+    return ((n) => Console.println(factorial(5) + sumSquares([n, 16, 1000])))(double(21));
+})();
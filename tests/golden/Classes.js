@@ -0,0 +1,40 @@
+const Eq$int = { eq: (a, b) => a === b };
+const Show$int = { show: (x) => String(x) };
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+export const Vector = {
+    Vec2: (value0, value1) => ({
+        "Vec20": value0,
+        "Vec21": value1,
+    }),
+};
+export const Vec2 = Vector.Vec2;
+export const Show$Vector = {
+    show: (v) => (() => {
+    const __condition = v;
+    if (__condition.Vec20 !== undefined && __condition.Vec21 !== undefined) {
+        const x = __condition.Vec20;
+        const y = __condition.Vec21;
+        return `(${x}, ${y})`;
+    }
+    throw new Error("Pattern match failed");
+})(),
+};
+export const Eq$Vector = {
+    eq: (a, b) => Show$Vector.show(a) === Show$Vector.show(b),
+};
+export var same = (__Eq_T, __Show_T, a, b) => {
+    return __Eq_T.eq(a, b) ? __Show_T.show(a) : "different";
+}
+export var older = (age) => {
+    return (() => {
+    const __condition = age;
+    if (true) {
+        const n = __condition;
+        return n + 1;
+    }
+    throw new Error("Pattern match failed");
+})();
+}
+var main = (() => {
+    return Console.println(same(Eq$Vector, Show$Vector, Vec2(1, 2), Vec2(1, 2)) + same(Eq$int, Show$int, 1, 2));
+})();
@@ -0,0 +1,17 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+import * as Greeting from "file:///<root>/tests/golden/Greeting.mjs";
+export var describe = (x) => {
+    return (() => {
+    const __condition = x;
+    if (__condition === 0) {
+        return "zero";
+    }
+    if (__condition === 1) {
+        return "one";
+    }
+    return "many";
+})();
+}
+var main = (() => {
+    return ((_) => Console.println(Greeting.greet("world")))(Console.println(describe(1)));
+})();
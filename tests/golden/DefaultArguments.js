@@ -0,0 +1,5 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+import * as Greeting from "file:///<root>/tests/golden/Greeting.mjs";
+var main = (() => {
+    return ((_) => ((_) => ((_) => Console.println(Greeting.greet("world", "Bye")))(Console.println(Greeting.greet("world", undefined, "?"))))(Console.println(Greeting.greet("world", "Hi"))))(Console.println(Greeting.greet("world")));
+})();
@@ -0,0 +1,7 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+export var clamp = (value, low = 0, high = low + 10) => {
+    return value < low ? low : value > high ? high : value;
+}
+var main = (() => {
+    return Console.println([clamp(50), clamp(50, 45), clamp(50, undefined, 20)]);
+})();
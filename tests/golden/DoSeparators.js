@@ -0,0 +1,4 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+var main = (() => {
+    return ((words) => ((_) => ((_) => ((_) => Console.println(words[2]))([words[1], words[2]]))(Console.println(words[0])))(Console.println("zero")))(["one", "two", "three"]);
+})();
@@ -0,0 +1,6 @@
+export var punctuation = () => {
+    return "!";
+}
+export var greet = (name, greeting = "Hello", ending = punctuation()) => {
+    return greeting + ", " + name + ending;
+}
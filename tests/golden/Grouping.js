@@ -0,0 +1,15 @@
+const Show$int = { show: (x) => String(x) };
+const Show$bool = { show: (x) => String(x) };
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+export var add = (a, b) => {
+    return a + b;
+}
+export var double = (x) => {
+    return x * 2;
+}
+export var subtract = (a, b) => {
+    return a - b;
+}
+var main = (() => {
+    return ((_) => ((_) => ((_) => ((_) => ((_) => ((_) => ((_) => ((_) => Console.println(Show$bool.show(!(1 === 2) && !false)))(Console.println(Show$int.show(-(1 + 2) * - -2))))(Console.println(Show$int.show(subtract(8, add(1, 2))))))(Console.println(Show$int.show(double(1 + 1) + 4 * (2 + 3)))))(Console.println(Show$bool.show(1 !== 2 && (false || true)))))(Console.println(Show$int.show((true ? 1 : 2) + 1))))(Console.println(Show$int.show(2 * (3 + 4) - (5 - 1) / 2))))(Console.println(Show$int.show(10 - (4 - 3)))))(Console.println(Show$int.show((1 + 2) * 3)));
+})();
@@ -0,0 +1,5 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+import * as String from "file:///<root>/runtime/Data/String.mjs";
+var main = (() => {
+    return ((split) => Console.println(split(",")))(String.split.bind(null, "a,b,c"));
+})();
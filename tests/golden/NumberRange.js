@@ -0,0 +1,4 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+var main = (() => {
+    return Console.println([4294967295, 9007199254740991, 5]);
+})();
@@ -0,0 +1,53 @@
+import { Maybe, Just, Nothing } from "file:///<root>/runtime/Data/Maybe.mjs";
+export const Shape = {
+    Circle: (value0) => ({
+        "Circle0": value0,
+    }),
+    Rect: (value0, value1) => ({
+        "Rect0": value0,
+        "Rect1": value1,
+    }),
+    Dot: { "Dot": "Dot" },
+};
+export const Circle = Shape.Circle;
+export const Rect = Shape.Rect;
+export const Dot = Shape.Dot;
+export var area = (shape) => {
+    return (() => {
+    const __condition = shape;
+    if (__condition.Circle0 !== undefined) {
+        const r = __condition.Circle0;
+        return 3.14 * r * r;
+    }
+    if (__condition.Rect0 !== undefined && __condition.Rect1 !== undefined) {
+        const w = __condition.Rect0;
+        const h = __condition.Rect1;
+        if (w === h) {
+        return w * w;
+    }
+    }
+    if (__condition.Rect0 !== undefined && __condition.Rect1 !== undefined) {
+        const w = __condition.Rect0;
+        const h = __condition.Rect1;
+        return w * h;
+    }
+    if (__condition.Dot !== undefined) {
+        return 0.0;
+    }
+    throw new Error("Pattern match failed");
+})();
+}
+export var first = (xs) => {
+    return (() => {
+    const __condition = xs;
+    if (__condition.length === 0) {
+        return Nothing;
+    }
+    if (__condition[0] !== undefined && __condition.length >= 1) {
+        const x = __condition[0];
+        const rest = __condition.slice(1);
+        return Just(x);
+    }
+    throw new Error("Pattern match failed");
+})();
+}
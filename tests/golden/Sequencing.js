@@ -0,0 +1,24 @@
+import * as Console from "file:///<root>/runtime/IO/Console.mjs";
+import { Maybe, Just, Nothing } from "file:///<root>/runtime/Data/Maybe.mjs";
+export var half = (x) => {
+    return x % 2 === 0 ? Just(x / 2) : Nothing;
+}
+export var quarter = (x) => {
+    return ((__do0) => (() => {
+    const __condition = __do0;
+    if (__condition.Just0 !== undefined) {
+        const y = __condition.Just0;
+        return ((z) => half(z))(y);
+    }
+    return __do0;
+})())(half(x));
+}
+export var add = (a, b = 10) => {
+    return a + b;
+}
+export var increment = (x) => {
+    return x + 1;
+}
+var main = (() => {
+    return ((twice) => Console.println((increment)(twice(add(1, 2)))))(((...__args) => (increment)((increment)(...__args))));
+})();
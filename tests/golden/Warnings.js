@@ -0,0 +1,3 @@
+export var one = () => {
+    return 1;
+}
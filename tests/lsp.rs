@@ -0,0 +1,221 @@
+//! Tests for `harmony lsp`, driving the server through the messages a client
+//! sends.
+
+use std::{
+    io::Write,
+    process::{Child, Command, Output, Stdio},
+};
+
+use harmony::lsp::LanguageServer;
+use serde_json::{json, Value};
+
+const URI: &str = "file:///tmp/harmony-lsp/Main.harm";
+
+fn open(server: &mut LanguageServer, text: &str) -> Vec<String> {
+    let replies: Vec<Value> = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didOpen",
+        "params": { "textDocument": { "uri": URI, "text": text } },
+    }));
+    messages(&replies)
+}
+
+fn change(server: &mut LanguageServer, text: &str) -> Vec<String> {
+    let replies: Vec<Value> = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/didChange",
+        "params": {
+            "textDocument": { "uri": URI },
+            "contentChanges": [{ "text": text }],
+        },
+    }));
+    messages(&replies)
+}
+
+/// The messages of the diagnostics published in `replies`.
+fn messages(replies: &[Value]) -> Vec<String> {
+    replies
+        .iter()
+        .flat_map(|reply| reply["params"]["diagnostics"].as_array().unwrap().clone())
+        .map(|diagnostic| diagnostic["message"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn half_typed_documents_are_diagnosed() {
+    let mut server: LanguageServer = LanguageServer::new();
+    let diagnostics: Vec<String> = open(&mut server, "module Main\n\nfun main = \"hello");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].starts_with("Unterminated string"));
+
+    let diagnostics: Vec<String> = change(&mut server, "module Main\n\nfun main = 'ab");
+    assert!(diagnostics[0].starts_with("Unterminated character literal"));
+
+    let diagnostics: Vec<String> = change(&mut server, "module Main\n\nfun main = !1");
+    assert_eq!(
+        diagnostics,
+        vec!["Unary operator '!' cannot be applied to type 'int'"]
+    );
+
+    let diagnostics: Vec<String> = change(&mut server, "module Main\n\nfun main() -> bool = !true");
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+}
+
+const SOURCE: &str = "module Main
+
+import IO.Console as Console
+
+--- Doubles `x`.
+fun double(x: int) -> int = x * 2
+
+fun main = Console.println (double (21))
+";
+
+fn request(server: &mut LanguageServer, method: &str, params: Value) -> Value {
+    let replies: Vec<Value> = server.handle(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    }));
+    assert_eq!(replies.len(), 1, "{:?}", replies);
+    replies[0].clone()
+}
+
+fn at(line: usize, character: usize) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+#[test]
+fn hover_shows_the_signature_and_doc() {
+    let mut server: LanguageServer = LanguageServer::new();
+    assert!(open(&mut server, SOURCE).is_empty());
+    let reply: Value = request(&mut server, "textDocument/hover", at(7, 30));
+    let hover: &str = reply["result"]["contents"]["value"].as_str().unwrap();
+    assert!(hover.contains("fun double(x: int) -> int"), "{}", hover);
+    assert!(hover.contains("Doubles `x`."), "{}", hover);
+}
+
+#[test]
+fn definition_points_at_the_declaration() {
+    let mut server: LanguageServer = LanguageServer::new();
+    assert!(open(&mut server, SOURCE).is_empty());
+    let reply: Value = request(&mut server, "textDocument/definition", at(7, 30));
+    assert_eq!(reply["result"]["uri"], URI);
+    assert_eq!(
+        reply["result"]["range"]["start"],
+        json!({ "line": 5, "character": 4 })
+    );
+}
+
+#[test]
+fn completion_lists_the_members_of_an_import() {
+    let mut server: LanguageServer = LanguageServer::new();
+    assert!(open(&mut server, SOURCE).is_empty());
+    change(&mut server, &SOURCE.replace("Console.println", "Console."));
+    let reply: Value = request(&mut server, "textDocument/completion", at(7, 19));
+    let labels: Vec<&str> = reply["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert!(labels.contains(&"println"), "{:?}", labels);
+
+    // A position past the end of the text completes nothing.
+    let reply: Value = request(&mut server, "textDocument/completion", at(40, 3));
+    assert_eq!(reply["result"], json!([]));
+}
+
+#[test]
+fn document_symbols_list_the_declarations() {
+    let mut server: LanguageServer = LanguageServer::new();
+    assert!(open(&mut server, SOURCE).is_empty());
+    let reply: Value = request(
+        &mut server,
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": URI } }),
+    );
+    let names: Vec<&str> = reply["result"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["double", "main"]);
+}
+
+#[test]
+fn rename_edits_a_parameter_and_its_uses() {
+    let mut server: LanguageServer = LanguageServer::new();
+    assert!(open(&mut server, SOURCE).is_empty());
+    let mut params: Value = at(5, 28);
+    params["newName"] = json!("n");
+    let reply: Value = request(&mut server, "textDocument/rename", params);
+    let edits: &Vec<Value> = reply["result"]["changes"][URI].as_array().unwrap();
+    let starts: Vec<&Value> = edits.iter().map(|edit| &edit["range"]["start"]).collect();
+    assert_eq!(
+        starts,
+        vec![
+            &json!({ "line": 5, "character": 11 }),
+            &json!({ "line": 5, "character": 28 })
+        ]
+    );
+    assert!(edits.iter().all(|edit| edit["newText"] == "n"));
+}
+
+/// Writes `message` framed by a `Content-Length` header, as clients do.
+fn frame(message: Value) -> String {
+    let body: String = message.to_string();
+    format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+#[test]
+fn messages_are_framed_over_stdio() {
+    let mut child: Child = Command::new(env!("CARGO_BIN_EXE_harmony"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let input: String = [
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }),
+        json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ]
+    .into_iter()
+    .map(frame)
+    .collect();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output: Output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let mut replies: Vec<Value> = vec![];
+    let mut rest: &str = std::str::from_utf8(&output.stdout).unwrap();
+    while let Some((header, after)) = rest.split_once("\r\n\r\n") {
+        let length: usize = header
+            .strip_prefix("Content-Length: ")
+            .unwrap()
+            .parse()
+            .unwrap();
+        replies.push(serde_json::from_str(&after[..length]).unwrap());
+        rest = &after[length..];
+    }
+    assert!(rest.is_empty(), "{:?}", rest);
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+    assert_eq!(
+        replies[1],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+}
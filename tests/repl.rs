@@ -0,0 +1,34 @@
+//! Tests for `harmony repl`, feeding inputs to a session the way its prompt
+//! does.
+
+use harmony::{compiler::CompilerOptions, error::HarmonyError, repl::Repl};
+
+fn error(repl: &mut Repl, input: &str) -> String {
+    let error: HarmonyError = repl.eval(input).unwrap_err();
+    error.message
+}
+
+#[test]
+fn errors_keep_the_session() {
+    let mut repl: Repl = Repl::new(&CompilerOptions::new(vec![])).unwrap();
+    repl.eval("fun increment(x: int) -> int = x + 1").unwrap();
+
+    assert_eq!(error(&mut repl, "\"abc"), "Unterminated string");
+    assert_eq!(
+        error(&mut repl, "'ab'"),
+        "Character literal must contain exactly one character"
+    );
+    assert_eq!(
+        error(&mut repl, "!1"),
+        "Unary operator '!' cannot be applied to type 'int'"
+    );
+    assert_eq!(
+        error(&mut repl, "fun broken(x: int) -> int = \"abc"),
+        "Unterminated string"
+    );
+
+    repl.eval(":type !true").unwrap();
+    repl.eval(":type increment (1)").unwrap();
+    repl.eval("fun decrement(x: int) -> int = increment (x) - 2")
+        .unwrap();
+}
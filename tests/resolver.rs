@@ -0,0 +1,106 @@
+//! Tests for how `FileResolver` finds imported modules, over in-memory and
+//! on-disk files.
+
+use std::path::{Path, PathBuf};
+
+use harmony::{
+    compiler::{compile_source, Compiler, CompilerOptions},
+    error::HarmonyError,
+    log::Level,
+    resolver::{DiskFileSystem, FileResolver, MemoryFileSystem, ModuleResolver},
+};
+
+const ROOT: &str = "/project";
+
+fn resolver(files: &[(&str, &str)]) -> FileResolver {
+    let mut file_system: MemoryFileSystem = MemoryFileSystem::new();
+    for (path, source) in files {
+        file_system.insert(Path::new(ROOT).join(path), *source);
+    }
+    FileResolver::new(Path::new(ROOT), file_system)
+}
+
+#[test]
+fn runtime_modules_come_before_siblings() {
+    let resolver: FileResolver = resolver(&[
+        ("runtime/Data/List.harm", "module Data.List"),
+        ("src/List.harm", "module List"),
+        ("src/Helpers.harm", "module Helpers"),
+    ]);
+    assert_eq!(
+        resolver.resolve("Data.List", "src/Main.harm").as_deref(),
+        Some("/project/runtime/Data/List.harm")
+    );
+    assert_eq!(
+        resolver.resolve("List", "src/Main.harm").as_deref(),
+        Some("/project/src/List.harm")
+    );
+    assert_eq!(
+        resolver.resolve("Helpers", "src/Main.harm").as_deref(),
+        Some("/project/src/Helpers.harm")
+    );
+}
+
+#[test]
+fn memory_files_shadow_the_disk() {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("harmony-resolver-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(directory.join("Saved.harm"), "module Saved -- on disk").unwrap();
+    std::fs::write(directory.join("Other.harm"), "module Other -- on disk").unwrap();
+
+    let mut file_system: MemoryFileSystem = MemoryFileSystem::over(DiskFileSystem);
+    file_system.insert(directory.join("Saved.harm"), "module Saved -- unsaved");
+    file_system.insert(directory.join("New.harm"), "module New -- unsaved");
+    let resolver: FileResolver = FileResolver::new(&directory, file_system);
+    let read = |name: &str| {
+        let path: String = resolver.resolve(name, "Main.harm").unwrap();
+        resolver.read(&path).unwrap()
+    };
+    let sources: [String; 3] = [read("Saved"), read("Other"), read("New")];
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(sources[0], "module Saved -- unsaved");
+    assert_eq!(sources[1], "module Other -- on disk");
+    assert_eq!(sources[2], "module New -- unsaved");
+}
+
+#[test]
+fn missing_modules_are_reported() {
+    let resolver: FileResolver = resolver(&[("src/Helpers.harm", "module Helpers")]);
+    assert_eq!(resolver.resolve("Missing", "src/Main.harm"), None);
+
+    let source: &str = "module Main\n\nimport Missing as Missing\n";
+    let errors: Vec<HarmonyError> = compile_source("src/Main.harm", source, resolver).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].message, "Module Missing not found");
+    assert_eq!(errors[0].location.line, 3);
+}
+
+#[test]
+fn the_compiler_resolves_from_the_root_in_its_options() {
+    let directory: PathBuf =
+        std::env::temp_dir().join(format!("harmony-root-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("Main.harm"),
+        "module Main\n\nimport Helpers as Helpers\n",
+    )
+    .unwrap();
+    std::fs::write(directory.join("Helpers.harm"), "module Helpers").unwrap();
+
+    let mut options: CompilerOptions = CompilerOptions::new(vec![]);
+    options.level = Level::Silent;
+    options.root = directory.clone();
+    let mut compiler: Compiler = Compiler::new(&options, &vec!["Main.harm".to_string()], vec![]);
+    compiler.check();
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(compiler.errors.is_empty(), "{:?}", compiler.errors);
+    let helpers: String = directory.join("Helpers.harm").display().to_string();
+    assert!(
+        compiler.compiled_files.contains_key(&helpers),
+        "{:?}",
+        compiler.compiled_files.keys()
+    );
+}